@@ -1,13 +1,17 @@
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::error::Error;
+use core::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
 
 use p3_field::{ExtensionField, PrimeField32};
 use p3_interaction::{Interaction, InteractionType};
-use p3_matrix::dense::RowMajorMatrixView;
+use p3_matrix::{dense::RowMajorMatrixView, Matrix};
 use rust_xlsxwriter::Worksheet;
+use serde_json::{Map, Value};
 
 use crate::debug::rap::write_traces_to_worksheet;
 use crate::folders::EntriesLog;
@@ -51,3 +55,199 @@ pub trait AirLogger {
         )
     }
 }
+
+/// Errors from writing traces, see [`write_trace_csv`] and [`write_trace_json`].
+#[derive(Debug)]
+pub enum TraceWriteError {
+    /// The number of headers differs from the width of the trace.
+    WidthMismatch {
+        headers: usize,
+        width: usize,
+    },
+    Io(io::Error),
+}
+
+impl Display for TraceWriteError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::WidthMismatch { headers, width } => write!(
+                f,
+                "{} headers don't match a trace of width {}",
+                headers, width
+            ),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for TraceWriteError {}
+
+impl From<io::Error> for TraceWriteError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Writes the main trace of `air` as CSV, with a header row of [`AirLogger::main_headers`]
+/// followed by one line per row.
+pub fn write_trace_csv<F, A, W>(
+    air: &A,
+    trace: &impl Matrix<F>,
+    writer: W,
+) -> Result<(), TraceWriteError>
+where
+    F: Display + Clone + Send + Sync,
+    A: AirLogger + ?Sized,
+    W: Write,
+{
+    write_csv(air.main_headers(), trace, writer)
+}
+
+/// Writes the preprocessed trace of `air` as CSV, with a header row of
+/// [`AirLogger::preprocessed_headers`] followed by one line per row.
+pub fn write_preprocessed_trace_csv<F, A, W>(
+    air: &A,
+    trace: &impl Matrix<F>,
+    writer: W,
+) -> Result<(), TraceWriteError>
+where
+    F: Display + Clone + Send + Sync,
+    A: AirLogger + ?Sized,
+    W: Write,
+{
+    write_csv(air.preprocessed_headers(), trace, writer)
+}
+
+/// Writes the traces of `air` as a JSON object with a `preprocessed`, `main` and `permutation`
+/// section for each trace that is present. A section is an array with one object per row, keyed by
+/// [`AirLogger::preprocessed_headers`] and [`AirLogger::main_headers`] respectively. Permutation
+/// columns are keyed `permutation[i]`, except for the last one, `running_sum`, and hold the
+/// coefficients of their extension field elements.
+pub fn write_trace_json<F, EF, A, W>(
+    air: &A,
+    preprocessed_trace: &Option<RowMajorMatrixView<F>>,
+    main_trace: &Option<RowMajorMatrixView<F>>,
+    permutation_trace: &Option<RowMajorMatrixView<EF>>,
+    mut writer: W,
+) -> Result<(), TraceWriteError>
+where
+    F: PrimeField32,
+    EF: ExtensionField<F>,
+    A: AirLogger + ?Sized,
+    W: Write,
+{
+    let mut sections = Map::new();
+    if let Some(trace) = preprocessed_trace {
+        let rows = json_rows(air.preprocessed_headers(), trace, |value: F| {
+            value.as_canonical_u32().into()
+        })?;
+        sections.insert("preprocessed".into(), rows);
+    }
+    if let Some(trace) = main_trace {
+        let rows = json_rows(air.main_headers(), trace, |value: F| {
+            value.as_canonical_u32().into()
+        })?;
+        sections.insert("main".into(), rows);
+    }
+    if let Some(trace) = permutation_trace {
+        let width = trace.width();
+        let headers = (0..width)
+            .map(|i| {
+                if i == width - 1 {
+                    "running_sum".into()
+                } else {
+                    format!("permutation[{}]", i)
+                }
+            })
+            .collect();
+        let rows = json_rows(headers, trace, |value: EF| {
+            value
+                .as_base_slice()
+                .iter()
+                .map(|coeff| coeff.as_canonical_u32())
+                .collect::<Vec<_>>()
+                .into()
+        })?;
+        sections.insert("permutation".into(), rows);
+    }
+
+    serde_json::to_writer(&mut writer, &sections).map_err(io::Error::from)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn json_rows<T>(
+    headers: Vec<String>,
+    trace: &impl Matrix<T>,
+    to_json: impl Fn(T) -> Value,
+) -> Result<Value, TraceWriteError>
+where
+    T: Clone + Send + Sync,
+{
+    if headers.len() != trace.width() {
+        return Err(TraceWriteError::WidthMismatch {
+            headers: headers.len(),
+            width: trace.width(),
+        });
+    }
+
+    let rows = trace
+        .rows()
+        .map(|row| {
+            let row = headers
+                .iter()
+                .cloned()
+                .zip(row.map(&to_json))
+                .collect::<Map<_, _>>();
+            Value::Object(row)
+        })
+        .collect();
+    Ok(Value::Array(rows))
+}
+
+fn write_csv<F, W>(
+    headers: Vec<String>,
+    trace: &impl Matrix<F>,
+    mut writer: W,
+) -> Result<(), TraceWriteError>
+where
+    F: Display + Clone + Send + Sync,
+    W: Write,
+{
+    if headers.len() != trace.width() {
+        return Err(TraceWriteError::WidthMismatch {
+            headers: headers.len(),
+            width: trace.width(),
+        });
+    }
+
+    let headers = headers.iter().map(|header| escape_csv(header));
+    write_csv_line(&mut writer, headers)?;
+    for row in trace.rows() {
+        write_csv_line(&mut writer, row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_csv_line<W: Write>(
+    writer: &mut W,
+    fields: impl Iterator<Item = impl Display>,
+) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "{}", field)?;
+    }
+    writeln!(writer)
+}
+
+/// Quotes `field` if it contains a separator, a quote or a line break.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
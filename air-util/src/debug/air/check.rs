@@ -1,12 +1,8 @@
-use alloc::vec;
-
 use p3_air::Air;
 use p3_field::Field;
 use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
-use p3_matrix::Matrix;
-use p3_maybe_rayon::prelude::IntoParallelIterator;
 
+use crate::debug::rows::for_each_row;
 use crate::folders::air::DebugConstraintBuilder;
 
 /// Check that all constraints vanish on the subgroup.
@@ -19,54 +15,17 @@ pub fn check_constraints<F, A>(
     F: Field,
     A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
 {
-    let height = match (main.as_ref(), preprocessed.as_ref()) {
-        (Some(main), Some(preprocessed)) => core::cmp::max(main.height(), preprocessed.height()),
-        (Some(main), None) => main.height(),
-        (None, Some(preprocessed)) => preprocessed.height(),
-        (None, None) => 0,
-    };
-
-    // Check that constraints are satisfied.
-    (0..height).into_par_iter().for_each(|i| {
-        let i_next = (i + 1) % height;
-
-        let (preprocessed_local, preprocessed_next) = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                (
-                    preprocessed.row_slice(i).to_vec(),
-                    preprocessed.row_slice(i_next).to_vec(),
-                )
-            })
-            .unwrap_or((vec![], vec![]));
-        let (main_local, main_next) = main
-            .as_ref()
-            .map(|main| (main.row_slice(i).to_vec(), main.row_slice(i_next).to_vec()))
-            .unwrap_or((vec![], vec![]));
-
+    for_each_row(preprocessed, main, &[], |row| {
         let mut builder = DebugConstraintBuilder {
-            row_index: i,
-            preprocessed: VerticalPair::new(
-                RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
-                RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
-            ),
-            main: VerticalPair::new(
-                RowMajorMatrixView::new_row(&*main_local),
-                RowMajorMatrixView::new_row(&*main_next),
-            ),
+            row_index: row.row_index,
+            preprocessed: row.preprocessed(),
+            main: row.main(),
             public_values,
-            is_first_row: F::zero(),
-            is_last_row: F::zero(),
-            is_transition: F::one(),
+            is_first_row: row.is_first_row,
+            is_last_row: row.is_last_row,
+            is_transition: row.is_transition,
+            rows_after: row.rows_after,
         };
-        if i == 0 {
-            builder.is_first_row = F::one();
-        }
-        if i == height - 1 {
-            builder.is_last_row = F::one();
-            builder.is_transition = F::zero();
-        }
-
         air.eval(&mut builder);
     });
 }
@@ -108,6 +108,7 @@ where
             is_first_row: F::zero(),
             is_last_row: F::zero(),
             is_transition: F::one(),
+            rows_after: height - 1 - i,
         };
         if i == 0 {
             builder.is_first_row = F::one();
@@ -1,5 +1,6 @@
 pub mod air;
 pub mod rap;
+mod rows;
 
 #[cfg(feature = "air-logger")]
 use rust_xlsxwriter::Format;
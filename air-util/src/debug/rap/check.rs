@@ -2,21 +2,27 @@ use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
 
+use hashbrown::HashMap;
 use p3_field::{ExtensionField, Field};
-use p3_interaction::{Bus, InteractionType, Rap, NUM_PERM_CHALLENGES};
-use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
+use p3_interaction::{
+    interaction_batches, logup_groups, trace_height, Bus, InteractionAir, InteractionType,
+    PermutationBackendKind, Rap, NUM_PERM_CHALLENGES,
+};
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
 use p3_matrix::Matrix;
-use p3_maybe_rayon::prelude::IntoParallelIterator;
+use p3_maybe_rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
-use crate::folders::rap::DebugConstraintBuilder;
+use crate::debug::rows::{for_each_row, local_and_next, RowSelectors, RowWindow};
+use crate::folders::{rap::DebugConstraintBuilder, window};
 
 /// Check that all constraints vanish on the subgroup.
 pub fn check_constraints<F, EF, A>(
     air: &A,
     preprocessed: &Option<RowMajorMatrixView<F>>,
     main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
     perm: &Option<RowMajorMatrixView<EF>>,
     perm_challenges: [EF; NUM_PERM_CHALLENGES],
     cumulative_sum: Option<EF>,
@@ -26,73 +32,303 @@ pub fn check_constraints<F, EF, A>(
     EF: ExtensionField<F>,
     A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
 {
-    let height = match (main.as_ref(), preprocessed.as_ref()) {
-        (Some(main), Some(preprocessed)) => core::cmp::max(main.height(), preprocessed.height()),
-        (Some(main), None) => main.height(),
-        (None, Some(preprocessed)) => preprocessed.height(),
-        (None, None) => 0,
-    };
-
-    if let Some(perm) = perm {
-        assert_eq!(perm.height(), height);
+    let height = trace_height(preprocessed, main);
+    for trace in main_ext.iter().chain(perm) {
+        assert_eq!(trace.height(), height);
     }
 
     // Check that constraints are satisfied.
-    (0..height).into_par_iter().for_each(|i| {
-        let i_next = (i + 1) % height;
-
-        let (preprocessed_local, preprocessed_next) = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                (
-                    preprocessed.row_slice(i).to_vec(),
-                    preprocessed.row_slice(i_next).to_vec(),
-                )
-            })
-            .unwrap_or((vec![], vec![]));
-        let (main_local, main_next) = main
-            .as_ref()
-            .map(|main| (main.row_slice(i).to_vec(), main.row_slice(i_next).to_vec()))
-            .unwrap_or((vec![], vec![]));
-        let (perm_local, perm_next) = perm
-            .as_ref()
-            .map(|perm| (perm.row_slice(i).to_vec(), perm.row_slice(i_next).to_vec()))
-            .unwrap_or((vec![], vec![]));
-
-        let mut builder = DebugConstraintBuilder {
-            row_index: i,
-            preprocessed: VerticalPair::new(
-                RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
-                RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
-            ),
-            main: VerticalPair::new(
-                RowMajorMatrixView::new_row(&*main_local),
-                RowMajorMatrixView::new_row(&*main_next),
-            ),
-            permutation: VerticalPair::new(
-                RowMajorMatrixView::new_row(perm_local.as_slice()),
-                RowMajorMatrixView::new_row(perm_next.as_slice()),
-            ),
+    for_each_row(preprocessed, main, &air.main_rotations(), |row| {
+        eval_window(
+            air,
+            row,
+            main_ext,
+            perm,
             perm_challenges,
+            cumulative_sum,
             public_values,
-            cumulative_sum: cumulative_sum.unwrap_or_default(),
-            is_first_row: F::zero(),
-            is_last_row: F::zero(),
-            is_transition: F::one(),
-        };
-        if i == 0 {
-            builder.is_first_row = F::one();
-        }
-        if i == height - 1 {
-            builder.is_last_row = F::one();
-            builder.is_transition = F::zero();
-        }
-
-        air.eval_all(&mut builder);
+            None,
+        );
     });
 }
 
-// TODO: Check number of virtual columns in bus are same
+/// Evaluate all constraints on every row without asserting, returning a matrix with one row per
+/// trace row and one column per constraint, in the order in which they are emitted.
+pub fn collect_constraint_values<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    perm: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+) -> RowMajorMatrix<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let height = trace_height(preprocessed, main);
+
+    for trace in main_ext.iter().chain(perm) {
+        assert_eq!(trace.height(), height);
+    }
+
+    let rows = (0..height)
+        .into_par_iter()
+        .map(|i| {
+            let selectors = RowSelectors::of_row(i, height);
+            eval_row(
+                air,
+                preprocessed,
+                main,
+                main_ext,
+                perm,
+                perm_challenges,
+                cumulative_sum,
+                public_values,
+                i,
+                selectors,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let width = rows.first().map_or(0, |row| row.len());
+    debug_assert!(
+        rows.iter().all(|row| row.len() == width),
+        "number of constraints should be the same on every row"
+    );
+
+    RowMajorMatrix::new(rows.into_iter().flatten().collect(), width)
+}
+
+/// Which rows a constraint applies to, according to the selector it's gated by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// The constraint isn't gated by a selector.
+    EveryRow,
+    FirstRow,
+    LastRow,
+    Transition,
+}
+
+/// The first constraint that doesn't vanish, see [`try_check_constraints`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintError {
+    pub row: usize,
+    /// The index of the constraint, in the order in which the constraints are emitted.
+    pub constraint: usize,
+    pub kind: ConstraintKind,
+}
+
+impl Display for ConstraintError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} constraint {} doesn't vanish on row {}",
+            self.kind, self.constraint, self.row
+        )
+    }
+}
+
+impl core::error::Error for ConstraintError {}
+
+/// Like [`check_constraints`], but returns the first constraint that doesn't vanish, on the lowest
+/// row, instead of panicking.
+pub fn try_check_constraints<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    perm: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+) -> Result<(), ConstraintError>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let values = collect_constraint_values(
+        air,
+        preprocessed,
+        main,
+        main_ext,
+        perm,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+    );
+    check_constraint_values(
+        air,
+        preprocessed,
+        main,
+        main_ext,
+        perm,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+        &values,
+    )
+}
+
+/// Like [`try_check_constraints`], on the `values` that [`collect_constraint_values`] returned for
+/// the same traces, so callers that inspect the values themselves only evaluate them once.
+#[allow(clippy::too_many_arguments)]
+pub fn check_constraint_values<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    perm: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+    values: &RowMajorMatrix<EF>,
+) -> Result<(), ConstraintError>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let Some((row, constraint)) = values.rows().enumerate().find_map(|(row, mut values)| {
+        values
+            .position(|value| !value.is_zero())
+            .map(|constraint| (row, constraint))
+    }) else {
+        return Ok(());
+    };
+
+    // The selectors only scale the constraints they gate, so the kind of the failing constraint
+    // is the one whose selector alone keeps it from vanishing
+    let fails_with = |selectors| {
+        let values = eval_row(
+            air,
+            preprocessed,
+            main,
+            main_ext,
+            perm,
+            perm_challenges,
+            cumulative_sum,
+            public_values,
+            row,
+            selectors,
+        );
+        !values[constraint].is_zero()
+    };
+    let none = RowSelectors::default();
+    let kind = if fails_with(none) {
+        ConstraintKind::EveryRow
+    } else if fails_with(RowSelectors {
+        is_first_row: true,
+        ..none
+    }) {
+        ConstraintKind::FirstRow
+    } else if fails_with(RowSelectors {
+        is_last_row: true,
+        ..none
+    }) {
+        ConstraintKind::LastRow
+    } else {
+        ConstraintKind::Transition
+    };
+
+    Err(ConstraintError {
+        row,
+        constraint,
+        kind,
+    })
+}
+
+/// Evaluates all constraints on row `i` with the given selectors, returning their values.
+#[allow(clippy::too_many_arguments)]
+fn eval_row<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    perm: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+    i: usize,
+    selectors: RowSelectors,
+) -> Vec<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let row = RowWindow::new(preprocessed, main, &air.main_rotations(), i, selectors);
+    eval_window(
+        air,
+        row,
+        main_ext,
+        perm,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+        Some(vec![]),
+    )
+    .unwrap_or_default()
+}
+
+/// Evaluates all constraints on `row`, asserting that they vanish unless `constraint_values` is
+/// set, in which case their values are appended to it and returned.
+fn eval_window<F, EF, A>(
+    air: &A,
+    row: RowWindow<F>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    perm: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+    constraint_values: Option<Vec<EF>>,
+) -> Option<Vec<EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let (main_ext_local, main_ext_next) = local_and_next(main_ext, row.row_index, &[]);
+    let (perm_local, perm_next) = local_and_next(perm, row.row_index, &[]);
+    let mut builder = DebugConstraintBuilder {
+        row_index: row.row_index,
+        preprocessed: row.preprocessed(),
+        main: row.main(),
+        main_ext: window(&main_ext_local, &main_ext_next),
+        permutation: window(&perm_local, &perm_next),
+        perm_challenges,
+        public_values,
+        cumulative_sum: cumulative_sum.unwrap_or_default(),
+        is_first_row: row.is_first_row,
+        is_last_row: row.is_last_row,
+        is_transition: row.is_transition,
+        rows_after: row.rows_after,
+        constraint_values,
+    };
+
+    air.eval_all(&mut builder);
+    builder.constraint_values
+}
+
+/// The sums of the permutation traces of a set of airs, see [`compute_cumulative_sums`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CumulativeSums<EF> {
+    /// The sum of every bus the airs interact on, including the buses that balance.
+    pub buses: BTreeMap<usize, EF>,
+    /// The sum of the cumulative sums on the last rows of the permutation traces.
+    pub total: EF,
+}
+
+/// Check that every bus is balanced across all airs. The balance is checked per bus rather than
+/// per air, so an air may only send (a source) or only receive (a sink) on a bus as long as other
+/// airs provide the matching entries.
+///
+/// All interactions on a bus must also have tuples of the same width. Otherwise the lookup is
+/// malformed, even if the sums happen to cancel.
 pub fn check_cumulative_sums<F, EF, A, B>(
     airs: &[A],
     preprocessed: &[Option<RowMajorMatrixView<F>>],
@@ -104,31 +340,105 @@ pub fn check_cumulative_sums<F, EF, A, B>(
     A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
     B: Bus,
 {
-    let mut sums = BTreeMap::new();
-    for (i, air) in airs.iter().enumerate() {
-        for (j, (interaction, interaction_type)) in air.all_interactions().iter().enumerate() {
-            if let Some(permutation) = permutation[i].as_ref() {
-                for (n, perm_row) in permutation.rows().enumerate() {
-                    let preprocessed_row = preprocessed[i]
-                        .as_ref()
-                        .map(|preprocessed| {
-                            let row = preprocessed.row_slice(n);
-                            let row: &[_] = (*row).borrow();
-                            row.to_vec()
-                        })
-                        .unwrap_or_default();
-                    let main_row = main[i]
-                        .as_ref()
-                        .map(|main| {
-                            let row = main.row_slice(n);
-                            let row: &[_] = (*row).borrow();
-                            row.to_vec()
-                        })
-                        .unwrap_or_default();
-                    let perm_row: Vec<_> = perm_row.collect();
+    // Bus index -> number of fields of the first interaction on the bus
+    let mut widths: BTreeMap<usize, usize> = BTreeMap::new();
+    for air in airs.iter() {
+        for (interaction, _) in air.all_interactions().iter() {
+            let width = interaction.num_fields();
+            let expected = *widths.entry(interaction.argument_index).or_insert(width);
+            assert_eq!(
+                expected,
+                width,
+                "{} bus has interactions with {} and {} fields",
+                B::from(interaction.argument_index),
+                expected,
+                width
+            );
+        }
+    }
+
+    let sums = compute_cumulative_sums(airs, preprocessed, main, permutation);
+    for (i, sum) in sums.buses {
+        assert_eq!(
+            sum,
+            EF::zero(),
+            "{} bus cumulative sum is not zero",
+            B::from(i)
+        );
+    }
+    assert_eq!(sums.total, EF::zero());
+}
+
+/// Sums up the contributions of all airs to every bus, and their cumulative sums. The airs are
+/// summed up in parallel.
+pub fn compute_cumulative_sums<F, EF, A>(
+    airs: &[A],
+    preprocessed: &[Option<RowMajorMatrixView<F>>],
+    main: &[Option<RowMajorMatrixView<F>>],
+    permutation: &[Option<RowMajorMatrixView<EF>>],
+) -> CumulativeSums<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<DebugConstraintBuilder<'a, F, EF>>,
+{
+    // The partial sums of each air are computed in parallel, and then added up in air order
+    let partial_sums = (0..airs.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut sums = BTreeMap::new();
+            let Some(permutation) = permutation[i].as_ref() else {
+                return sums;
+            };
+            let interactions = airs[i].all_interactions();
+            // Batched and LogUp columns already hold the multiplicities, and each of them is on a
+            // single bus
+            let column_buses = match airs[i].permutation_backend() {
+                PermutationBackendKind::Reciprocal => airs[i]
+                    .max_permutation_constraint_degree()
+                    .map(|max_degree| {
+                        interaction_batches(&interactions, Some(max_degree))
+                            .into_iter()
+                            .map(|batch| interactions[batch.start].0.argument_index)
+                            .collect::<Vec<_>>()
+                    }),
+                PermutationBackendKind::LogUp => Some(
+                    logup_groups(&interactions)
+                        .into_iter()
+                        .map(|group| interactions[group[0]].0.argument_index)
+                        .collect(),
+                ),
+            };
+            for (n, perm_row) in permutation.rows().enumerate() {
+                let perm_row: Vec<_> = perm_row.collect();
+                if let Some(column_buses) = &column_buses {
+                    for (column, &bus) in column_buses.iter().enumerate() {
+                        sums.entry(bus)
+                            .and_modify(|c| *c += perm_row[column])
+                            .or_insert(perm_row[column]);
+                    }
+                    continue;
+                }
+
+                let preprocessed_row = preprocessed[i]
+                    .as_ref()
+                    .map(|preprocessed| {
+                        let row = preprocessed.row_slice(n);
+                        let row: &[_] = (*row).borrow();
+                        row.to_vec()
+                    })
+                    .unwrap_or_default();
+                let main_row = main[i]
+                    .as_ref()
+                    .map(|main| {
+                        let row = main.row_slice(n);
+                        let row: &[_] = (*row).borrow();
+                        row.to_vec()
+                    })
+                    .unwrap_or_default();
+                for (j, (interaction, interaction_type)) in interactions.iter().enumerate() {
                     let mult = interaction
-                        .count
-                        .apply::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
+                        .multiplicity::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
                     let val = match interaction_type {
                         InteractionType::Send => perm_row[j] * mult,
                         InteractionType::Receive => -perm_row[j] * mult,
@@ -138,22 +448,165 @@ pub fn check_cumulative_sums<F, EF, A, B>(
                         .or_insert(val);
                 }
             }
-        }
-    }
-    for (i, sum) in sums {
-        assert_eq!(
-            sum,
-            EF::zero(),
-            "{} bus cumulative sum is not zero",
-            B::from(i)
-        );
+            sums
+        })
+        .collect::<Vec<_>>();
+
+    let mut sums = BTreeMap::new();
+    for (bus, sum) in partial_sums.into_iter().flatten() {
+        sums.entry(bus)
+            .and_modify(|c: &mut EF| *c += sum)
+            .or_insert(sum);
     }
 
-    // Check cumulative sums
-    let sum: EF = permutation
+    let total = permutation
         .iter()
         .flatten()
         .map(|perm| *perm.row_slice(perm.height() - 1).last().unwrap())
         .sum();
-    assert_eq!(sum, EF::zero());
+
+    CumulativeSums { buses: sums, total }
+}
+
+/// Check that the total multiplicity sent on every bus equals the total multiplicity received.
+/// This is necessary for the bus to balance and, unlike the cumulative sums, doesn't depend on
+/// the permutation challenges.
+pub fn check_bus_counts<F, A, B>(
+    airs: &[A],
+    preprocessed: &[Option<RowMajorMatrixView<F>>],
+    main: &[Option<RowMajorMatrixView<F>>],
+) where
+    F: Field,
+    A: InteractionAir<F>,
+    B: Bus,
+{
+    // Bus index -> (sent, received)
+    let mut counts: BTreeMap<usize, (F, F)> = BTreeMap::new();
+    for (i, air) in airs.iter().enumerate() {
+        let interactions = air.all_interactions();
+        if interactions.is_empty() {
+            continue;
+        }
+        let height = trace_height(&preprocessed[i], &main[i]);
+        for n in 0..height {
+            let preprocessed_row = preprocessed[i]
+                .as_ref()
+                .map(|preprocessed| {
+                    let row = preprocessed.row_slice(n);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+            let main_row = main[i]
+                .as_ref()
+                .map(|main| {
+                    let row = main.row_slice(n);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+            for (interaction, interaction_type) in interactions.iter() {
+                let mult = interaction
+                    .multiplicity::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
+                let (sent, received) = counts
+                    .entry(interaction.argument_index)
+                    .or_insert((F::zero(), F::zero()));
+                match interaction_type {
+                    InteractionType::Send => *sent += mult,
+                    InteractionType::Receive => *received += mult,
+                }
+            }
+        }
+    }
+    for (i, (sent, received)) in counts {
+        assert_eq!(
+            sent,
+            received,
+            "{} bus sends {} but receives {}",
+            B::from(i),
+            sent,
+            received
+        );
+    }
+}
+
+/// Check that every tuple sent on a bus is received with the same total multiplicity. This
+/// catches the same imbalances as [`check_cumulative_sums`], but names the offending tuple and
+/// doesn't need the permutation traces or challenges. `public_values` holds the public values of
+/// each air, which the tuples of its interactions may reference.
+pub fn check_lookups<F, A, B>(
+    airs: &[A],
+    preprocessed: &[Option<RowMajorMatrixView<F>>],
+    main: &[Option<RowMajorMatrixView<F>>],
+    public_values: &[&[F]],
+) where
+    F: Field,
+    A: InteractionAir<F>,
+    B: Bus,
+{
+    // Bus index -> tuple -> sent minus received multiplicity
+    let mut counts: BTreeMap<usize, HashMap<Vec<F>, F>> = BTreeMap::new();
+    for (i, air) in airs.iter().enumerate() {
+        let interactions = air.all_interactions();
+        if interactions.is_empty() {
+            continue;
+        }
+        let height = trace_height(&preprocessed[i], &main[i]);
+        let row = |n: usize| {
+            let preprocessed_row = preprocessed[i]
+                .as_ref()
+                .map(|preprocessed| preprocessed.row_slice(n).to_vec())
+                .unwrap_or_default();
+            let main_row = main[i]
+                .as_ref()
+                .map(|main| main.row_slice(n).to_vec())
+                .unwrap_or_default();
+            (preprocessed_row, main_row)
+        };
+        for n in 0..height {
+            let (preprocessed_local, main_local) = row(n);
+            // The next row wraps around on the last row, like in the permutation trace
+            let (preprocessed_next, main_next) = row((n + 1) % height);
+            for (interaction, interaction_type) in interactions.iter() {
+                let tuple = interaction
+                    .fields
+                    .iter()
+                    .map(|field| field.apply::<F, F>(&preprocessed_local, &main_local))
+                    .chain(
+                        interaction
+                            .next_fields
+                            .iter()
+                            .map(|field| field.apply::<F, F>(&preprocessed_next, &main_next)),
+                    )
+                    .chain(
+                        interaction
+                            .public_fields
+                            .iter()
+                            .map(|&index| public_values[i][index]),
+                    )
+                    .collect::<Vec<_>>();
+                let mult = interaction.multiplicity::<F, F>(&preprocessed_local, &main_local);
+                let mult = match interaction_type {
+                    InteractionType::Send => mult,
+                    InteractionType::Receive => -mult,
+                };
+                *counts
+                    .entry(interaction.argument_index)
+                    .or_default()
+                    .entry(tuple)
+                    .or_insert(F::zero()) += mult;
+            }
+        }
+    }
+    for (i, tuples) in counts {
+        for (tuple, mult) in tuples {
+            assert!(
+                mult.is_zero(),
+                "{} bus has unmatched tuple {:?}, with sends minus receives {}",
+                B::from(i),
+                tuple,
+                mult
+            );
+        }
+    }
 }
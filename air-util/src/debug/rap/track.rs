@@ -1,18 +1,18 @@
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 
 use hashbrown::HashMap;
 use p3_field::{ExtensionField, Field};
-use p3_interaction::{InteractionType, Rap, NUM_PERM_CHALLENGES};
+use p3_interaction::{trace_height, InteractionType, Rap, NUM_PERM_CHALLENGES};
 use p3_matrix::dense::RowMajorMatrixView;
 use p3_matrix::stack::VerticalPair;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::IntoParallelIterator;
 
-use crate::folders::EntriesLog;
-use crate::util::{MultiTraceEntry, TrackedFieldExpression};
+use crate::folders::{window, EntriesLog, SelectorCoverage};
+use crate::util::{ColumnEntry, MultiTraceEntry, TrackedFieldExpression};
 use crate::{
     folders::rap::{DebugConstraintBuilder, TrackingConstraintBuilder},
     util::{TraceEntry, TrackedFieldVariable},
@@ -22,6 +22,7 @@ pub fn track_constraints<F, EF, A>(
     air: &A,
     preprocessed: &Option<RowMajorMatrixView<F>>,
     main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
     permutation: &Option<RowMajorMatrixView<EF>>,
     perm_challenges: [EF; NUM_PERM_CHALLENGES],
     cumulative_sum: Option<EF>,
@@ -32,17 +33,88 @@ where
     EF: ExtensionField<F>,
     A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>,
 {
-    let height = match (main.as_ref(), preprocessed.as_ref()) {
-        (Some(main), Some(preprocessed)) => core::cmp::max(main.height(), preprocessed.height()),
-        (Some(main), None) => main.height(),
-        (None, Some(preprocessed)) => preprocessed.height(),
-        (None, None) => 0,
-    };
-    if let Some(perm) = permutation {
-        assert_eq!(perm.height(), height);
+    track_rows(
+        air,
+        preprocessed,
+        main,
+        main_ext,
+        permutation,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+        false,
+    )
+    .0
+}
+
+/// Returns the selectors guarding each constraint of the air, in the order they're asserted, and
+/// the number of rows they were active on. See [`unexercised_constraints`] for the constraints
+/// whose selectors were never active, e.g. transition constraints on a trace with a single row.
+pub fn selector_coverage<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    permutation: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+) -> Vec<SelectorCoverage>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>,
+{
+    track_rows(
+        air,
+        preprocessed,
+        main,
+        main_ext,
+        permutation,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+        true,
+    )
+    .1
+}
+
+/// Returns the indices of the constraints that weren't enforced on any row, given the coverage
+/// returned by [`selector_coverage`].
+pub fn unexercised_constraints(coverage: &[SelectorCoverage]) -> Vec<usize> {
+    coverage
+        .iter()
+        .enumerate()
+        .filter(|(_, constraint)| !constraint.is_exercised())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn track_rows<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    permutation: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+    track_selectors: bool,
+) -> (EntriesLog<TraceEntry>, Vec<SelectorCoverage>)
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>,
+{
+    let height = trace_height(preprocessed, main);
+    let main_rotations = air.main_rotations();
+    for trace in main_ext.iter().chain(permutation) {
+        assert_eq!(trace.height(), height);
     }
 
     let mut entries = EntriesLog::<TraceEntry>::default();
+    let mut coverage = Vec::<SelectorCoverage>::new();
     (0..height).into_par_iter().for_each(|i| {
         let i_next = (i + 1) % height;
 
@@ -86,20 +158,40 @@ where
                             TrackedFieldVariable::new(*x, entry)
                         })
                         .collect::<Vec<_>>(),
-                    main.row_slice(i_next)
+                    // The next row followed by the rows at the air's rotations
+                    [1].into_iter()
+                        .chain(main_rotations.iter().copied())
+                        .flat_map(|offset| {
+                            let row = (i + offset) % height;
+                            main.row_slice(row)
+                                .iter()
+                                .enumerate()
+                                .map(|(j, x)| {
+                                    let entry = TraceEntry::Main { row, col: j };
+                                    TrackedFieldVariable::new(*x, entry)
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .unwrap_or((vec![], vec![]));
+        let (main_ext_local, main_ext_next) = main_ext
+            .as_ref()
+            .map(|main_ext| {
+                [i, i_next].map(|row| {
+                    main_ext
+                        .row_slice(row)
                         .iter()
                         .enumerate()
                         .map(|(j, x)| {
-                            let entry = TraceEntry::Main {
-                                row: i_next,
-                                col: j,
-                            };
+                            let entry = TraceEntry::MainExt { row, col: j };
                             TrackedFieldVariable::new(*x, entry)
                         })
-                        .collect::<Vec<_>>(),
-                )
+                        .collect::<Vec<_>>()
+                })
             })
-            .unwrap_or((vec![], vec![]));
+            .map_or((vec![], vec![]), |[local, next]| (local, next));
         let (permutation_local, permutation_next) = permutation
             .as_ref()
             .map(|permutation| {
@@ -143,10 +235,8 @@ where
                 RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
                 RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
             ),
-            main: VerticalPair::new(
-                RowMajorMatrixView::new_row(&*main_local),
-                RowMajorMatrixView::new_row(&*main_next),
-            ),
+            main: window(&main_local, &main_next),
+            main_ext: window(&main_ext_local, &main_ext_next),
             permutation: VerticalPair::new(
                 RowMajorMatrixView::new_row(&*permutation_local),
                 RowMajorMatrixView::new_row(&*permutation_next),
@@ -157,6 +247,8 @@ where
             is_first_row: F::zero(),
             is_last_row: F::zero(),
             is_transition: F::one(),
+            rows_after: height - 1 - i,
+            selector_coverage: track_selectors.then(Vec::new),
         };
         if i == 0 {
             builder.is_first_row = F::one();
@@ -168,9 +260,65 @@ where
 
         air.eval_all(&mut builder);
         entries.extend(&builder.entries);
+        for (j, constraint) in builder.selector_coverage.iter().flatten().enumerate() {
+            if j == coverage.len() {
+                coverage.push(SelectorCoverage::default());
+            }
+            coverage[j].extend(constraint);
+        }
     });
 
-    entries
+    (entries, coverage)
+}
+
+/// Counts how many constraint evaluations reference each trace cell. Cells missing from the
+/// histogram are unconstrained, which usually points to a soundness bug.
+pub fn column_constraint_histogram<F, EF, A>(
+    air: &A,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_ext: &Option<RowMajorMatrixView<EF>>,
+    permutation: &Option<RowMajorMatrixView<EF>>,
+    perm_challenges: [EF; NUM_PERM_CHALLENGES],
+    cumulative_sum: Option<EF>,
+    public_values: &[F],
+) -> BTreeMap<TraceEntry, usize>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Rap<TrackingConstraintBuilder<'a, F, EF>>,
+{
+    track_constraints(
+        air,
+        preprocessed,
+        main,
+        main_ext,
+        permutation,
+        perm_challenges,
+        cumulative_sum,
+        public_values,
+    )
+    .constraint_counts
+}
+
+/// Returns the preprocessed and main columns that no constraint reads on any row, given the
+/// entries logged by [`track_constraints`]. A column that is never read is either dead or missing
+/// its constraints.
+pub fn unread_columns(
+    preprocessed_width: usize,
+    main_width: usize,
+    entries: &EntriesLog<TraceEntry>,
+) -> Vec<ColumnEntry> {
+    let read = entries
+        .constrained
+        .iter()
+        .map(|&entry| ColumnEntry::from(entry))
+        .collect::<BTreeSet<_>>();
+    (0..preprocessed_width)
+        .map(|col| ColumnEntry::Preprocessed { col })
+        .chain((0..main_width).map(|col| ColumnEntry::Main { col }))
+        .filter(|column| !read.contains(column))
+        .collect()
 }
 
 pub fn track_interactions<F, EF, A>(
@@ -186,11 +334,9 @@ where
     let mut bus_counts = BTreeMap::new();
     let mut entries = vec![EntriesLog::default(); airs.len()];
     for (i, air) in airs.iter().enumerate() {
+        let height = trace_height(&preprocessed[i], &main[i]);
         let preprocessed_i = preprocessed[i].as_ref();
         let main_i = main[i].as_ref();
-        let preprocessed_height = preprocessed_i.map_or(0, |t| t.height());
-        let main_height = main_i.map_or(0, |t| t.height());
-        let height = preprocessed_height.max(main_height);
         for n in 0..height {
             let preprocessed_row = preprocessed_i
                 .map(|preprocessed| {
@@ -235,8 +381,7 @@ where
                 };
                 entries[i].constrained.insert(TraceEntry::from(entry));
                 let mut mult = interaction
-                    .count
-                    .apply::<TrackedFieldExpression<F, MultiTraceEntry>, _>(
+                    .multiplicity::<TrackedFieldExpression<F, MultiTraceEntry>, _>(
                         preprocessed_row.as_slice(),
                         main_row.as_slice(),
                     );
@@ -166,9 +166,8 @@ where
             .unwrap_or_default();
 
         for (j, (interaction, _)) in interactions.iter().enumerate() {
-            let count = interaction
-                .count
-                .apply::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
+            let count =
+                interaction.multiplicity::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
             let format = generate_format(
                 &mut header_format[offset],
                 &entries,
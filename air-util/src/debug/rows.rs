@@ -0,0 +1,125 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::Field;
+use p3_interaction::trace_height;
+use p3_matrix::dense::RowMajorMatrixView;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::folders::{window, ViewPair};
+
+/// Which selectors are active on a row.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RowSelectors {
+    pub is_first_row: bool,
+    pub is_last_row: bool,
+    pub is_transition: bool,
+}
+
+impl RowSelectors {
+    /// The selectors of row `i` of a trace with `height` rows.
+    pub fn of_row(i: usize, height: usize) -> Self {
+        Self {
+            is_first_row: i == 0,
+            is_last_row: i == height - 1,
+            is_transition: i != height - 1,
+        }
+    }
+}
+
+/// Returns row `i` of `trace`, and the next row followed by the rows at `rotations`. The rows
+/// wrap around past the last one, matching the evaluation of the constraints on the cyclic
+/// subgroup. A missing trace has empty rows.
+pub(crate) fn local_and_next<T>(
+    trace: &Option<RowMajorMatrixView<T>>,
+    i: usize,
+    rotations: &[usize],
+) -> (Vec<T>, Vec<T>)
+where
+    T: Clone + Send + Sync,
+{
+    trace
+        .as_ref()
+        .map(|trace| {
+            let height = trace.height();
+            let next = [1]
+                .into_iter()
+                .chain(rotations.iter().copied())
+                .flat_map(|offset| trace.row_slice((i + offset) % height).to_vec())
+                .collect::<Vec<_>>();
+            (trace.row_slice(i).to_vec(), next)
+        })
+        .unwrap_or((vec![], vec![]))
+}
+
+/// The rows of the preprocessed and main traces that the constraints on a row are evaluated on,
+/// along with the values of its selectors. Shared by the debug builders of airs and raps.
+pub(crate) struct RowWindow<F> {
+    pub row_index: usize,
+    pub preprocessed_local: Vec<F>,
+    pub preprocessed_next: Vec<F>,
+    pub main_local: Vec<F>,
+    /// The next row followed by the rows at the main rotations.
+    pub main_next: Vec<F>,
+    pub is_first_row: F,
+    pub is_last_row: F,
+    pub is_transition: F,
+    pub rows_after: usize,
+}
+
+impl<F: Field> RowWindow<F> {
+    pub fn new(
+        preprocessed: &Option<RowMajorMatrixView<F>>,
+        main: &Option<RowMajorMatrixView<F>>,
+        main_rotations: &[usize],
+        i: usize,
+        selectors: RowSelectors,
+    ) -> Self {
+        let height = trace_height(preprocessed, main);
+        let (preprocessed_local, preprocessed_next) = local_and_next(preprocessed, i, &[]);
+        let (main_local, main_next) = local_and_next(main, i, main_rotations);
+        let selector = |active: bool| if active { F::one() } else { F::zero() };
+        Self {
+            row_index: i,
+            preprocessed_local,
+            preprocessed_next,
+            main_local,
+            main_next,
+            is_first_row: selector(selectors.is_first_row),
+            is_last_row: selector(selectors.is_last_row),
+            is_transition: selector(selectors.is_transition),
+            rows_after: if selectors.is_transition {
+                height - 1 - i
+            } else {
+                0
+            },
+        }
+    }
+
+    pub fn preprocessed(&self) -> ViewPair<F> {
+        window(&self.preprocessed_local, &self.preprocessed_next)
+    }
+
+    pub fn main(&self) -> ViewPair<F> {
+        window(&self.main_local, &self.main_next)
+    }
+}
+
+/// Calls `eval` on the window of every row of the traces, in parallel.
+pub(crate) fn for_each_row<F, E>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    main_rotations: &[usize],
+    eval: E,
+) where
+    F: Field,
+    E: Fn(RowWindow<F>),
+{
+    let height = trace_height(preprocessed, main);
+    (0..height).into_par_iter().for_each(|i| {
+        let selectors = RowSelectors::of_row(i, height);
+        let row = RowWindow::new(preprocessed, main, main_rotations, i, selectors);
+        eval(row);
+    });
+}
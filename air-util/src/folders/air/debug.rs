@@ -1,7 +1,7 @@
 use p3_air::{AirBuilder, AirBuilderWithPublicValues, PairBuilder};
 use p3_field::Field;
 
-use crate::folders::ViewPair;
+use crate::folders::{row_transition_window_selector, ViewPair};
 
 /// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
 /// be detected early.
@@ -13,6 +13,8 @@ pub struct DebugConstraintBuilder<'a, F: Field> {
     pub is_first_row: F,
     pub is_last_row: F,
     pub is_transition: F,
+    /// The number of rows after this one, for the selectors of transition windows larger than 2.
+    pub rows_after: usize,
 }
 
 impl<'a, F: Field> AirBuilder for DebugConstraintBuilder<'a, F> {
@@ -37,7 +39,7 @@ impl<'a, F: Field> AirBuilder for DebugConstraintBuilder<'a, F> {
         if size == 2 {
             self.is_transition
         } else {
-            panic!("only supports a window size of 2")
+            row_transition_window_selector(self.rows_after, size)
         }
     }
 
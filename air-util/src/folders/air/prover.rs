@@ -1,7 +1,7 @@
 use p3_air::{AirBuilder, AirBuilderWithPublicValues, PairBuilder};
 use p3_uni_stark::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
-use crate::folders::ViewPair;
+use crate::folders::{transition_window_selector, ViewPair};
 
 /// A folder for prover constraints.
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
@@ -11,6 +11,9 @@ pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
     pub is_transition: PackedVal<SC>,
+    /// The inverse of the generator of the trace domain, for the selectors of transition windows
+    /// larger than 2.
+    pub trace_generator_inv: Val<SC>,
     pub alpha: PackedChallenge<SC>,
     pub accumulator: PackedChallenge<SC>,
 }
@@ -37,11 +40,7 @@ where
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition, self.trace_generator_inv, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
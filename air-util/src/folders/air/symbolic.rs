@@ -71,11 +71,12 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        assert!(size >= 2, "a transition window has at least 2 rows");
+        // The selector of a window of `size` rows multiplies `IsTransition` by `size - 2` more
+        // linear factors, which are stood in for by `IsLastRow` to account for their degree.
+        (2..size).fold(SymbolicExpression::IsTransition, |selector, _| {
+            selector * SymbolicExpression::IsLastRow
+        })
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
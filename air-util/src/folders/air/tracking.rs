@@ -1,7 +1,7 @@
 use p3_air::{AirBuilder, AirBuilderWithPublicValues, PairBuilder};
 use p3_field::Field;
 
-use crate::folders::{EntriesLog, ViewPair};
+use crate::folders::{row_transition_window_selector, EntriesLog, ViewPair};
 use crate::util::{TraceEntry, TrackedFieldExpression, TrackedFieldVariable};
 
 pub struct TrackingConstraintBuilder<'a, F>
@@ -15,6 +15,8 @@ where
     pub is_first_row: F,
     pub is_last_row: F,
     pub is_transition: F,
+    /// The number of rows after this one, for the selectors of transition windows larger than 2.
+    pub rows_after: usize,
 }
 
 impl<'a, F> AirBuilder for TrackingConstraintBuilder<'a, F>
@@ -42,14 +44,16 @@ where
         if size == 2 {
             self.is_transition.into()
         } else {
-            panic!("only supports a window size of 2")
+            row_transition_window_selector::<F>(self.rows_after, size).into()
         }
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x = x.into();
-        self.entries.constrained.extend(x.constraint_origin);
+        self.entries.record_constraint(&x.constraint_origin);
         if !x.value.is_zero() {
+            #[cfg(feature = "expression-trace")]
+            tracing::debug!("constraint doesn't vanish: {} = {}", x.expression, x.value);
             self.entries.failing.extend(x.value_origin);
         }
     }
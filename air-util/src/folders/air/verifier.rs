@@ -1,7 +1,7 @@
 use p3_air::{AirBuilder, AirBuilderWithPublicValues, PairBuilder};
 use p3_uni_stark::{StarkGenericConfig, Val};
 
-use crate::folders::ViewPair;
+use crate::folders::{transition_window_selector, ViewPair};
 
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub preprocessed: ViewPair<'a, SC::Challenge>,
@@ -10,6 +10,9 @@ pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub is_first_row: SC::Challenge,
     pub is_last_row: SC::Challenge,
     pub is_transition: SC::Challenge,
+    /// The inverse of the generator of the trace domain, for the selectors of transition windows
+    /// larger than 2.
+    pub trace_generator_inv: Val<SC>,
     pub alpha: SC::Challenge,
     pub accumulator: SC::Challenge,
 }
@@ -33,11 +36,7 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition, self.trace_generator_inv, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
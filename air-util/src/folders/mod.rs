@@ -1,20 +1,108 @@
 pub mod air;
 pub mod rap;
 
-use alloc::collections::BTreeSet;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::ops::Add;
+
+use p3_field::{AbstractField, Field};
 use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
 
+use crate::util::RowSelector;
+
 pub type ViewPair<'a, T> = VerticalPair<RowMajorMatrixView<'a, T>, RowMajorMatrixView<'a, T>>;
 
+/// Stacks the `local` row on top of `next`, which holds the next row optionally followed by
+/// rotation rows of the same width.
+pub fn window<'a, T>(local: &'a [T], next: &'a [T]) -> ViewPair<'a, T>
+where
+    T: Clone + Send + Sync,
+{
+    let next = if local.is_empty() {
+        RowMajorMatrixView::new_row(next)
+    } else {
+        RowMajorMatrixView::new(next, local.len())
+    };
+    VerticalPair::new(RowMajorMatrixView::new_row(local), next)
+}
+
+/// Returns the selector of a transition window of `size` rows, which vanishes on the last
+/// `size - 1` rows of the trace. `is_transition` is the uni-stark selector `x - g^{-1}` and
+/// `g_inv` is `g^{-1}`, where `g` generates the trace domain, so the selector is
+/// `(x - g^{-1}) * ... * (x - g^{-(size - 1)})`.
+///
+/// The rows of a window past the next one are read through `InteractionAir::main_rotations`.
+pub fn transition_window_selector<F, Expr>(is_transition: Expr, g_inv: F, size: usize) -> Expr
+where
+    F: Field,
+    Expr: AbstractField + Add<F, Output = Expr>,
+{
+    assert!(size >= 2, "a transition window has at least 2 rows");
+    let mut selector = is_transition.clone();
+    let mut g_inv_k = g_inv;
+    for _ in 2..size {
+        g_inv_k *= g_inv;
+        selector *= is_transition.clone() + (g_inv - g_inv_k);
+    }
+    selector
+}
+
+/// Returns the selector of a transition window of `size` rows on a row followed by `rows_after`
+/// rows, for builders that evaluate constraints row by row.
+pub fn row_transition_window_selector<F: AbstractField>(rows_after: usize, size: usize) -> F {
+    assert!(size >= 2, "a transition window has at least 2 rows");
+    if rows_after >= size - 1 {
+        F::one()
+    } else {
+        F::zero()
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct EntriesLog<T: Copy + Ord> {
     pub failing: BTreeSet<T>,
     pub constrained: BTreeSet<T>,
+    /// The number of constraint evaluations referencing each entry.
+    pub constraint_counts: BTreeMap<T, usize>,
 }
 
 impl<T: Copy + Ord> EntriesLog<T> {
     pub fn extend(&mut self, other: &Self) {
         self.failing.extend(&other.failing);
         self.constrained.extend(&other.constrained);
+        for (entry, count) in other.constraint_counts.iter() {
+            *self.constraint_counts.entry(*entry).or_default() += count;
+        }
+    }
+
+    /// Records a constraint evaluation referencing the entries in `origin`.
+    pub fn record_constraint(&mut self, origin: &BTreeSet<T>) {
+        for entry in origin.iter() {
+            *self.constraint_counts.entry(*entry).or_default() += 1;
+        }
+        self.constrained.extend(origin);
+    }
+}
+
+/// The selectors guarding a constraint, and the number of rows they were all active on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelectorCoverage {
+    pub selectors: BTreeSet<RowSelector>,
+    /// The number of rows the constraint was evaluated on.
+    pub rows: usize,
+    /// The number of rows all of `selectors` were active on, i.e. the constraint was enforced on.
+    pub active_rows: usize,
+}
+
+impl SelectorCoverage {
+    /// Whether the constraint was enforced on any row. A transition constraint on a trace with a
+    /// single row, for example, never is.
+    pub fn is_exercised(&self) -> bool {
+        self.active_rows > 0
+    }
+
+    pub fn extend(&mut self, other: &Self) {
+        self.selectors.extend(&other.selectors);
+        self.rows += other.rows;
+        self.active_rows += other.active_rows;
     }
 }
@@ -1,17 +1,23 @@
+use alloc::vec::Vec;
+
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
 use p3_field::{ExtensionField, Field};
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 
-use crate::folders::ViewPair;
+use crate::folders::{row_transition_window_selector, ViewPair};
 
 /// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
 /// be detected early.
+///
+/// If `constraint_values` is set, the builder instead records the value of every constraint
+/// without asserting, so that the evaluated values can be inspected.
 pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F>> {
     pub row_index: usize,
     pub preprocessed: ViewPair<'a, F>,
     pub main: ViewPair<'a, F>,
+    pub main_ext: ViewPair<'a, EF>,
     pub permutation: ViewPair<'a, EF>,
     pub perm_challenges: [EF; NUM_PERM_CHALLENGES],
     pub public_values: &'a [F],
@@ -19,6 +25,9 @@ pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F>> {
     pub is_first_row: F,
     pub is_last_row: F,
     pub is_transition: F,
+    /// The number of rows after this one, for the selectors of transition windows larger than 2.
+    pub rows_after: usize,
+    pub constraint_values: Option<Vec<EF>>,
 }
 
 impl<'a, F: Field, EF: ExtensionField<F>> AirBuilder for DebugConstraintBuilder<'a, F, EF> {
@@ -43,13 +52,18 @@ impl<'a, F: Field, EF: ExtensionField<F>> AirBuilder for DebugConstraintBuilder<
         if size == 2 {
             self.is_transition
         } else {
-            panic!("only supports a window size of 2")
+            row_transition_window_selector(self.rows_after, size)
         }
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        let x = x.into();
+        if let Some(values) = self.constraint_values.as_mut() {
+            values.push(EF::from_base(x));
+            return;
+        }
         assert!(
-            x.into().is_zero(),
+            x.is_zero(),
             "constraints had nonzero value on row {}",
             self.row_index
         );
@@ -58,6 +72,10 @@ impl<'a, F: Field, EF: ExtensionField<F>> AirBuilder for DebugConstraintBuilder<
     fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
         let x = x.into();
         let y = y.into();
+        if let Some(values) = self.constraint_values.as_mut() {
+            values.push(EF::from_base(x - y));
+            return;
+        }
         assert_eq!(
             x, y,
             "values didn't match on row {}: {} != {}",
@@ -91,8 +109,13 @@ impl<'a, F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBu
     where
         I: Into<Self::ExprEF>,
     {
+        let x = x.into();
+        if let Some(values) = self.constraint_values.as_mut() {
+            values.push(x);
+            return;
+        }
         assert!(
-            x.into().is_zero(),
+            x.is_zero(),
             "constraints had nonzero value on row {}",
             self.row_index
         );
@@ -105,6 +128,10 @@ impl<'a, F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBu
     {
         let x = x.into();
         let y = y.into();
+        if let Some(values) = self.constraint_values.as_mut() {
+            values.push(x - y);
+            return;
+        }
         assert_eq!(
             x, y,
             "values didn't match on row {}: {} != {}",
@@ -135,4 +162,8 @@ impl<'a, F: Field, EF: ExtensionField<F>> InteractionAirBuilder
     fn cumulative_sum(&self) -> Self::RandomVar {
         self.cumulative_sum
     }
+
+    fn main_ext(&self) -> Self::MP {
+        self.main_ext
+    }
 }
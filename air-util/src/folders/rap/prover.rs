@@ -1,15 +1,17 @@
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
-use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
+use p3_field::{AbstractExtensionField, PackedValue, Powers};
+use p3_interaction::{reduce_rows_packed, Interaction, InteractionAirBuilder, NUM_PERM_CHALLENGES};
 use p3_uni_stark::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
-use crate::folders::ViewPair;
+use crate::folders::{transition_window_selector, ViewPair};
 
 /// A folder for prover constraints.
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
     pub preprocessed: ViewPair<'a, PackedVal<SC>>,
     pub main: ViewPair<'a, PackedVal<SC>>,
+    pub main_ext: ViewPair<'a, PackedChallenge<SC>>,
     pub perm: ViewPair<'a, PackedChallenge<SC>>,
     pub perm_challenges: [PackedChallenge<SC>; NUM_PERM_CHALLENGES],
     pub public_values: &'a [Val<SC>],
@@ -17,8 +19,13 @@ pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
     pub is_transition: PackedVal<SC>,
+    /// The inverse of the generator of the trace domain, for the selectors of transition windows
+    /// larger than 2.
+    pub trace_generator_inv: Val<SC>,
     pub alpha: PackedChallenge<SC>,
     pub accumulator: PackedChallenge<SC>,
+    /// The number of constraints folded into the accumulator.
+    pub num_constraints: usize,
 }
 
 impl<'a, SC> AirBuilder for ProverConstraintFolder<'a, SC>
@@ -43,17 +50,14 @@ where
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition, self.trace_generator_inv, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x: PackedVal<SC> = x.into();
         self.accumulator *= self.alpha;
         self.accumulator += x;
+        self.num_constraints += 1;
     }
 }
 
@@ -81,6 +85,7 @@ where
         let x: PackedChallenge<SC> = x.into();
         self.accumulator *= self.alpha;
         self.accumulator += x;
+        self.num_constraints += 1;
     }
 }
 
@@ -113,4 +118,38 @@ impl<'a, SC: StarkGenericConfig> InteractionAirBuilder for ProverConstraintFolde
     fn cumulative_sum(&self) -> Self::RandomVar {
         self.cumulative_sum
     }
+
+    fn main_ext(&self) -> Self::MP {
+        self.main_ext
+    }
+
+    fn reduce_interaction(
+        &self,
+        preprocessed_local: &[Self::Var],
+        main_local: &[Self::Var],
+        preprocessed_next: &[Self::Var],
+        main_next: &[Self::Var],
+        interaction: &Interaction<Self::F>,
+        alpha: Self::ExprEF,
+        betas: Powers<Self::ExprEF>,
+    ) -> Self::ExprEF {
+        reduce_rows_packed(
+            preprocessed_local,
+            main_local,
+            preprocessed_next,
+            main_next,
+            self.public_values,
+            interaction,
+            unpack_uniform::<SC>(alpha),
+            Powers {
+                base: unpack_uniform::<SC>(betas.base),
+                current: unpack_uniform::<SC>(betas.current),
+            },
+        )
+    }
+}
+
+/// Extracts the scalar from a packed challenge that holds the same value on every lane.
+fn unpack_uniform<SC: StarkGenericConfig>(x: PackedChallenge<SC>) -> SC::Challenge {
+    SC::Challenge::from_base_fn(|i| x.as_base_slice()[i].as_slice()[0])
 }
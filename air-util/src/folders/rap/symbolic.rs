@@ -14,6 +14,7 @@ use p3_uni_stark::{Entry, SymbolicExpression, SymbolicVariable};
 pub struct SymbolicAirBuilder<F: Field> {
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    main_ext: RowMajorMatrix<SymbolicVariable<F>>,
     permutation: RowMajorMatrix<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
     perm_challenges: [SymbolicVariable<F>; NUM_PERM_CHALLENGES],
@@ -25,6 +26,8 @@ impl<F: Field> SymbolicAirBuilder<F> {
     pub(crate) fn new(
         preprocessed_width: usize,
         main_width: usize,
+        main_rotations: &[usize],
+        main_ext_width: usize,
         permutation_width: usize,
         num_public_values: usize,
     ) -> Self {
@@ -37,16 +40,27 @@ impl<F: Field> SymbolicAirBuilder<F> {
             .collect();
         let main_values = [0, 1]
             .into_iter()
+            .chain(main_rotations.iter().copied())
             .flat_map(|offset| {
                 (0..main_width)
                     .map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
             })
             .collect();
+        // The extension columns are only opened at the local and next rows, and are indexed after
+        // the base field columns so that they don't alias them.
+        let main_ext_values = [0, 1]
+            .into_iter()
+            .flat_map(|offset| {
+                (0..main_ext_width).map(move |index| {
+                    SymbolicVariable::new(Entry::Main { offset }, main_width + index)
+                })
+            })
+            .collect();
         let perm_values = [0, 1]
             .into_iter()
             .flat_map(|offset| {
                 (0..permutation_width)
-                    .map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
             })
             .collect();
         let public_values = (0..num_public_values)
@@ -63,6 +77,7 @@ impl<F: Field> SymbolicAirBuilder<F> {
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, main_width),
+            main_ext: RowMajorMatrix::new(main_ext_values, main_ext_width),
             permutation: RowMajorMatrix::new(perm_values, permutation_width),
             public_values,
             perm_challenges,
@@ -95,11 +110,12 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        assert!(size >= 2, "a transition window has at least 2 rows");
+        // The selector of a window of `size` rows multiplies `IsTransition` by `size - 2` more
+        // linear factors, which are stood in for by `IsLastRow` to account for their degree.
+        (2..size).fold(SymbolicExpression::IsTransition, |selector, _| {
+            selector * SymbolicExpression::IsLastRow
+        })
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
@@ -151,4 +167,8 @@ impl<F: Field> InteractionAirBuilder for SymbolicAirBuilder<F> {
     fn cumulative_sum(&self) -> Self::VarEF {
         self.cumulative_sum
     }
+
+    fn main_ext(&self) -> Self::MP {
+        self.main_ext.clone()
+    }
 }
@@ -1,12 +1,16 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
 use p3_field::{ExtensionField, Field};
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 
-use crate::folders::{EntriesLog, ViewPair};
+use crate::folders::{row_transition_window_selector, EntriesLog, SelectorCoverage, ViewPair};
 use crate::util::{
-    TraceEntry, TrackedExtensionFieldExpression, TrackedFieldExpression, TrackedFieldVariable,
+    RowSelector, TraceEntry, TrackedExtensionFieldExpression, TrackedFieldExpression,
+    TrackedFieldVariable,
 };
 
 // TODO: Remove permutations?
@@ -18,6 +22,7 @@ where
     pub entries: EntriesLog<TraceEntry>,
     pub preprocessed: ViewPair<'a, TrackedFieldVariable<F, TraceEntry>>,
     pub main: ViewPair<'a, TrackedFieldVariable<F, TraceEntry>>,
+    pub main_ext: ViewPair<'a, TrackedFieldVariable<EF, TraceEntry>>,
     pub permutation: ViewPair<'a, TrackedFieldVariable<EF, TraceEntry>>,
     pub perm_challenges: [TrackedFieldVariable<EF, TraceEntry>; NUM_PERM_CHALLENGES],
     pub public_values: &'a [TrackedFieldVariable<F, TraceEntry>],
@@ -25,6 +30,72 @@ where
     pub is_first_row: F,
     pub is_last_row: F,
     pub is_transition: F,
+    /// The number of rows after this one, for the selectors of transition windows larger than 2.
+    pub rows_after: usize,
+    /// When set, the selectors guarding each constraint are recorded in the order the constraints
+    /// are asserted. This assumes the air asserts the same constraints on every row.
+    pub selector_coverage: Option<Vec<SelectorCoverage>>,
+}
+
+impl<'a, F, EF> TrackingConstraintBuilder<'a, F, EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    fn selector_value(&self, selector: RowSelector) -> F {
+        match selector {
+            RowSelector::FirstRow => self.is_first_row,
+            RowSelector::LastRow => self.is_last_row,
+            RowSelector::Transition { size: 2 } => self.is_transition,
+            RowSelector::Transition { size } => {
+                row_transition_window_selector::<F>(self.rows_after, size)
+            }
+        }
+    }
+
+    /// Returns the selector as an expression, which references it when selectors are tracked.
+    fn selector(&self, selector: RowSelector) -> TrackedFieldExpression<F, TraceEntry> {
+        let value = self.selector_value(selector);
+        if self.selector_coverage.is_none() {
+            return value.into();
+        }
+        let mut x: TrackedFieldExpression<F, TraceEntry> =
+            TrackedFieldVariable::new(value, TraceEntry::Selector { selector }).into();
+        // The selector only guards the constraint, so it can't be the cause of a failure
+        x.value_origin.clear();
+        x
+    }
+
+    fn record_constraint(&mut self, origin: &BTreeSet<TraceEntry>) {
+        if self.selector_coverage.is_none() {
+            self.entries.record_constraint(origin);
+            return;
+        }
+
+        let mut selectors = BTreeSet::new();
+        let mut entries = BTreeSet::new();
+        for entry in origin.iter() {
+            match *entry {
+                TraceEntry::Selector { selector } => {
+                    selectors.insert(selector);
+                }
+                entry => {
+                    entries.insert(entry);
+                }
+            }
+        }
+        let active = selectors
+            .iter()
+            .all(|selector| !self.selector_value(*selector).is_zero());
+        self.entries.record_constraint(&entries);
+        if let Some(coverage) = self.selector_coverage.as_mut() {
+            coverage.push(SelectorCoverage {
+                selectors,
+                rows: 1,
+                active_rows: usize::from(active),
+            });
+        }
+    }
 }
 
 impl<'a, F, EF> AirBuilder for TrackingConstraintBuilder<'a, F, EF>
@@ -42,25 +113,23 @@ where
     }
 
     fn is_first_row(&self) -> Self::Expr {
-        self.is_first_row.into()
+        self.selector(RowSelector::FirstRow)
     }
 
     fn is_last_row(&self) -> Self::Expr {
-        self.is_last_row.into()
+        self.selector(RowSelector::LastRow)
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition.into()
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        self.selector(RowSelector::Transition { size })
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x = x.into();
-        self.entries.constrained.extend(x.constraint_origin);
+        self.record_constraint(&x.constraint_origin);
         if !x.value.is_zero() {
+            #[cfg(feature = "expression-trace")]
+            tracing::debug!("constraint doesn't vanish: {} = {}", x.expression, x.value);
             self.entries.failing.extend(x.value_origin);
         }
     }
@@ -102,7 +171,7 @@ where
         I: Into<Self::ExprEF>,
     {
         let x = x.into();
-        self.entries.constrained.extend(x.0.constraint_origin);
+        self.record_constraint(&x.0.constraint_origin);
         if !x.0.value.is_zero() {
             self.entries.failing.extend(x.0.value_origin);
         }
@@ -134,4 +203,8 @@ where
     fn cumulative_sum(&self) -> Self::VarEF {
         self.cumulative_sum
     }
+
+    fn main_ext(&self) -> Self::MP {
+        self.main_ext
+    }
 }
@@ -5,11 +5,12 @@ use p3_field::AbstractField;
 use p3_interaction::{InteractionAirBuilder, NUM_PERM_CHALLENGES};
 use p3_uni_stark::{StarkGenericConfig, Val};
 
-use crate::folders::ViewPair;
+use crate::folders::{transition_window_selector, ViewPair};
 
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub preprocessed: ViewPair<'a, SC::Challenge>,
     pub main: ViewPair<'a, SC::Challenge>,
+    pub main_ext: ViewPair<'a, SC::Challenge>,
     pub perm: ViewPair<'a, SC::Challenge>,
     pub perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     pub public_values: &'a [Val<SC>],
@@ -17,8 +18,13 @@ pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
     pub is_first_row: SC::Challenge,
     pub is_last_row: SC::Challenge,
     pub is_transition: SC::Challenge,
+    /// The inverse of the generator of the trace domain, for the selectors of transition windows
+    /// larger than 2.
+    pub trace_generator_inv: Val<SC>,
     pub alpha: SC::Challenge,
     pub accumulator: SC::Challenge,
+    /// The number of constraints folded into the accumulator.
+    pub num_constraints: usize,
 }
 
 impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC> {
@@ -40,17 +46,14 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition, self.trace_generator_inv, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         let x: SC::Challenge = x.into();
         self.accumulator *= self.alpha;
         self.accumulator += x;
+        self.num_constraints += 1;
     }
 }
 
@@ -86,6 +89,7 @@ where
         let x: SC::Challenge = x.into();
         self.accumulator *= SC::Challenge::from_f(self.alpha);
         self.accumulator += x;
+        self.num_constraints += 1;
     }
 }
 
@@ -110,4 +114,8 @@ impl<'a, SC: StarkGenericConfig> InteractionAirBuilder for VerifierConstraintFol
     fn cumulative_sum(&self) -> Self::RandomVar {
         self.cumulative_sum
     }
+
+    fn main_ext(&self) -> Self::MP {
+        self.main_ext
+    }
 }
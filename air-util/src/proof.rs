@@ -20,6 +20,9 @@ pub struct InteractionAirProof<Challenge> {
 pub struct OpenedValues<Challenge> {
     pub preprocessed: Option<AdjacentOpenedValues<Challenge>>,
     pub main: Option<AdjacentOpenedValues<Challenge>>,
+    /// The flattened extension field columns of the main trace, committed along with it.
+    #[serde(default)]
+    pub main_ext: Option<AdjacentOpenedValues<Challenge>>,
     pub permutation: Option<AdjacentOpenedValues<Challenge>>,
     // TODO: Check if inner size is 2
     pub quotient_chunks: Option<Vec<Vec<Challenge>>>,
@@ -29,4 +32,7 @@ pub struct OpenedValues<Challenge> {
 pub struct AdjacentOpenedValues<Challenge> {
     pub local: Vec<Challenge>,
     pub next: Vec<Challenge>,
+    /// The rows at the rotations declared by the air, only opened for the main trace.
+    #[serde(default)]
+    pub rotations: Vec<Vec<Challenge>>,
 }
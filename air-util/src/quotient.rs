@@ -24,8 +24,10 @@ where
     1 << d
 }
 
+/// Returns the maximum degree among all constraints of `air`, including its permutation
+/// constraints, or 0 if it has none.
 #[instrument(name = "infer constraint degree", skip_all, level = "debug")]
-fn get_max_constraint_degree<F, A>(air: &A, num_public_values: usize) -> usize
+pub fn get_max_constraint_degree<F, A>(air: &A, num_public_values: usize) -> usize
 where
     F: Field,
     A: Rap<SymbolicAirBuilder<F>>,
@@ -37,8 +39,12 @@ where
         .unwrap_or(0)
 }
 
+/// Evaluates all constraints of `air`, including its permutation constraints, symbolically.
 #[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
-fn get_symbolic_constraints<F, A>(air: &A, num_public_values: usize) -> Vec<SymbolicExpression<F>>
+pub fn get_symbolic_constraints<F, A>(
+    air: &A,
+    num_public_values: usize,
+) -> Vec<SymbolicExpression<F>>
 where
     F: Field,
     A: Rap<SymbolicAirBuilder<F>>,
@@ -46,6 +52,8 @@ where
     let mut builder = SymbolicAirBuilder::new(
         air.preprocessed_width(),
         air.width(),
+        &air.main_rotations(),
+        air.main_ext_width(),
         air.permutation_width().unwrap_or_default(),
         num_public_values,
     );
@@ -5,10 +5,23 @@ pub enum ColumnEntry {
     None,
     Preprocessed { col: usize },
     Main { col: usize },
+    MainExt { col: usize },
     Permutation { col: usize },
     VirtualColumnCount { interaction: usize },
     VirtualColumnField { interaction: usize, field: usize },
     Public { index: usize },
+    Selector { selector: RowSelector },
+}
+
+/// A selector that guards a constraint to some rows of the trace.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RowSelector {
+    FirstRow,
+    LastRow,
+    /// A transition window of `size` rows.
+    Transition {
+        size: usize,
+    },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -22,6 +35,10 @@ pub enum TraceEntry {
         row: usize,
         col: usize,
     },
+    MainExt {
+        row: usize,
+        col: usize,
+    },
     Permutation {
         row: usize,
         col: usize,
@@ -38,6 +55,10 @@ pub enum TraceEntry {
     Public {
         index: usize,
     },
+    /// Only referenced by constraints when selectors are tracked.
+    Selector {
+        selector: RowSelector,
+    },
 }
 
 impl Default for TraceEntry {
@@ -117,6 +138,7 @@ impl From<TraceEntry> for ColumnEntry {
             TraceEntry::None => ColumnEntry::None,
             TraceEntry::Preprocessed { col, .. } => ColumnEntry::Preprocessed { col },
             TraceEntry::Main { col, .. } => ColumnEntry::Main { col },
+            TraceEntry::MainExt { col, .. } => ColumnEntry::MainExt { col },
             TraceEntry::Permutation { col, .. } => ColumnEntry::Permutation { col },
             TraceEntry::VirtualColumnCount { interaction, .. } => {
                 ColumnEntry::VirtualColumnCount { interaction }
@@ -125,6 +147,7 @@ impl From<TraceEntry> for ColumnEntry {
                 interaction, field, ..
             } => ColumnEntry::VirtualColumnField { interaction, field },
             TraceEntry::Public { index } => ColumnEntry::Public { index },
+            TraceEntry::Selector { selector } => ColumnEntry::Selector { selector },
         }
     }
 }
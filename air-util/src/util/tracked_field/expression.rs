@@ -1,10 +1,15 @@
 use alloc::collections::BTreeSet;
+#[cfg(feature = "expression-trace")]
+use alloc::sync::Arc;
 use core::fmt::Debug;
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use p3_field::{AbstractField, Field};
 
+#[cfg(feature = "expression-trace")]
+use super::ExpressionTrace;
+
 #[derive(Clone, Debug)]
 pub struct TrackedFieldExpression<F, E>
 where
@@ -14,6 +19,9 @@ where
     pub value: F,
     pub value_origin: BTreeSet<E>,
     pub constraint_origin: BTreeSet<E>,
+    /// The operations the expression was built with, e.g. to print a failing constraint.
+    #[cfg(feature = "expression-trace")]
+    pub expression: Arc<ExpressionTrace<F, E>>,
 }
 
 impl<F, E> Default for TrackedFieldExpression<F, E>
@@ -26,6 +34,8 @@ where
             value: F::zero(),
             value_origin: BTreeSet::new(),
             constraint_origin: BTreeSet::new(),
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Constant(F::zero())),
         }
     }
 }
@@ -40,6 +50,8 @@ where
             value,
             value_origin: BTreeSet::new(),
             constraint_origin: BTreeSet::new(),
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Constant(value)),
         }
     }
 }
@@ -70,6 +82,8 @@ where
             value: self.value + rhs.value,
             value_origin,
             constraint_origin: &self.constraint_origin | &rhs.constraint_origin,
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Add(self.expression, rhs.expression)),
         }
     }
 }
@@ -152,6 +166,8 @@ where
             value: self.value - rhs.value,
             value_origin,
             constraint_origin: &self.constraint_origin | &rhs.constraint_origin,
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Sub(self.expression, rhs.expression)),
         }
     }
 }
@@ -200,6 +216,8 @@ where
             value: -self.value,
             value_origin: self.value_origin,
             constraint_origin: self.constraint_origin,
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Neg(self.expression)),
         }
     }
 }
@@ -243,6 +261,8 @@ where
             value: self.value * rhs.value,
             value_origin,
             constraint_origin,
+            #[cfg(feature = "expression-trace")]
+            expression: Arc::new(ExpressionTrace::Mul(self.expression, rhs.expression)),
         }
     }
 }
@@ -321,11 +341,7 @@ where
 
     #[inline]
     fn from_f(f: Self::F) -> Self {
-        Self {
-            value: F::from_f(f),
-            value_origin: BTreeSet::new(),
-            constraint_origin: BTreeSet::new(),
-        }
+        Self::from(F::from_f(f))
     }
 
     fn from_bool(b: bool) -> Self {
@@ -0,0 +1,111 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt::{self, Debug, Display, Formatter};
+
+/// The arithmetic a [`super::TrackedFieldExpression`] was built with, i.e. which entries and
+/// constants were combined by which operations. Only recorded with the `expression-trace`
+/// feature, since unlike the entry sets it grows with every operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpressionTrace<F, E> {
+    Constant(F),
+    Entry(E),
+    Add(Arc<Self>, Arc<Self>),
+    Sub(Arc<Self>, Arc<Self>),
+    Neg(Arc<Self>),
+    Mul(Arc<Self>, Arc<Self>),
+}
+
+impl<F, E> ExpressionTrace<F, E> {
+    /// Returns a displayable form of the expression that prints each entry with `label`.
+    pub fn display_with<L>(&self, label: L) -> ExpressionTraceDisplay<'_, F, E, L>
+    where
+        L: Fn(&E) -> String,
+    {
+        ExpressionTraceDisplay {
+            expression: self,
+            label,
+        }
+    }
+
+    /// Binding strength of the outermost operation, used to only parenthesize where needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Add(..) | Self::Sub(..) => 0,
+            Self::Mul(..) => 1,
+            Self::Neg(..) => 2,
+            Self::Constant(..) | Self::Entry(..) => 3,
+        }
+    }
+}
+
+/// Prints entries with their `Debug` form. See [`ExpressionTrace::display_with`] for custom
+/// labels.
+impl<F: Display, E: Debug> Display for ExpressionTrace<F, E> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(&self.display_with(|entry| alloc::format!("{:?}", entry)), f)
+    }
+}
+
+/// See [`ExpressionTrace::display_with`].
+pub struct ExpressionTraceDisplay<'a, F, E, L> {
+    expression: &'a ExpressionTrace<F, E>,
+    label: L,
+}
+
+impl<'a, F, E, L> ExpressionTraceDisplay<'a, F, E, L>
+where
+    F: Display,
+    L: Fn(&E) -> String,
+{
+    /// Writes `expression`, in parentheses if it binds less tightly than `min_precedence`.
+    fn write(
+        &self,
+        f: &mut Formatter,
+        expression: &ExpressionTrace<F, E>,
+        min_precedence: u8,
+    ) -> fmt::Result {
+        let parenthesize = expression.precedence() < min_precedence;
+        if parenthesize {
+            write!(f, "(")?;
+        }
+        let precedence = expression.precedence();
+        match expression {
+            ExpressionTrace::Constant(value) => write!(f, "{}", value)?,
+            ExpressionTrace::Entry(entry) => write!(f, "{}", (self.label)(entry))?,
+            ExpressionTrace::Add(x, y) => {
+                self.write(f, x, precedence)?;
+                write!(f, " + ")?;
+                self.write(f, y, precedence)?;
+            }
+            ExpressionTrace::Sub(x, y) => {
+                // The right operand of a subtraction is grouped, e.g. `a - (b + c)`
+                self.write(f, x, precedence)?;
+                write!(f, " - ")?;
+                self.write(f, y, precedence + 1)?;
+            }
+            ExpressionTrace::Neg(x) => {
+                write!(f, "-")?;
+                self.write(f, x, precedence)?;
+            }
+            ExpressionTrace::Mul(x, y) => {
+                self.write(f, x, precedence)?;
+                write!(f, " * ")?;
+                self.write(f, y, precedence)?;
+            }
+        }
+        if parenthesize {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, F, E, L> Display for ExpressionTraceDisplay<'a, F, E, L>
+where
+    F: Display,
+    L: Fn(&E) -> String,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.write(f, self.expression, 0)
+    }
+}
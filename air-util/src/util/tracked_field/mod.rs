@@ -1,9 +1,13 @@
 mod entry;
 mod expression;
 mod expression_ext;
+#[cfg(feature = "expression-trace")]
+mod expression_trace;
 mod variable;
 
 pub use entry::*;
 pub use expression::*;
 pub use expression_ext::*;
+#[cfg(feature = "expression-trace")]
+pub use expression_trace::*;
 pub use variable::*;
@@ -1,4 +1,6 @@
 use alloc::collections::BTreeSet;
+#[cfg(feature = "expression-trace")]
+use alloc::sync::Arc;
 use alloc::vec;
 use core::fmt::Debug;
 use core::marker::PhantomData;
@@ -7,6 +9,8 @@ use core::ops::{Add, Mul, Sub};
 use p3_field::{ExtensionField, Field};
 
 use super::expression::TrackedFieldExpression;
+#[cfg(feature = "expression-trace")]
+use super::ExpressionTrace;
 use super::TrackedExtensionFieldExpression;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -42,11 +46,15 @@ where
     E: Default + Clone + Debug + Ord,
 {
     fn from(value: TrackedFieldVariable<F, E>) -> Self {
+        #[cfg(feature = "expression-trace")]
+        let expression = Arc::new(ExpressionTrace::Entry(value.entry.clone()));
         let entries = vec![value.entry];
         TrackedFieldExpression {
             value: value.value,
             value_origin: BTreeSet::from_iter(entries.clone()),
             constraint_origin: BTreeSet::from_iter(entries),
+            #[cfg(feature = "expression-trace")]
+            expression,
         }
     }
 }
@@ -0,0 +1,964 @@
+use core::borrow::Borrow;
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::debug::rap::{
+    check_bus_counts, check_constraints, check_cumulative_sums, check_lookups,
+    collect_constraint_values, compute_cumulative_sums,
+};
+use p3_air_util::get_max_constraint_degree;
+use p3_baby_bear::BabyBear;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractField, ExtensionField, Field, Powers};
+use p3_goldilocks::Goldilocks;
+use p3_interaction::{
+    generate_permutation_trace, generate_permutation_trace_with_backend,
+    generate_permutation_trace_with_beta_source, generate_permutation_trace_with_options,
+    interaction_batches, BaseInteractionAir, BetaSource, Bus, DefaultBetaSource, Interaction,
+    InteractionAir, InteractionAirBuilder, InteractionType, LogUpBackend, PermutationBackendKind,
+    PermutationTrace, PermutationTraceError, PermutationTraceOptions, Rap, ReciprocalBackend,
+    NUM_PERM_CHALLENGES,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use rand::distributions::{Distribution, Standard};
+use rand::{thread_rng, Rng};
+
+const HEIGHT: usize = 8;
+
+struct TestBus;
+
+impl From<usize> for TestBus {
+    fn from(_value: usize) -> Self {
+        Self
+    }
+}
+
+impl Display for TestBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "TestBus")
+    }
+}
+
+impl Bus for TestBus {
+    fn index(&self) -> usize {
+        0
+    }
+}
+
+/// Sends its first column and receives its second column with multiplicity 2.
+struct TestChip;
+
+impl<F: Field> BaseAir<F> for TestChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TestChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for TestChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction::new(
+            vec![VirtualPairCol::single_main(main_indices[1])],
+            VirtualPairCol::constant(F::two()),
+            TestBus,
+        )]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction::new(
+            vec![VirtualPairCol::single_main(main_indices[0])],
+            VirtualPairCol::constant(F::two()),
+            TestBus,
+        )]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TestChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TestChip {}
+
+/// Builds a main trace whose second column is a rotation of the first, so that the sends and
+/// receives balance.
+fn balanced_main<F: Field>() -> RowMajorMatrix<F>
+where
+    Standard: Distribution<F>,
+{
+    let mut rng = thread_rng();
+    let column: Vec<F> = (0..HEIGHT).map(|_| rng.gen()).collect();
+    let values = (0..HEIGHT)
+        .flat_map(|i| [column[i], column[(i + 1) % HEIGHT]])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn check<F, EF>(main: RowMajorMatrix<F>)
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    Standard: Distribution<EF>,
+{
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let main = Some(main.as_view());
+    let interactions = InteractionAir::<F>::all_interactions(&TestChip);
+    let perm =
+        generate_permutation_trace(&None, &main, &[], &interactions, perm_challenges).unwrap();
+    assert_eq!(
+        Some(perm.width()),
+        InteractionAir::<F>::permutation_width(&TestChip)
+    );
+    let perm_trace = PermutationTrace::new(perm.clone(), interactions.len()).unwrap();
+    let flat_perm_trace =
+        PermutationTrace::<EF>::from_base(perm_trace.flatten_to_base::<F>(), interactions.len())
+            .unwrap();
+    assert_eq!(flat_perm_trace.matrix(), &perm);
+    assert_eq!(
+        PermutationTrace::validate(&perm, interactions.len() + 1),
+        Err(PermutationTraceError::WidthMismatch {
+            expected: interactions.len() + 2,
+            actual: perm.width(),
+        })
+    );
+
+    // The running sum accumulates `mult / rlc` for every send and subtracts it for every receive.
+    // Each interaction has a single field, so its RLC is `alpha + field`.
+    let alpha = perm_challenges[0];
+    let expected: EF = (0..HEIGHT)
+        .map(|i| {
+            let row = main.as_ref().unwrap().row_slice(i);
+            EF::two() * ((alpha + row[0]).inverse() - (alpha + row[1]).inverse())
+        })
+        .sum();
+    let cumulative_sum = *perm.row_slice(HEIGHT - 1).last().unwrap();
+    assert_eq!(cumulative_sum, expected);
+
+    let perm = Some(perm.as_view());
+    check_constraints(
+        &TestChip,
+        &None,
+        &main,
+        &None,
+        &perm,
+        perm_challenges,
+        Some(cumulative_sum),
+        &[],
+    );
+    check_cumulative_sums::<_, _, _, TestBus>(&[TestChip], &[None], &[main], &[perm]);
+}
+
+#[test]
+fn test_check_degree_2_extension() {
+    check::<Goldilocks, BinomialExtensionField<Goldilocks, 2>>(balanced_main());
+}
+
+#[test]
+fn test_check_degree_4_extension() {
+    check::<BabyBear, BinomialExtensionField<BabyBear, 4>>(balanced_main());
+}
+
+#[test]
+#[should_panic(expected = "bus cumulative sum is not zero")]
+fn test_check_unbalanced_degree_2_extension() {
+    let mut main = balanced_main::<Goldilocks>();
+    main.values[0] += Goldilocks::one();
+    check::<Goldilocks, BinomialExtensionField<Goldilocks, 2>>(main);
+}
+
+#[test]
+#[should_panic(expected = "bus cumulative sum is not zero")]
+fn test_check_unbalanced_degree_4_extension() {
+    let mut main = balanced_main::<BabyBear>();
+    main.values[0] += BabyBear::one();
+    check::<BabyBear, BinomialExtensionField<BabyBear, 4>>(main);
+}
+
+#[test]
+fn test_interactions_by_bus() {
+    let interactions = InteractionAir::<BabyBear>::interactions_by_bus(&TestChip);
+    assert_eq!(interactions.keys().copied().collect::<Vec<_>>(), vec![0]);
+    assert!(matches!(
+        interactions[&0]
+            .iter()
+            .map(|(_, ty)| ty)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        [InteractionType::Receive, InteractionType::Send]
+    ));
+}
+
+/// Scales the fields of every interaction by one more than the interaction's index.
+struct IndexedBetaSource;
+
+impl BetaSource for IndexedBetaSource {
+    fn betas<EF>(
+        &self,
+        random_elements: &[EF; NUM_PERM_CHALLENGES],
+        interaction_index: usize,
+    ) -> Powers<EF>
+    where
+        EF: AbstractField,
+    {
+        Powers {
+            base: random_elements[1].clone(),
+            current: EF::from_canonical_usize(interaction_index + 1),
+        }
+    }
+}
+
+/// [`TestChip`] with its permutation constraints evaluated under [`IndexedBetaSource`].
+struct IndexedBetaChip;
+
+impl<F: Field> BaseAir<F> for IndexedBetaChip {
+    fn width(&self) -> usize {
+        BaseAir::<F>::width(&TestChip)
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for IndexedBetaChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for IndexedBetaChip {}
+
+impl<F: Field> InteractionAir<F> for IndexedBetaChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        InteractionAir::<F>::receives(&TestChip)
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        InteractionAir::<F>::sends(&TestChip)
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for IndexedBetaChip {
+    fn eval_permutation_constraints(&self, builder: &mut AB) {
+        self.eval_permutation_constraints_with_beta_source(builder, &IndexedBetaSource);
+    }
+}
+
+fn check_beta_source<B: BetaSource>(beta_source: &B) {
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let main = balanced_main::<F>();
+    let main = Some(main.as_view());
+    let interactions = InteractionAir::<F>::all_interactions(&IndexedBetaChip);
+    let perm = generate_permutation_trace_with_beta_source(
+        &None,
+        &main,
+        &[],
+        &interactions,
+        perm_challenges,
+        PermutationTraceOptions::default(),
+        beta_source,
+    )
+    .unwrap();
+    let cumulative_sum = *perm.row_slice(HEIGHT - 1).last().unwrap();
+
+    check_constraints(
+        &IndexedBetaChip,
+        &None,
+        &main,
+        &None,
+        &Some(perm.as_view()),
+        perm_challenges,
+        Some(cumulative_sum),
+        &[],
+    );
+}
+
+#[test]
+fn test_check_beta_source() {
+    check_beta_source(&IndexedBetaSource);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match")]
+fn test_check_mismatched_beta_source() {
+    check_beta_source(&DefaultBetaSource);
+}
+
+/// Sends its only column on [`TestBus`] if it's a source, or receives it if it's a sink.
+enum DirectedChip {
+    Source,
+    Sink,
+}
+
+impl<F: Field> BaseAir<F> for DirectedChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for DirectedChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for DirectedChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            Self::Source => vec![],
+            Self::Sink => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::one(),
+                TestBus,
+            )],
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            Self::Source => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::one(),
+                TestBus,
+            )],
+            Self::Sink => vec![],
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for DirectedChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for DirectedChip {}
+
+fn check_source_and_sink(
+    source_main: RowMajorMatrix<BabyBear>,
+    sink_main: RowMajorMatrix<BabyBear>,
+) {
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let chips = [DirectedChip::Source, DirectedChip::Sink];
+    let mains = [Some(source_main.as_view()), Some(sink_main.as_view())];
+    let perms = chips
+        .iter()
+        .zip(mains.iter())
+        .map(|(chip, main)| {
+            let interactions = InteractionAir::<BabyBear>::all_interactions(chip);
+            generate_permutation_trace(&None, main, &[], &interactions, perm_challenges).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    for ((chip, main), perm) in chips.iter().zip(mains.iter()).zip(perms.iter()) {
+        let cumulative_sum = *perm.row_slice(HEIGHT - 1).last().unwrap();
+        // Neither chip is balanced on its own
+        assert_ne!(cumulative_sum, EF::zero());
+        check_constraints(
+            chip,
+            &None,
+            main,
+            &None,
+            &Some(perm.as_view()),
+            perm_challenges,
+            Some(cumulative_sum),
+            &[],
+        );
+    }
+
+    let perms = perms
+        .iter()
+        .map(|perm| Some(perm.as_view()))
+        .collect::<Vec<_>>();
+    check_bus_counts::<_, _, TestBus>(&chips, &[None, None], &mains);
+    check_cumulative_sums::<_, _, _, TestBus>(&chips, &[None, None], &mains, &perms);
+    check_lookups::<_, _, TestBus>(&chips, &[None, None], &mains, &[&[], &[]]);
+}
+
+#[test]
+fn test_check_source_and_sink() {
+    let source_main = RowMajorMatrix::<BabyBear>::rand(&mut thread_rng(), HEIGHT, 1);
+    let mut sink_values = source_main.values.clone();
+    sink_values.reverse();
+    check_source_and_sink(source_main, RowMajorMatrix::new(sink_values, 1));
+}
+
+#[test]
+#[should_panic(expected = "bus cumulative sum is not zero")]
+fn test_check_unbalanced_source_and_sink() {
+    let source_main = RowMajorMatrix::<BabyBear>::rand(&mut thread_rng(), HEIGHT, 1);
+    let mut sink_values = source_main.values.clone();
+    sink_values[0] += BabyBear::one();
+    check_source_and_sink(source_main, RowMajorMatrix::new(sink_values, 1));
+}
+
+/// Sends or receives the first `num_fields` columns of its main trace.
+struct TupleChip {
+    send: bool,
+    num_fields: usize,
+}
+
+impl<F: Field> BaseAir<F> for TupleChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TupleChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> TupleChip {
+    fn interactions(&self, main_indices: &[usize]) -> Vec<Interaction<F>> {
+        vec![Interaction::new(
+            main_indices[..self.num_fields]
+                .iter()
+                .map(|&i| VirtualPairCol::single_main(i))
+                .collect(),
+            VirtualPairCol::one(),
+            TestBus,
+        )]
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for TupleChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        if self.send {
+            vec![]
+        } else {
+            self.interactions(main_indices)
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        if self.send {
+            self.interactions(main_indices)
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TupleChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TupleChip {}
+
+#[test]
+#[should_panic(expected = "TestBus bus has interactions with 1 and 2 fields")]
+fn test_check_mismatched_bus_widths() {
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let chips = [
+        TupleChip {
+            send: true,
+            num_fields: 1,
+        },
+        TupleChip {
+            send: false,
+            num_fields: 2,
+        },
+    ];
+    let main = RowMajorMatrix::<BabyBear>::rand(&mut rng, HEIGHT, 2);
+    let mains = [Some(main.as_view()), Some(main.as_view())];
+    let perms = chips
+        .iter()
+        .map(|chip| {
+            let interactions = InteractionAir::<BabyBear>::all_interactions(chip);
+            generate_permutation_trace(&None, &mains[0], &[], &interactions, perm_challenges)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let perms = perms
+        .iter()
+        .map(|perm| Some(perm.as_view()))
+        .collect::<Vec<_>>();
+    check_cumulative_sums::<_, _, _, TestBus>(&chips, &[None, None], &mains, &perms);
+}
+
+#[test]
+#[should_panic(expected = "TestBus bus has unmatched tuple")]
+fn test_check_lookups_mismatch() {
+    let source_main = RowMajorMatrix::new_col(vec![BabyBear::zero(), BabyBear::one()]);
+    let sink_main = RowMajorMatrix::new_col(vec![BabyBear::zero(), BabyBear::two()]);
+    // The multiplicities balance, but the tuples don't
+    check_bus_counts::<_, _, TestBus>(
+        &[DirectedChip::Source, DirectedChip::Sink],
+        &[None, None],
+        &[Some(source_main.as_view()), Some(sink_main.as_view())],
+    );
+    check_lookups::<_, _, TestBus>(
+        &[DirectedChip::Source, DirectedChip::Sink],
+        &[None, None],
+        &[Some(source_main.as_view()), Some(sink_main.as_view())],
+        &[&[], &[]],
+    );
+}
+
+#[test]
+#[should_panic(expected = "TestBus bus sends")]
+fn test_check_bus_counts_mismatch() {
+    let mut rng = thread_rng();
+    let source_main = RowMajorMatrix::<BabyBear>::rand(&mut rng, HEIGHT, 1);
+    let sink_main = RowMajorMatrix::<BabyBear>::rand(&mut rng, 2 * HEIGHT, 1);
+    check_bus_counts::<_, _, TestBus>(
+        &[DirectedChip::Source, DirectedChip::Sink],
+        &[None, None],
+        &[Some(source_main.as_view()), Some(sink_main.as_view())],
+    );
+}
+
+/// Sends its first column twice, with multiplicities one and two, and receives its second column
+/// with multiplicity three. With a maximum constraint degree of three, the reciprocals are summed
+/// in pairs, and with the LogUp backend, the two sends share a column.
+struct BatchedChip {
+    max_constraint_degree: Option<usize>,
+    backend: PermutationBackendKind,
+}
+
+impl<F: Field> BaseAir<F> for BatchedChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for BatchedChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for BatchedChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction::new(
+            vec![VirtualPairCol::single_main(main_indices[1])],
+            VirtualPairCol::constant(F::from_canonical_u32(3)),
+            TestBus,
+        )]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![
+            Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::one(),
+                TestBus,
+            ),
+            Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::constant(F::two()),
+                TestBus,
+            ),
+        ]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for BatchedChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1])
+    }
+
+    fn max_permutation_constraint_degree(&self) -> Option<usize> {
+        self.max_constraint_degree
+    }
+
+    fn permutation_backend(&self) -> PermutationBackendKind {
+        self.backend
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for BatchedChip {}
+
+#[test]
+fn test_check_batched_reciprocals() {
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+    let main = balanced_main::<F>();
+    let main = Some(main.as_view());
+
+    let [(width, cumulative_sum), (batched_width, batched_cumulative_sum)] =
+        [None, Some(3)].map(|max_constraint_degree| {
+            let chip = BatchedChip {
+                max_constraint_degree,
+                backend: PermutationBackendKind::Reciprocal,
+            };
+            let interactions = InteractionAir::<F>::all_interactions(&chip);
+            let perm = generate_permutation_trace_with_options(
+                &None,
+                &main,
+                &[],
+                &interactions,
+                perm_challenges,
+                PermutationTraceOptions {
+                    max_constraint_degree,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                Some(perm.width()),
+                InteractionAir::<F>::permutation_width(&chip)
+            );
+
+            let cumulative_sum = *perm.row_slice(HEIGHT - 1).last().unwrap();
+            let perm = Some(perm.as_view());
+            check_constraints(
+                &chip,
+                &None,
+                &main,
+                &None,
+                &perm,
+                perm_challenges,
+                Some(cumulative_sum),
+                &[],
+            );
+            check_cumulative_sums::<_, _, _, TestBus>(&[chip], &[None], &[main], &[perm]);
+            (perm.unwrap().width(), cumulative_sum)
+        });
+
+    // Three interactions take three reciprocal columns, or two when summed in pairs
+    assert_eq!((width, batched_width), (4, 3));
+    assert_eq!(cumulative_sum, batched_cumulative_sum);
+}
+
+/// A chip sending its first column three times, with the second column as the count and the
+/// third as the filter, so that every multiplicity has degree two.
+struct FilteredBatchedChip;
+
+impl<F: Field> BaseAir<F> for FilteredBatchedChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for FilteredBatchedChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for FilteredBatchedChip {}
+
+impl<F: Field> InteractionAir<F> for FilteredBatchedChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        (0..3)
+            .map(|_| {
+                Interaction::new(
+                    vec![VirtualPairCol::single_main(0)],
+                    VirtualPairCol::single_main(1),
+                    TestBus,
+                )
+                .with_filter(VirtualPairCol::single_main(2))
+            })
+            .collect()
+    }
+
+    fn max_permutation_constraint_degree(&self) -> Option<usize> {
+        Some(3)
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for FilteredBatchedChip {}
+
+#[test]
+fn test_check_filtered_batched_reciprocals() {
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let chip = FilteredBatchedChip;
+    let interactions = InteractionAir::<F>::all_interactions(&chip);
+    // A batch of two has degree three, with or without the filters
+    assert_eq!(
+        interaction_batches(&interactions, Some(3)),
+        vec![0..2, 2..3]
+    );
+    assert!(get_max_constraint_degree::<F, _>(&chip, 0) <= 3);
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+    let values = (0..HEIGHT)
+        .flat_map(|i| [rng.gen(), rng.gen(), F::from_bool(i % 2 == 0)])
+        .collect();
+    let main = RowMajorMatrix::new(values, 3);
+    let main = Some(main.as_view());
+    let perm = generate_permutation_trace_with_options(
+        &None,
+        &main,
+        &[],
+        &interactions,
+        perm_challenges,
+        PermutationTraceOptions {
+            max_constraint_degree: Some(3),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        Some(perm.width()),
+        InteractionAir::<F>::permutation_width(&chip)
+    );
+
+    let cumulative_sum = *perm.row_slice(HEIGHT - 1).last().unwrap();
+    check_constraints(
+        &chip,
+        &None,
+        &main,
+        &None,
+        &Some(perm.as_view()),
+        perm_challenges,
+        Some(cumulative_sum),
+        &[],
+    );
+}
+
+#[test]
+fn test_check_logup_backend() {
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+    let main = balanced_main::<F>();
+    let main = Some(main.as_view());
+
+    let chips = [
+        PermutationBackendKind::Reciprocal,
+        PermutationBackendKind::LogUp,
+    ]
+    .map(|backend| BatchedChip {
+        max_constraint_degree: None,
+        backend,
+    });
+    let perms = chips.each_ref().map(|chip| {
+        let interactions = InteractionAir::<F>::all_interactions(chip);
+        let options = PermutationTraceOptions::default();
+        let perm = match chip.backend {
+            PermutationBackendKind::Reciprocal => generate_permutation_trace_with_backend(
+                &None,
+                &main,
+                &[],
+                &interactions,
+                perm_challenges,
+                options,
+                &ReciprocalBackend,
+                &DefaultBetaSource,
+            ),
+            PermutationBackendKind::LogUp => generate_permutation_trace_with_backend(
+                &None,
+                &main,
+                &[],
+                &interactions,
+                perm_challenges,
+                options,
+                &LogUpBackend,
+                &DefaultBetaSource,
+            ),
+        }
+        .unwrap();
+        assert_eq!(
+            Some(perm.width()),
+            InteractionAir::<F>::permutation_width(chip)
+        );
+        perm
+    });
+
+    // Three interactions take three reciprocal columns, or two when the sends share one
+    assert_eq!(perms.each_ref().map(|perm| perm.width()), [4, 3]);
+    let cumulative_sums = perms
+        .each_ref()
+        .map(|perm| *perm.row_slice(HEIGHT - 1).last().unwrap());
+    assert_eq!(cumulative_sums[0], cumulative_sums[1]);
+
+    for ((chip, perm), cumulative_sum) in chips.iter().zip(perms.iter()).zip(cumulative_sums) {
+        check_constraints(
+            chip,
+            &None,
+            &main,
+            &None,
+            &Some(perm.as_view()),
+            perm_challenges,
+            Some(cumulative_sum),
+            &[],
+        );
+    }
+    // Both layouts contribute the same to the bus, so they can be checked side by side
+    check_cumulative_sums::<_, _, _, TestBus>(
+        &chips,
+        &[None, None],
+        &[main, main],
+        &perms.each_ref().map(|perm| Some(perm.as_view())),
+    );
+}
+
+#[test]
+fn test_compute_cumulative_sums() {
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen::<EF>(), rng.gen::<EF>()];
+    let chips = [DirectedChip::Source, DirectedChip::Sink];
+    let source_main = RowMajorMatrix::<BabyBear>::rand(&mut rng, HEIGHT, 1);
+    let sums = |sink_main: &RowMajorMatrix<BabyBear>| {
+        let mains = [Some(source_main.as_view()), Some(sink_main.as_view())];
+        let perms = chips
+            .iter()
+            .zip(mains.iter())
+            .map(|(chip, main)| {
+                let interactions = InteractionAir::<BabyBear>::all_interactions(chip);
+                generate_permutation_trace(&None, main, &[], &interactions, perm_challenges)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let perms = perms
+            .iter()
+            .map(|perm| Some(perm.as_view()))
+            .collect::<Vec<_>>();
+        compute_cumulative_sums(&chips, &[None, None], &mains, &perms)
+    };
+
+    let balanced = sums(&source_main);
+    assert_eq!(
+        balanced.buses.into_iter().collect::<Vec<_>>(),
+        vec![(0, EF::zero())]
+    );
+    assert_eq!(balanced.total, EF::zero());
+
+    // Receiving `x + 1` instead of `x` leaves `1 / (alpha + x) - 1 / (alpha + x + 1)` on the bus
+    let mut sink_main = source_main.clone();
+    sink_main.values[0] += BabyBear::one();
+    let unbalanced = sums(&sink_main);
+    let alpha = perm_challenges[0];
+    let x = source_main.values[0];
+    let residual = (alpha + x).inverse() - (alpha + x + BabyBear::one()).inverse();
+    assert_eq!(unbalanced.buses[&0], residual);
+    assert_eq!(unbalanced.total, residual);
+}
+
+/// Constrains its second column to double the first on every row, and the first column of the
+/// next row to equal the second.
+struct DoublingChip;
+
+impl<F: Field> BaseAir<F> for DoublingChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for DoublingChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let local: &[AB::Var] = (*local).borrow();
+        let next: &[AB::Var] = (*next).borrow();
+
+        builder.assert_eq(local[1], local[0] * AB::Expr::two());
+        builder.when_transition().assert_eq(next[0], local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for DoublingChip {}
+
+impl<F: Field> InteractionAir<F> for DoublingChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for DoublingChip {}
+
+#[test]
+fn test_collect_constraint_values() {
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    // Row `i` is `(2^i, 2^(i + 1))`
+    let values = F::two()
+        .powers()
+        .take(HEIGHT)
+        .flat_map(|x| [x, x.double()])
+        .collect();
+    let mut main = RowMajorMatrix::new(values, 2);
+    let collect = |main: &RowMajorMatrix<F>| {
+        collect_constraint_values::<F, EF, _>(
+            &DoublingChip,
+            &None,
+            &Some(main.as_view()),
+            &None,
+            &None,
+            [EF::zero(); NUM_PERM_CHALLENGES],
+            None,
+            &[],
+        )
+    };
+
+    let values = collect(&main);
+    // One row per trace row and one column per constraint
+    assert_eq!((values.height(), values.width()), (HEIGHT, 2));
+    assert!(values.values.iter().all(|value| value.is_zero()));
+
+    // Changing the first column of row 3 breaks the doubling on row 3, and the transition into it
+    main.values[3 * 2] += F::one();
+    let values = collect(&main);
+    let failing = (0..HEIGHT)
+        .flat_map(|row| (0..2).map(move |constraint| (row, constraint)))
+        .filter(|&(row, constraint)| !values.get(row, constraint).is_zero())
+        .collect::<Vec<_>>();
+    assert_eq!(failing, vec![(2, 1), (3, 0)]);
+}
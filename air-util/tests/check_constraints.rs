@@ -0,0 +1,132 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, BaseAir, PairBuilder};
+use p3_air_util::debug::{air, rap};
+use p3_baby_bear::BabyBear;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+type F = BabyBear;
+type EF = BinomialExtensionField<BabyBear, 4>;
+
+const HEIGHT: usize = 8;
+
+/// Counts up from zero in its main column. With a preprocessed column, the main column must also
+/// match it on every row.
+struct CounterChip {
+    preprocessed: bool,
+}
+
+impl<F: Field> BaseAir<F> for CounterChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for CounterChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let local: &[AB::Var] = (*local).borrow();
+        let next: &[AB::Var] = (*next).borrow();
+
+        builder.when_first_row().assert_zero(local[0]);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::one());
+
+        if self.preprocessed {
+            let preprocessed = builder.preprocessed();
+            let preprocessed_local = preprocessed.row_slice(0);
+            let preprocessed_local: &[AB::Var] = (*preprocessed_local).borrow();
+            builder.assert_eq(local[0], preprocessed_local[0]);
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for CounterChip {}
+
+impl<F: Field> InteractionAir<F> for CounterChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for CounterChip {}
+
+fn counter_trace<F: Field>() -> RowMajorMatrix<F> {
+    RowMajorMatrix::new_col((0..HEIGHT).map(F::from_canonical_usize).collect())
+}
+
+/// Checks the constraints of the chip as both an air and a rap.
+fn check(preprocessed: Option<RowMajorMatrix<F>>, main: RowMajorMatrix<F>) {
+    let chip = CounterChip {
+        preprocessed: preprocessed.is_some(),
+    };
+    let preprocessed = preprocessed.as_ref().map(|trace| trace.as_view());
+    let main = Some(main.as_view());
+
+    air::check_constraints(&chip, &preprocessed, &main, &[]);
+    rap::check_constraints::<F, EF, _>(
+        &chip,
+        &preprocessed,
+        &main,
+        &None,
+        &None,
+        [EF::zero(); 2],
+        None,
+        &[],
+    );
+}
+
+#[test]
+fn test_check_without_preprocessed() {
+    check(None, counter_trace());
+}
+
+#[test]
+fn test_check_with_preprocessed() {
+    check(Some(counter_trace()), counter_trace());
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 2")]
+fn test_check_without_preprocessed_fails() {
+    let mut main = counter_trace();
+    main.values[3] += F::one();
+    check(None, main);
+}
+
+#[test]
+#[should_panic(expected = "values didn't match on row 5")]
+fn test_check_with_preprocessed_fails() {
+    let mut preprocessed = counter_trace();
+    preprocessed.values[5] += F::one();
+    check(Some(preprocessed), counter_trace());
+}
+
+#[test]
+fn test_try_check_with_preprocessed() {
+    let chip = CounterChip { preprocessed: true };
+    let mut preprocessed = counter_trace::<F>();
+    preprocessed.values[5] += F::one();
+    let main = counter_trace::<F>();
+
+    let result = rap::try_check_constraints::<F, EF, _>(
+        &chip,
+        &Some(preprocessed.as_view()),
+        &Some(main.as_view()),
+        &None,
+        &None,
+        [EF::zero(); 2],
+        None,
+        &[],
+    );
+    assert_eq!(
+        result,
+        Err(rap::ConstraintError {
+            row: 5,
+            constraint: 2,
+            kind: rap::ConstraintKind::EveryRow,
+        })
+    );
+}
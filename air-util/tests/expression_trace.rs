@@ -0,0 +1,63 @@
+#![cfg(feature = "expression-trace")]
+
+use p3_air_util::util::{
+    ExpressionTrace, TraceEntry, TrackedFieldExpression, TrackedFieldVariable,
+};
+use p3_baby_bear::BabyBear;
+use p3_field::AbstractField;
+
+type F = BabyBear;
+
+fn main_entry(col: usize) -> TrackedFieldVariable<F, TraceEntry> {
+    TrackedFieldVariable::new(
+        F::from_canonical_usize(col + 3),
+        TraceEntry::Main { row: 0, col },
+    )
+}
+
+fn label(entry: &TraceEntry) -> String {
+    match entry {
+        TraceEntry::Main { row, col } => format!("main[{row}][{col}]"),
+        _ => format!("{entry:?}"),
+    }
+}
+
+#[test]
+fn test_expression_trace_records_operations() {
+    let (a, b) = (main_entry(0), main_entry(1));
+    let x: TrackedFieldExpression<F, TraceEntry> = (a + b) * a - F::one();
+
+    assert_eq!(x.value, F::from_canonical_u32(20));
+    assert_eq!(
+        *x.expression,
+        ExpressionTrace::Sub(
+            ExpressionTrace::Mul(
+                ExpressionTrace::Add(
+                    ExpressionTrace::Entry(a.entry).into(),
+                    ExpressionTrace::Entry(b.entry).into(),
+                )
+                .into(),
+                ExpressionTrace::Entry(a.entry).into(),
+            )
+            .into(),
+            ExpressionTrace::Constant(F::one()).into(),
+        )
+    );
+}
+
+#[test]
+fn test_expression_trace_display() {
+    let (a, b, c) = (main_entry(0), main_entry(1), main_entry(2));
+
+    let x = (a + b) * c - F::one();
+    assert_eq!(
+        x.expression.display_with(label).to_string(),
+        "(main[0][0] + main[0][1]) * main[0][2] - 1"
+    );
+
+    let x = a - (b + c) - -(a * b);
+    assert_eq!(
+        x.expression.display_with(label).to_string(),
+        "main[0][0] - (main[0][1] + main[0][2]) - -(main[0][0] * main[0][1])"
+    );
+}
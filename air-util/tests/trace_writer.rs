@@ -0,0 +1,104 @@
+#![cfg(feature = "air-logger")]
+
+use p3_air_util::{
+    write_preprocessed_trace_csv, write_trace_csv, write_trace_json, AirLogger, TraceWriteError,
+};
+use p3_baby_bear::BabyBear;
+use p3_field::{extension::BinomialExtensionField, AbstractExtensionField, AbstractField};
+use p3_matrix::dense::RowMajorMatrix;
+use serde_json::{json, Value};
+
+struct LoggedChip;
+
+impl AirLogger for LoggedChip {
+    fn preprocessed_headers(&self) -> Vec<String> {
+        vec!["selector".to_string()]
+    }
+
+    fn main_headers(&self) -> Vec<String> {
+        vec!["a".to_string(), "b, c".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![
+            ("a".to_string(), "Field".to_string(), 0..1),
+            ("b, c".to_string(), "Field".to_string(), 1..2),
+        ]
+    }
+}
+
+fn write(trace: &RowMajorMatrix<BabyBear>) -> Result<String, TraceWriteError> {
+    let mut csv = vec![];
+    write_trace_csv(&LoggedChip, trace, &mut csv)?;
+    Ok(String::from_utf8(csv).unwrap())
+}
+
+#[test]
+fn test_write_trace_csv() {
+    let trace = RowMajorMatrix::new([1, 2, 3, 4].map(BabyBear::from_canonical_u32).to_vec(), 2);
+    assert_eq!(write(&trace).unwrap(), "a,\"b, c\"\n1,2\n3,4\n");
+
+    let preprocessed = RowMajorMatrix::new_col(vec![BabyBear::one(), BabyBear::zero()]);
+    let mut csv = vec![];
+    write_preprocessed_trace_csv(&LoggedChip, &preprocessed, &mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), "selector\n1\n0\n");
+}
+
+#[test]
+fn test_write_trace_csv_width_mismatch() {
+    let trace = RowMajorMatrix::new_col(vec![BabyBear::one(); 2]);
+    assert!(matches!(
+        write(&trace),
+        Err(TraceWriteError::WidthMismatch {
+            headers: 2,
+            width: 1
+        })
+    ));
+}
+
+#[test]
+fn test_write_trace_json() {
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    let preprocessed = RowMajorMatrix::new_col(vec![BabyBear::one(), BabyBear::zero()]);
+    let main = RowMajorMatrix::new([1, 2, 3, 4].map(BabyBear::from_canonical_u32).to_vec(), 2);
+    let coeffs = |i: u32| EF::from_base_slice(&[i, 0, 0, 7].map(BabyBear::from_canonical_u32));
+    let permutation = RowMajorMatrix::new((0..4).map(coeffs).collect(), 2);
+
+    let mut buf = vec![];
+    write_trace_json(
+        &LoggedChip,
+        &Some(preprocessed.as_view()),
+        &Some(main.as_view()),
+        &Some(permutation.as_view()),
+        &mut buf,
+    )
+    .unwrap();
+    let value: Value = serde_json::from_slice(&buf).unwrap();
+
+    // Rows are keyed by the chip's headers
+    for row in value["main"].as_array().unwrap() {
+        let keys = row.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys, LoggedChip.main_headers());
+    }
+    assert_eq!(
+        value,
+        json!({
+            "preprocessed": [{ "selector": 1 }, { "selector": 0 }],
+            "main": [{ "a": 1, "b, c": 2 }, { "a": 3, "b, c": 4 }],
+            "permutation": [
+                { "permutation[0]": [0, 0, 0, 7], "running_sum": [1, 0, 0, 7] },
+                { "permutation[0]": [2, 0, 0, 7], "running_sum": [3, 0, 0, 7] },
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_write_trace_json_without_traces() {
+    let mut buf = vec![];
+    write_trace_json::<BabyBear, BabyBear, _, _>(&LoggedChip, &None, &None, &None, &mut buf)
+        .unwrap();
+    assert_eq!(buf, b"{}");
+}
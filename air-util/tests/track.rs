@@ -0,0 +1,120 @@
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::debug::rap::{
+    selector_coverage, track_constraints, unexercised_constraints, unread_columns,
+};
+use p3_air_util::util::{ColumnEntry, RowSelector};
+use p3_baby_bear::BabyBear;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+type F = BabyBear;
+type EF = BinomialExtensionField<BabyBear, 4>;
+
+const HEIGHT: usize = 8;
+
+/// Counts up from zero in its first column and squares the count in its second. The third column
+/// is deliberately left unconstrained.
+struct SquaresChip;
+
+impl<F: Field> BaseAir<F> for SquaresChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SquaresChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let local: &[AB::Var] = (*local).borrow();
+        let next: &[AB::Var] = (*next).borrow();
+
+        builder.when_first_row().assert_zero(local[0]);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + AB::Expr::one());
+        builder.assert_eq(local[1], local[0] * local[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SquaresChip {}
+
+impl<F: Field> InteractionAir<F> for SquaresChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SquaresChip {}
+
+fn squares_trace(height: usize) -> RowMajorMatrix<F> {
+    let values = (0..height)
+        .flat_map(|i| {
+            let i = F::from_canonical_usize(i);
+            [i, i * i, F::from_canonical_u32(42)]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+#[test]
+fn test_unread_columns() {
+    let main = squares_trace(HEIGHT);
+
+    let entries = track_constraints::<F, EF, _>(
+        &SquaresChip,
+        &None,
+        &Some(main.as_view()),
+        &None,
+        &None,
+        [EF::zero(); 2],
+        None,
+        &[],
+    );
+    assert!(entries.failing.is_empty());
+    assert_eq!(
+        unread_columns(0, BaseAir::<F>::width(&SquaresChip), &entries),
+        vec![ColumnEntry::Main { col: 2 }]
+    );
+}
+
+#[test]
+fn test_selector_coverage() {
+    let coverage = |height| {
+        let main = squares_trace(height);
+        selector_coverage::<F, EF, _>(
+            &SquaresChip,
+            &None,
+            &Some(main.as_view()),
+            &None,
+            &None,
+            [EF::zero(); 2],
+            None,
+            &[],
+        )
+    };
+
+    let full = coverage(HEIGHT);
+    let selectors = full
+        .iter()
+        .map(|constraint| constraint.selectors.iter().copied().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        selectors,
+        vec![
+            vec![RowSelector::FirstRow],
+            vec![RowSelector::Transition { size: 2 }],
+            vec![],
+        ]
+    );
+    let active_rows = full
+        .iter()
+        .map(|constraint| constraint.active_rows)
+        .collect::<Vec<_>>();
+    assert_eq!(active_rows, vec![1, HEIGHT - 1, HEIGHT]);
+    assert!(unexercised_constraints(&full).is_empty());
+
+    // A single row never exercises the transition constraint
+    assert_eq!(unexercised_constraints(&coverage(1)), vec![1]);
+}
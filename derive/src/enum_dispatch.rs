@@ -1,115 +1,283 @@
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::Fields;
+use syn::{parse_quote, Fields, GenericParam, Generics, Type};
 
 pub fn generate_trait_impls(
     name: &syn::Ident,
+    generics: &Generics,
     variants: &syn::punctuated::Punctuated<syn::Variant, syn::token::Comma>,
-) -> proc_macro2::TokenStream {
+) -> TokenStream {
     let variant_names: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
-    let variant_field_types: Vec<_> = variants
+    let (variant_patterns, variant_field_types): (Vec<_>, Vec<_>) = variants
         .iter()
-        .map(|variant| match &variant.fields {
-            Fields::Unnamed(fields) => &fields.unnamed.first().unwrap().ty,
-            _ => panic!("EnumDispatch only supports enum variants with a single unnamed field"),
+        .map(|variant| {
+            let ident = &variant.ident;
+            match &variant.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    (quote!(#name::#ident(chip)), &fields.unnamed[0].ty)
+                }
+                Fields::Named(fields) if fields.named.len() == 1 => {
+                    let field = &fields.named[0];
+                    let field_name = field.ident.as_ref().unwrap();
+                    (quote!(#name::#ident { #field_name: chip }), &field.ty)
+                }
+                _ => panic!(
+                    "EnumDispatch only supports enum variants with a single field, but variant {} \
+                     has {}",
+                    ident,
+                    variant.fields.len()
+                ),
+            }
         })
-        .collect();
+        .unzip();
+
+    // The trait parameters are named so that they can't clash with the enum's own generics
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let display_generics = generics.clone();
+    let base_air_generics = dispatch_generics(
+        generics,
+        Some(parse_quote!(__F: p3_field::Field)),
+        &variant_field_types,
+        quote!(p3_air::BaseAir<__F>),
+    );
+    let air_generics = dispatch_generics(
+        generics,
+        Some(parse_quote!(__AB: p3_air::AirBuilder)),
+        &variant_field_types,
+        quote!(p3_air::Air<__AB>),
+    );
+    let base_interaction_air_generics = dispatch_generics(
+        generics,
+        Some(parse_quote!(__F: p3_field::Field)),
+        &variant_field_types,
+        quote!(p3_interaction::BaseInteractionAir<__F>),
+    );
+    let interaction_air_generics = dispatch_generics(
+        generics,
+        Some(parse_quote!(__F: p3_field::Field)),
+        &variant_field_types,
+        quote!(p3_interaction::InteractionAir<__F>),
+    );
+    let rap_generics = dispatch_generics(
+        generics,
+        Some(parse_quote!(__AB: p3_interaction::InteractionAirBuilder)),
+        &variant_field_types,
+        quote!(p3_interaction::Rap<__AB>),
+    );
+    let air_logger_generics = dispatch_generics(
+        generics,
+        None,
+        &variant_field_types,
+        quote!(p3_air_util::AirLogger),
+    );
+    let chip_generics = dispatch_generics(
+        generics,
+        None,
+        &variant_field_types,
+        quote!(p3_machine::chip::Chip),
+    );
+    let (display_impl, _, display_where) = display_generics.split_for_impl();
+    let (base_air_impl, _, base_air_where) = base_air_generics.split_for_impl();
+    let (air_impl, _, air_where) = air_generics.split_for_impl();
+    let (base_interaction_air_impl, _, base_interaction_air_where) =
+        base_interaction_air_generics.split_for_impl();
+    let (interaction_air_impl, _, interaction_air_where) =
+        interaction_air_generics.split_for_impl();
+    let (rap_impl, _, rap_where) = rap_generics.split_for_impl();
+    let (air_logger_impl, _, air_logger_where) = air_logger_generics.split_for_impl();
+    let (chip_impl, _, chip_where) = chip_generics.split_for_impl();
 
     quote! {
-        impl core::fmt::Display for #name {
+        impl #display_impl #name #ty_generics #display_where {
+            /// The indices of the buses the chip interacts on, in increasing order.
+            pub fn buses_used<__F: p3_field::Field>(&self) -> alloc::vec::Vec<usize>
+            where
+                Self: p3_interaction::InteractionAir<__F>,
+            {
+                p3_interaction::InteractionAir::<__F>::interactions_by_bus(self)
+                    .into_keys()
+                    .collect()
+            }
+        }
+
+        impl #display_impl core::fmt::Display for #name #ty_generics #display_where {
             fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
                 match self {
-                    #(#name::#variant_names(_) => write!(f, stringify!(#variant_names)),)*
+                    #(#name::#variant_names { .. } => write!(f, stringify!(#variant_names)),)*
                 }
             }
         }
 
-        impl<F: p3_field::Field> p3_air::BaseAir<F> for #name {
+        impl #base_air_impl p3_air::BaseAir<__F> for #name #ty_generics #base_air_where {
             fn width(&self) -> usize {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air::BaseAir<F>>::width(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air::BaseAir<__F>>::width(chip),)*
                 }
             }
 
-            fn preprocessed_trace(&self) -> Option<p3_matrix::dense::RowMajorMatrix<F>> {
+            fn preprocessed_trace(&self) -> Option<p3_matrix::dense::RowMajorMatrix<__F>> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air::BaseAir<F>>::preprocessed_trace(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air::BaseAir<__F>>::preprocessed_trace(chip),)*
                 }
             }
         }
 
-        impl<AB: p3_air::AirBuilder> p3_air::Air<AB> for #name {
-            fn eval(&self, builder: &mut AB) {
+        impl #air_impl p3_air::Air<__AB> for #name #ty_generics #air_where {
+            fn eval(&self, builder: &mut __AB) {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air::Air<AB>>::eval(chip, builder),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air::Air<__AB>>::eval(chip, builder),)*
                 }
             }
         }
 
-        impl<F: p3_field::Field> p3_interaction::BaseInteractionAir<F> for #name {
-            fn receives_from_indices(&self, preprocessed_indices: &[usize], main_indices: &[usize]) -> alloc::vec::Vec<p3_interaction::Interaction<F>> {
+        impl #base_interaction_air_impl p3_interaction::BaseInteractionAir<__F> for #name #ty_generics #base_interaction_air_where {
+            fn receives_from_indices(&self, preprocessed_indices: &[usize], main_indices: &[usize]) -> alloc::vec::Vec<p3_interaction::Interaction<__F>> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_interaction::BaseInteractionAir<F>>::receives_from_indices(chip, preprocessed_indices, main_indices),)*
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::BaseInteractionAir<__F>>::receives_from_indices(chip, preprocessed_indices, main_indices),)*
                 }
             }
 
-            fn sends_from_indices(&self, preprocessed_indices: &[usize], main_indices: &[usize]) -> alloc::vec::Vec<p3_interaction::Interaction<F>> {
+            fn sends_from_indices(&self, preprocessed_indices: &[usize], main_indices: &[usize]) -> alloc::vec::Vec<p3_interaction::Interaction<__F>> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_interaction::BaseInteractionAir<F>>::sends_from_indices(chip, preprocessed_indices, main_indices),)*
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::BaseInteractionAir<__F>>::sends_from_indices(chip, preprocessed_indices, main_indices),)*
                 }
             }
         }
 
-        impl<F: p3_field::Field> p3_interaction::InteractionAir<F> for #name {
-            fn receives(&self) -> alloc::vec::Vec<p3_interaction::Interaction<F>> {
+        impl #interaction_air_impl p3_interaction::InteractionAir<__F> for #name #ty_generics #interaction_air_where {
+            fn receives(&self) -> alloc::vec::Vec<p3_interaction::Interaction<__F>> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::receives(chip),)*
+                }
+            }
+
+            fn sends(&self) -> alloc::vec::Vec<p3_interaction::Interaction<__F>> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::sends(chip),)*
+                }
+            }
+
+            fn interaction_bus_max(&self) -> Option<usize> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::interaction_bus_max(chip),)*
+                }
+            }
+
+            fn main_rotations(&self) -> alloc::vec::Vec<usize> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::main_rotations(chip),)*
+                }
+            }
+
+            fn main_ext_width(&self) -> usize {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::main_ext_width(chip),)*
+                }
+            }
+
+            fn max_permutation_constraint_degree(&self) -> Option<usize> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_interaction::InteractionAir<F>>::receives(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::max_permutation_constraint_degree(chip),)*
                 }
             }
 
-            fn sends(&self) -> alloc::vec::Vec<p3_interaction::Interaction<F>> {
+            fn permutation_backend(&self) -> p3_interaction::PermutationBackendKind {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_interaction::InteractionAir<F>>::sends(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::InteractionAir<__F>>::permutation_backend(chip),)*
                 }
             }
         }
 
-        impl<AB: p3_interaction::InteractionAirBuilder> p3_interaction::Rap<AB> for #name {
+        impl #rap_impl p3_interaction::Rap<__AB> for #name #ty_generics #rap_where {
             fn preprocessed_width(&self) -> usize {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_interaction::Rap<AB>>::preprocessed_width(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::Rap<__AB>>::preprocessed_width(chip),)*
+                }
+            }
+
+            fn eval_permutation_constraints(&self, builder: &mut __AB) {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_interaction::Rap<__AB>>::eval_permutation_constraints(chip, builder),)*
                 }
             }
         }
 
         #[cfg(feature = "air-logger")]
-        impl p3_air_util::AirLogger for #name {
+        impl #air_logger_impl p3_air_util::AirLogger for #name #ty_generics #air_logger_where {
             fn preprocessed_headers(&self) -> alloc::vec::Vec<String> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air_util::AirLogger>::preprocessed_headers(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air_util::AirLogger>::preprocessed_headers(chip),)*
                 }
             }
 
             fn main_headers(&self) -> alloc::vec::Vec<String> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air_util::AirLogger>::main_headers(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air_util::AirLogger>::main_headers(chip),)*
                 }
             }
 
             #[cfg(feature = "schema")]
             fn preprocessed_headers_and_types(&self) -> alloc::vec::Vec<(String, String, core::ops::Range<usize>)> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air_util::AirLogger>::preprocessed_headers_and_types(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air_util::AirLogger>::preprocessed_headers_and_types(chip),)*
                 }
             }
 
             #[cfg(feature = "schema")]
             fn main_headers_and_types(&self) -> alloc::vec::Vec<(String, String, core::ops::Range<usize>)> {
                 match self {
-                    #(#name::#variant_names(chip) => <#variant_field_types as p3_air_util::AirLogger>::main_headers_and_types(chip),)*
+                    #(#variant_patterns => <#variant_field_types as p3_air_util::AirLogger>::main_headers_and_types(chip),)*
                 }
             }
         }
 
-        impl p3_machine::chip::Chip for #name {}
+        impl #chip_impl p3_machine::chip::Chip for #name #ty_generics #chip_where {
+            fn public_value_range(&self) -> Option<core::ops::Range<usize>> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_machine::chip::Chip>::public_value_range(chip),)*
+                }
+            }
+
+            fn is_real_column(&self) -> Option<usize> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_machine::chip::Chip>::is_real_column(chip),)*
+                }
+            }
+
+            fn committed_width(&self) -> Option<usize> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_machine::chip::Chip>::committed_width(chip),)*
+                }
+            }
+
+            fn main_segment_widths(&self) -> Option<alloc::vec::Vec<usize>> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_machine::chip::Chip>::main_segment_widths(chip),)*
+                }
+            }
+
+            fn check_preprocessed_inputs(&self) -> Result<(), p3_machine::error::TraceGenError> {
+                match self {
+                    #(#variant_patterns => <#variant_field_types as p3_machine::chip::Chip>::check_preprocessed_inputs(chip),)*
+                }
+            }
+        }
+    }
+}
+
+/// Returns the enum's generics with `param` appended, bounding the type of every variant by
+/// `bound`.
+fn dispatch_generics(
+    generics: &Generics,
+    param: Option<GenericParam>,
+    variant_types: &[&Type],
+    bound: TokenStream,
+) -> Generics {
+    let mut generics = generics.clone();
+    generics.params.extend(param);
+    let where_clause = generics.make_where_clause();
+    for ty in variant_types {
+        where_clause.predicates.push(parse_quote!(#ty: #bound));
     }
+    generics
 }
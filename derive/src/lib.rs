@@ -27,17 +27,13 @@ pub fn bus_derive(input: TokenStream) -> TokenStream {
         _ => panic!("Bus can only be derived for enums"),
     };
     let variant_names: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
-    let variant_discriminants: Vec<_> = variants
-        .iter()
-        .map(|variant| {
-            if let Some((_, expr)) = &variant.discriminant {
-                expr
-            } else {
-                panic!("All enum variants must have an explicit discriminant");
-            }
-        })
-        .collect();
+    if let Some(variant) = variants.iter().find(|variant| !variant.fields.is_empty()) {
+        panic!("Bus variant {} must not have fields", variant.ident);
+    }
+    let num_variants = variants.len();
 
+    // Bus indices are the enum's discriminants, so the compiler assigns the implicit ones and
+    // rejects duplicates
     let expanded = quote! {
         impl core::fmt::Display for #name {
             fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -50,13 +46,24 @@ pub fn bus_derive(input: TokenStream) -> TokenStream {
         impl From<usize> for #name {
             fn from(value: usize) -> Self {
                 match value {
-                    #(#variant_discriminants => Self::#variant_names,)*
-                    _ => panic!("Invalid value for enum #name"),
+                    #(value if value == #name::#variant_names as usize => Self::#variant_names,)*
+                    _ => panic!(concat!("Invalid value for enum ", stringify!(#name))),
                 }
             }
         }
 
-        impl p3_interaction::Bus for #name {}
+        impl p3_interaction::Bus for #name {
+            fn index(&self) -> usize {
+                match self {
+                    #(#name::#variant_names => #name::#variant_names as usize,)*
+                }
+            }
+        }
+
+        impl #name {
+            /// All buses, in declaration order.
+            pub const ALL: [#name; #num_variants] = [#(#name::#variant_names),*];
+        }
     };
 
     TokenStream::from(expanded)
@@ -72,7 +79,7 @@ pub fn enum_dispatch_derive(input: TokenStream) -> TokenStream {
         _ => panic!("EnumDispatch can only be derived for enums"),
     };
 
-    let trait_impls = generate_trait_impls(&name, &variants);
+    let trait_impls = generate_trait_impls(&name, &input.generics, &variants);
 
     TokenStream::from(trait_impls)
 }
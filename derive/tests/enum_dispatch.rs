@@ -0,0 +1,124 @@
+extern crate alloc;
+
+use core::fmt::{self, Display, Formatter};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_derive::EnumDispatch;
+use p3_field::Field;
+use p3_interaction::{
+    BaseInteractionAir, InteractionAir, InteractionAirBuilder, PermutationBackendKind, Rap,
+};
+use p3_machine::{
+    chip::{chip_preprocessed_trace, Chip},
+    error::TraceGenError,
+};
+
+const TUNED_ERROR: TraceGenError = TraceGenError::CapacityExceeded {
+    rows: 2,
+    capacity: 1,
+};
+
+/// A chip overriding the defaults of the chip traits, which enums must forward.
+#[derive(Clone, Debug)]
+struct TunedChip;
+
+impl Display for TunedChip {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Tuned")
+    }
+}
+
+impl<F: Field> BaseAir<F> for TunedChip {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TunedChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for TunedChip {}
+
+impl<F: Field> InteractionAir<F> for TunedChip {
+    fn interaction_bus_max(&self) -> Option<usize> {
+        Some(5)
+    }
+
+    fn main_rotations(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    fn max_permutation_constraint_degree(&self) -> Option<usize> {
+        Some(3)
+    }
+
+    fn permutation_backend(&self) -> PermutationBackendKind {
+        PermutationBackendKind::LogUp
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TunedChip {}
+
+#[cfg(feature = "air-logger")]
+impl p3_air_util::AirLogger for TunedChip {
+    fn main_headers(&self) -> Vec<String> {
+        (0..3).map(|i| format!("col_{}", i)).collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        (0..3)
+            .map(|i| (format!("col_{}", i), "Field".to_string(), i..i + 1))
+            .collect()
+    }
+}
+
+impl Chip for TunedChip {
+    fn is_real_column(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn committed_width(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn main_segment_widths(&self) -> Option<Vec<usize>> {
+        Some(vec![1, 1])
+    }
+
+    fn check_preprocessed_inputs(&self) -> Result<(), TraceGenError> {
+        Err(TUNED_ERROR)
+    }
+}
+
+#[derive(Clone, Debug, EnumDispatch)]
+enum TunedChips {
+    Tuned(TunedChip),
+}
+
+#[test]
+fn test_enum_dispatch_forwards_overrides() {
+    let chip = TunedChips::Tuned(TunedChip);
+    assert_eq!(
+        InteractionAir::<BabyBear>::interaction_bus_max(&chip),
+        Some(5)
+    );
+    assert_eq!(InteractionAir::<BabyBear>::main_rotations(&chip), vec![2]);
+    assert_eq!(
+        InteractionAir::<BabyBear>::max_permutation_constraint_degree(&chip),
+        Some(3)
+    );
+    assert_eq!(
+        InteractionAir::<BabyBear>::permutation_backend(&chip),
+        PermutationBackendKind::LogUp
+    );
+    assert_eq!(chip.is_real_column(), Some(0));
+    assert_eq!(chip.committed_width(), Some(2));
+    assert_eq!(chip.main_segment_widths(), Some(vec![1, 1]));
+    assert!(matches!(
+        chip_preprocessed_trace::<BabyBear, _>(&chip),
+        Err(error) if error == TUNED_ERROR
+    ));
+}
@@ -1,7 +1,223 @@
 extern crate alloc;
 
+use core::fmt::{self, Debug, Display, Formatter};
+use core::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_baby_bear::BabyBear;
 #[cfg(feature = "air-logger")]
 use p3_derive::Columnar;
+use p3_derive::{Bus, EnumDispatch};
+use p3_field::Field;
+use p3_interaction::{
+    BaseInteractionAir, Bus as _, Interaction, InteractionAir, InteractionAirBuilder, Rap,
+};
+use p3_machine::chip::Chip;
+
+#[test]
+fn test_bus() {
+    #[derive(Bus)]
+    enum MyBus {
+        Input = 0,
+        Output = 1,
+    }
+
+    assert_eq!(MyBus::Output.index(), 1);
+    assert_eq!(MyBus::from(0).index(), 0);
+    assert_eq!(MyBus::Input.to_string(), "Input");
+}
+
+/// Indices are assigned like enum discriminants.
+#[derive(Bus)]
+enum ChipBus {
+    Range,
+    Memory = 4,
+    Output,
+}
+
+#[test]
+fn test_bus_implicit_discriminants() {
+    assert_eq!(ChipBus::ALL.map(|bus| bus.index()), [0, 4, 5]);
+    assert_eq!(ChipBus::from(5).to_string(), "Output");
+}
+
+/// A chip sending its only column on each of its buses.
+#[derive(Clone, Debug)]
+struct SenderChip {
+    buses: Vec<usize>,
+}
+
+impl Display for SenderChip {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Sender")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SenderChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SenderChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for SenderChip {}
+
+impl<F: Field> InteractionAir<F> for SenderChip {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.buses
+            .iter()
+            .map(|&bus| {
+                Interaction::new(
+                    vec![VirtualPairCol::single_main(0)],
+                    VirtualPairCol::one(),
+                    ChipBus::from(bus),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SenderChip {}
+
+#[cfg(feature = "air-logger")]
+impl p3_air_util::AirLogger for SenderChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["value".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("value".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for SenderChip {}
+
+#[test]
+fn test_buses_used() {
+    #[derive(Clone, Debug, EnumDispatch)]
+    enum Chips {
+        Sender(SenderChip),
+    }
+
+    let chip = Chips::Sender(SenderChip {
+        buses: vec![ChipBus::Output.index(), ChipBus::Range.index(), 5],
+    });
+    assert_eq!(chip.buses_used::<BabyBear>(), vec![0, 5]);
+
+    // Buses nothing is wired to
+    let used = chip.buses_used::<BabyBear>();
+    let unused = ChipBus::ALL
+        .into_iter()
+        .filter(|bus| !used.contains(&bus.index()))
+        .map(|bus| bus.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(unused, vec!["Memory"]);
+}
+
+/// A chip with a lifetime and a type parameter, whose width is the number of its labels.
+#[derive(Clone, Debug)]
+struct LabeledChip<'a, T> {
+    labels: &'a [&'a str],
+    _marker: PhantomData<T>,
+}
+
+impl<T> Display for LabeledChip<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Labeled")
+    }
+}
+
+impl<T, F: Field> BaseAir<F> for LabeledChip<'_, T> {
+    fn width(&self) -> usize {
+        self.labels.len()
+    }
+}
+
+impl<T, AB: AirBuilder> Air<AB> for LabeledChip<'_, T> {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<T, F: Field> BaseInteractionAir<F> for LabeledChip<'_, T> {}
+
+impl<T, F: Field> InteractionAir<F> for LabeledChip<'_, T> {}
+
+impl<T, AB: InteractionAirBuilder> Rap<AB> for LabeledChip<'_, T> {}
+
+#[cfg(feature = "air-logger")]
+impl<T> p3_air_util::AirLogger for LabeledChip<'_, T> {
+    fn main_headers(&self) -> Vec<String> {
+        self.labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        (0..self.labels.len())
+            .map(|i| (self.labels[i].to_string(), "Field".to_string(), i..i + 1))
+            .collect()
+    }
+}
+
+impl<T: Clone + Debug> Chip for LabeledChip<'_, T> {}
+
+#[test]
+fn test_generic_enum_dispatch() {
+    #[derive(Clone, Debug, EnumDispatch)]
+    enum Chips<'a, T: Clone + Debug> {
+        First(LabeledChip<'a, T>),
+        Second(LabeledChip<'a, u8>),
+    }
+
+    fn assert_chip<C: Chip>(_chip: &C) {}
+
+    let labels = ["a", "b"];
+    let chips = [
+        Chips::First(LabeledChip::<u32> {
+            labels: &labels,
+            _marker: PhantomData,
+        }),
+        Chips::Second(LabeledChip {
+            labels: &labels[..1],
+            _marker: PhantomData,
+        }),
+    ];
+    assert_chip(&chips[0]);
+    assert_eq!(
+        chips
+            .iter()
+            .map(|chip| (chip.to_string(), BaseAir::<BabyBear>::width(chip)))
+            .collect::<Vec<_>>(),
+        vec![("First".to_string(), 2), ("Second".to_string(), 1)]
+    );
+}
+
+#[test]
+fn test_named_enum_dispatch() {
+    #[derive(Clone, Debug, EnumDispatch)]
+    enum Chips<'a> {
+        Named { chip: LabeledChip<'a, u8> },
+        Unnamed(LabeledChip<'a, u16>),
+    }
+
+    let labels = ["a", "b"];
+    let named = Chips::Named {
+        chip: LabeledChip {
+            labels: &labels,
+            _marker: PhantomData,
+        },
+    };
+    let unnamed = Chips::Unnamed(LabeledChip {
+        labels: &labels[..1],
+        _marker: PhantomData,
+    });
+    assert_eq!(named.to_string(), "Named");
+    assert_eq!(BaseAir::<BabyBear>::width(&named), 2);
+    assert_eq!(unnamed.to_string(), "Unnamed");
+    assert_eq!(BaseAir::<BabyBear>::width(&unnamed), 1);
+}
 
 #[test]
 #[cfg(feature = "air-logger")]
@@ -0,0 +1,135 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use p3_air::VirtualPairCol;
+use p3_baby_bear::BabyBear;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::AbstractField;
+use p3_interaction::{
+    batch_multiplicative_inverse_allowing_zero, generate_permutation_trace, Interaction,
+    InteractionType,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use rand::{thread_rng, Rng};
+
+type F = BabyBear;
+type EF = BinomialExtensionField<BabyBear, 4>;
+
+const MAIN_WIDTH: usize = 8;
+const WIDE_MAIN_WIDTH: usize = 64;
+const WIDE_NUM_INTERACTIONS: usize = 10;
+const LOG_HEIGHTS: [usize; 3] = [12, 14, 16];
+
+/// A chip with one send and one receive on each of two buses, each carrying half the columns.
+fn interactions() -> Vec<(Interaction<F>, InteractionType)> {
+    let half = MAIN_WIDTH / 2;
+    (0..2)
+        .flat_map(|bus| {
+            let fields = (0..half)
+                .map(|i| VirtualPairCol::single_main(bus * half + i))
+                .collect::<Vec<_>>();
+            let interaction = Interaction {
+                fields,
+                next_fields: vec![],
+                public_fields: vec![],
+                count: VirtualPairCol::constant(F::one()),
+                filter: None,
+                argument_index: bus,
+            };
+            [
+                (interaction.clone(), InteractionType::Send),
+                (interaction, InteractionType::Receive),
+            ]
+        })
+        .collect()
+}
+
+/// A chip with many interactions on many columns, where reducing the tuples dominates. Each
+/// interaction sends all but one of the columns, starting at a different column.
+fn wide_interactions() -> Vec<(Interaction<F>, InteractionType)> {
+    (0..WIDE_NUM_INTERACTIONS)
+        .map(|i| {
+            let fields = (0..WIDE_MAIN_WIDTH - 1)
+                .map(|j| VirtualPairCol::single_main((i + j) % WIDE_MAIN_WIDTH))
+                .collect::<Vec<_>>();
+            let interaction = Interaction {
+                fields,
+                next_fields: vec![],
+                public_fields: vec![],
+                count: VirtualPairCol::constant(F::one()),
+                filter: None,
+                argument_index: i,
+            };
+            (interaction, InteractionType::Send)
+        })
+        .collect()
+}
+
+fn bench_generate_permutation_trace(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let interactions = interactions();
+    let random_elements = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let mut group = c.benchmark_group("generate_permutation_trace");
+    for log_height in LOG_HEIGHTS {
+        let main = RowMajorMatrix::<F>::rand(&mut rng, 1 << log_height, MAIN_WIDTH);
+        group.bench_with_input(BenchmarkId::from_parameter(log_height), &main, |b, main| {
+            b.iter(|| {
+                generate_permutation_trace(
+                    &None,
+                    &Some(main.as_view()),
+                    &[],
+                    &interactions,
+                    random_elements,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_generate_wide_permutation_trace(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let interactions = wide_interactions();
+    let random_elements = [rng.gen::<EF>(), rng.gen::<EF>()];
+
+    let mut group = c.benchmark_group("generate_wide_permutation_trace");
+    for log_height in LOG_HEIGHTS {
+        let main = RowMajorMatrix::<F>::rand(&mut rng, 1 << log_height, WIDE_MAIN_WIDTH);
+        group.bench_with_input(BenchmarkId::from_parameter(log_height), &main, |b, main| {
+            b.iter(|| {
+                generate_permutation_trace(
+                    &None,
+                    &Some(main.as_view()),
+                    &[],
+                    &interactions,
+                    random_elements,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_batch_multiplicative_inverse_allowing_zero(c: &mut Criterion) {
+    let mut rng = thread_rng();
+
+    let mut group = c.benchmark_group("batch_multiplicative_inverse_allowing_zero");
+    for log_height in LOG_HEIGHTS {
+        let values = (0..(1 << log_height) * (interactions().len() + 1))
+            .map(|i| if i % 16 == 0 { EF::zero() } else { rng.gen() })
+            .collect::<Vec<EF>>();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(log_height),
+            &values,
+            |b, values| b.iter(|| batch_multiplicative_inverse_allowing_zero(values.clone())),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_generate_permutation_trace,
+    bench_generate_wide_permutation_trace,
+    bench_batch_multiplicative_inverse_allowing_zero
+);
+criterion_main!(benches);
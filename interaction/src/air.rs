@@ -1,16 +1,56 @@
+use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 
-use p3_air::{Air, ExtensionBuilder, PairBuilder, PermutationAirBuilder};
-use p3_field::{AbstractField, Field};
+use p3_air::{
+    Air, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
+};
+use p3_field::{AbstractField, Field, Powers};
 use p3_matrix::Matrix;
 
+use crate::backend::{LogUpBackend, PermutationBackend, PermutationBackendKind, ReciprocalBackend};
 use crate::interaction::{Interaction, InteractionType};
-use crate::util::{generate_rlc_elements, reduce_row};
+use crate::util::{
+    generate_rlc_elements, interaction_batches, interaction_bus_max, reduce_rows, BetaSource,
+    DefaultBetaSource,
+};
+use crate::NUM_PERM_CHALLENGES;
 
-pub trait InteractionAirBuilder: PermutationAirBuilder + PairBuilder {
+pub trait InteractionAirBuilder:
+    PermutationAirBuilder + PairBuilder + AirBuilderWithPublicValues
+{
     fn cumulative_sum(&self) -> Self::VarEF;
+
+    /// The extension field columns of the main trace, see [`InteractionAir::main_ext_width`].
+    /// Like the permutation trace, the matrix holds the local and next rows.
+    fn main_ext(&self) -> Self::MP;
+
+    /// Reduces the tuple of `interaction` on the current window, along with the public values it
+    /// references, to a single extension element.
+    /// Builders over packed values can override this with [`crate::reduce_rows_packed`].
+    #[allow(clippy::too_many_arguments)]
+    fn reduce_interaction(
+        &self,
+        preprocessed_local: &[Self::Var],
+        main_local: &[Self::Var],
+        preprocessed_next: &[Self::Var],
+        main_next: &[Self::Var],
+        interaction: &Interaction<Self::F>,
+        alpha: Self::ExprEF,
+        betas: Powers<Self::ExprEF>,
+    ) -> Self::ExprEF {
+        reduce_rows(
+            preprocessed_local,
+            main_local,
+            preprocessed_next,
+            main_next,
+            self.public_values(),
+            interaction,
+            alpha,
+            betas,
+        )
+    }
 }
 
 pub trait BaseInteractionAir<F>
@@ -75,6 +115,77 @@ where
             .chain(self.sends().into_iter().map(|i| (i, InteractionType::Send)))
             .collect()
     }
+
+    /// All interactions of the air grouped by their bus index, in the order of
+    /// [`Self::all_interactions`] within each bus.
+    fn interactions_by_bus(&self) -> BTreeMap<usize, Vec<(Interaction<F>, InteractionType)>> {
+        let mut interactions = BTreeMap::new();
+        for (interaction, interaction_type) in self.all_interactions() {
+            interactions
+                .entry(interaction.argument_index)
+                .or_insert_with(Vec::new)
+                .push((interaction, interaction_type));
+        }
+        interactions
+    }
+
+    /// The largest bus index the air interacts on, which sizes the per-bus RLC elements. Airs
+    /// with many interactions may override this with a cached value, which must agree with
+    /// [`Self::all_interactions`].
+    fn interaction_bus_max(&self) -> Option<usize> {
+        interaction_bus_max(&self.all_interactions())
+    }
+
+    /// Additional row offsets, each greater than one, at which the main trace is opened. The main
+    /// matrix seen by the builder holds the local and next rows followed by one row per rotation
+    /// in this order, so the row at offset `main_rotations()[j]` is `main.row_slice(2 + j)`.
+    /// Rotated rows wrap around the trace like the next row does, so constraints on them need
+    /// their own gating near the end of the trace.
+    fn main_rotations(&self) -> Vec<usize> {
+        vec![]
+    }
+
+    /// The number of extension field columns of the main trace. They're committed alongside the
+    /// base field main trace, flattened to `EF::D` base field columns each, and presented to the
+    /// air as extension variables by [`InteractionAirBuilder::main_ext`].
+    fn main_ext_width(&self) -> usize {
+        0
+    }
+
+    /// The maximum degree of the constraints on the reciprocal columns of the permutation trace.
+    /// If set, the reciprocals of consecutive interactions are summed into shared columns, see
+    /// [`crate::interaction_batches`], which narrows the permutation trace at the cost of a
+    /// higher constraint degree. `None` keeps one reciprocal column per interaction.
+    fn max_permutation_constraint_degree(&self) -> Option<usize> {
+        None
+    }
+
+    /// The width of the permutation trace in extension field elements, i.e. one reciprocal column
+    /// per interaction, or per batch of interactions, and the running sum, or the width of the
+    /// air's [`Self::permutation_backend`] layout otherwise. Matches the trace built by
+    /// [`crate::generate_permutation_trace_with_backend`].
+    fn permutation_width(&self) -> Option<usize> {
+        let interactions = self.all_interactions();
+        if interactions.is_empty() {
+            return None;
+        }
+        let max_degree = self.max_permutation_constraint_degree();
+        Some(match self.permutation_backend() {
+            PermutationBackendKind::Reciprocal => {
+                ReciprocalBackend.permutation_width(&interactions, max_degree)
+            }
+            PermutationBackendKind::LogUp => {
+                LogUpBackend.permutation_width(&interactions, max_degree)
+            }
+        })
+    }
+
+    /// The permutation argument the air's permutation trace is generated and constrained with.
+    /// The trace must be generated with the matching backend, see
+    /// [`crate::generate_permutation_trace_with_backend`].
+    fn permutation_backend(&self) -> PermutationBackendKind {
+        PermutationBackendKind::Reciprocal
+    }
 }
 
 pub trait Rap<AB>: Air<AB> + InteractionAir<AB::F>
@@ -86,16 +197,40 @@ where
         0
     }
 
-    fn permutation_width(&self) -> Option<usize> {
-        let num_interactions = self.receives().len() + self.sends().len();
-        if num_interactions > 0 {
-            Some(num_interactions + 1)
-        } else {
-            None
+    fn eval_permutation_constraints(&self, builder: &mut AB) {
+        match self.permutation_backend() {
+            PermutationBackendKind::Reciprocal => {
+                self.eval_permutation_constraints_with_beta_source(builder, &DefaultBetaSource)
+            }
+            PermutationBackendKind::LogUp => self.eval_permutation_constraints_with_backend(
+                builder,
+                &LogUpBackend,
+                &DefaultBetaSource,
+            ),
         }
     }
 
-    fn eval_permutation_constraints(&self, builder: &mut AB) {
+    /// Evaluates the permutation constraints of `backend`, which must be the backend the
+    /// permutation trace was generated with.
+    fn eval_permutation_constraints_with_backend<P, B>(
+        &self,
+        builder: &mut AB,
+        backend: &P,
+        beta_source: &B,
+    ) where
+        P: PermutationBackend,
+        B: BetaSource,
+    {
+        backend.eval_permutation_constraints(self, builder, beta_source);
+    }
+
+    /// Evaluates the permutation constraints of the [`crate::ReciprocalBackend`] with the powers of
+    /// beta from `beta_source`, which must be the source the permutation trace was generated with.
+    /// Airs using a custom source override [`Self::eval_permutation_constraints`] to call this.
+    fn eval_permutation_constraints_with_beta_source<B>(&self, builder: &mut AB, beta_source: &B)
+    where
+        B: BetaSource,
+    {
         let interactions = self.all_interactions();
         if interactions.is_empty() {
             return;
@@ -124,39 +259,96 @@ where
         let phi_local = perm_local[perm_width - 1];
         let phi_next = perm_next[perm_width - 1];
 
-        let alphas: Vec<AB::ExprEF> = generate_rlc_elements(&interactions, rand_elems[0].into());
-        let betas = rand_elems[1].into().powers();
+        let num_buses = self.interaction_bus_max().map_or(0, |max| max + 1);
+        let random_elements: [AB::ExprEF; NUM_PERM_CHALLENGES] =
+            [rand_elems[0].into(), rand_elems[1].into()];
+        let alphas = generate_rlc_elements(num_buses, random_elements[0].clone());
 
         let lhs = phi_next.into() - phi_local.into();
         let mut rhs = AB::ExprEF::zero();
         let mut phi_0 = AB::ExprEF::zero();
-        for (m, (interaction, interaction_type)) in interactions.iter().enumerate() {
-            // Reciprocal constraints
-            let rlc = reduce_row(
-                preprocessed_local,
-                main_local,
-                interaction.fields.as_slice(),
-                alphas[interaction.argument_index].clone(),
-                betas.clone(),
-            );
-            builder.assert_one_ext(rlc * perm_local[m].into());
-
-            let mult_local = interaction
-                .count
-                .apply::<AB::Expr, AB::Var>(preprocessed_local, main_local);
-            let mult_next = interaction
-                .count
-                .apply::<AB::Expr, AB::Var>(preprocessed_next, main_next);
-
-            // Build the RHS of the permutation constraint
-            match interaction_type {
-                InteractionType::Send => {
-                    phi_0 += perm_local[m].into() * mult_local;
-                    rhs += perm_next[m].into() * mult_next;
+        let batches = self
+            .max_permutation_constraint_degree()
+            .map(|max_degree| interaction_batches(&interactions, Some(max_degree)));
+        match batches {
+            None => {
+                for (m, (interaction, interaction_type)) in interactions.iter().enumerate() {
+                    // Reciprocal constraints
+                    let rlc = builder.reduce_interaction(
+                        preprocessed_local,
+                        main_local,
+                        preprocessed_next,
+                        main_next,
+                        interaction,
+                        alphas[interaction.argument_index].clone(),
+                        beta_source.betas(&random_elements, m),
+                    );
+                    builder.assert_one_ext(rlc * perm_local[m].into());
+
+                    let mult_local = interaction
+                        .multiplicity::<AB::Expr, AB::Var>(preprocessed_local, main_local);
+                    let mult_next =
+                        interaction.multiplicity::<AB::Expr, AB::Var>(preprocessed_next, main_next);
+
+                    // Build the RHS of the permutation constraint
+                    match interaction_type {
+                        InteractionType::Send => {
+                            phi_0 += perm_local[m].into() * mult_local;
+                            rhs += perm_next[m].into() * mult_next;
+                        }
+                        InteractionType::Receive => {
+                            phi_0 -= perm_local[m].into() * mult_local;
+                            rhs -= perm_next[m].into() * mult_next;
+                        }
+                    }
                 }
-                InteractionType::Receive => {
-                    phi_0 -= perm_local[m].into() * mult_local;
-                    rhs -= perm_next[m].into() * mult_next;
+            }
+            Some(batches) => {
+                for (b, batch) in batches.into_iter().enumerate() {
+                    let (rlcs, mults): (Vec<_>, Vec<_>) = batch
+                        .map(|m| {
+                            let (interaction, interaction_type) = &interactions[m];
+                            let rlc = builder.reduce_interaction(
+                                preprocessed_local,
+                                main_local,
+                                preprocessed_next,
+                                main_next,
+                                interaction,
+                                alphas[interaction.argument_index].clone(),
+                                beta_source.betas(&random_elements, m),
+                            );
+                            let mult = interaction
+                                .multiplicity::<AB::Expr, AB::Var>(preprocessed_local, main_local);
+                            let mult = match interaction_type {
+                                InteractionType::Send => mult,
+                                InteractionType::Receive => -mult,
+                            };
+                            (rlc, mult)
+                        })
+                        .unzip();
+
+                    // Batched reciprocal constraints:
+                    // h * \prod_i d_i = \sum_i m_i \prod_{j != i} d_j
+                    let denominator = rlcs.iter().cloned().product::<AB::ExprEF>();
+                    let numerator = mults
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, mult)| {
+                            rlcs.iter()
+                                .enumerate()
+                                .filter(|&(j, _)| j != i)
+                                .map(|(_, rlc)| rlc.clone())
+                                .product::<AB::ExprEF>()
+                                * mult
+                        })
+                        .sum::<AB::ExprEF>();
+                    let h_local: AB::ExprEF = perm_local[b].into();
+                    let h_next: AB::ExprEF = perm_next[b].into();
+                    builder.assert_eq_ext(h_local.clone() * denominator, numerator);
+
+                    // The multiplicities are already part of the batched columns
+                    phi_0 += h_local;
+                    rhs += h_next;
                 }
             }
         }
@@ -173,6 +365,10 @@ where
             .assert_eq_ext(*perm_local.last().unwrap(), cumulative_sum);
     }
 
+    /// Evaluates the main constraints followed by the permutation constraints. Folders combine the
+    /// constraints with powers of a random challenge in the order they are emitted, so the prover
+    /// and the verifier only agree if every builder sees the same constraints in the same order.
+    /// Chips must not emit different constraints, or reorder them, depending on the builder type.
     fn eval_all(&self, builder: &mut AB) {
         self.eval(builder);
         self.eval_permutation_constraints(builder);
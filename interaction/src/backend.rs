@@ -0,0 +1,303 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+use p3_air::ExtensionBuilder;
+use p3_field::{AbstractField, ExtensionField, Field};
+use p3_matrix::{
+    dense::{RowMajorMatrix, RowMajorMatrixView},
+    Matrix,
+};
+
+use crate::air::{InteractionAirBuilder, Rap};
+use crate::generation::{
+    generate_permutation_trace_with_beta_source, generate_reciprocals, trace_height,
+    PermutationTraceOptions, NUM_PERM_CHALLENGES,
+};
+use crate::interaction::{Interaction, InteractionType};
+use crate::util::{generate_rlc_elements, interaction_batches, logup_groups, BetaSource};
+
+/// Selects the [`PermutationBackend`] an air's permutation trace is generated and constrained
+/// with. See [`crate::InteractionAir::permutation_backend`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PermutationBackendKind {
+    #[default]
+    Reciprocal,
+    LogUp,
+}
+
+/// A permutation argument, i.e. the layout of a permutation trace, how it's generated and the
+/// constraints on it. The last column of every layout is the running sum, whose last row is the
+/// air's contribution `\sum \pm m_i / d_i` to the cumulative sum of the machine, so airs with
+/// different backends can interact on the same buses.
+pub trait PermutationBackend: Sync {
+    /// The width of the permutation trace in extension field elements, including the running sum.
+    fn permutation_width<F: Field>(
+        &self,
+        interactions: &[(Interaction<F>, InteractionType)],
+        max_constraint_degree: Option<usize>,
+    ) -> usize;
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_permutation_trace<F, EF, B>(
+        &self,
+        preprocessed: &Option<RowMajorMatrixView<F>>,
+        main: &Option<RowMajorMatrixView<F>>,
+        public_values: &[F],
+        interactions: &[(Interaction<F>, InteractionType)],
+        random_elements: [EF; NUM_PERM_CHALLENGES],
+        options: PermutationTraceOptions,
+        beta_source: &B,
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+        B: BetaSource;
+
+    fn eval_permutation_constraints<A, AB, B>(&self, air: &A, builder: &mut AB, beta_source: &B)
+    where
+        A: Rap<AB> + ?Sized,
+        AB: InteractionAirBuilder,
+        B: BetaSource;
+}
+
+/// One reciprocal column per interaction, `q_i = 1 / d_i`, whose multiplicities are applied in
+/// the running sum. With a maximum constraint degree, the reciprocals are weighted by their
+/// multiplicities and summed into batched columns instead, see [`crate::interaction_batches`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReciprocalBackend;
+
+impl PermutationBackend for ReciprocalBackend {
+    fn permutation_width<F: Field>(
+        &self,
+        interactions: &[(Interaction<F>, InteractionType)],
+        max_constraint_degree: Option<usize>,
+    ) -> usize {
+        let num_columns = match max_constraint_degree {
+            Some(max_degree) => interaction_batches(interactions, Some(max_degree)).len(),
+            None => interactions.len(),
+        };
+        num_columns + 1
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_permutation_trace<F, EF, B>(
+        &self,
+        preprocessed: &Option<RowMajorMatrixView<F>>,
+        main: &Option<RowMajorMatrixView<F>>,
+        public_values: &[F],
+        interactions: &[(Interaction<F>, InteractionType)],
+        random_elements: [EF; NUM_PERM_CHALLENGES],
+        options: PermutationTraceOptions,
+        beta_source: &B,
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+        B: BetaSource,
+    {
+        generate_permutation_trace_with_beta_source(
+            preprocessed,
+            main,
+            public_values,
+            interactions,
+            random_elements,
+            options,
+            beta_source,
+        )
+    }
+
+    fn eval_permutation_constraints<A, AB, B>(&self, air: &A, builder: &mut AB, beta_source: &B)
+    where
+        A: Rap<AB> + ?Sized,
+        AB: InteractionAirBuilder,
+        B: BetaSource,
+    {
+        air.eval_permutation_constraints_with_beta_source(builder, beta_source);
+    }
+}
+
+/// One column per group of interactions with the same tuple on the same bus, see
+/// [`crate::logup_groups`], holding `h_g = (\sum_{i \in g} \pm m_i) / d_g`. Tables receiving the
+/// same tuple many times then need a single column and a single inverse for it, and each column
+/// is constrained by `h_g * d_g = \sum_{i \in g} \pm m_i`.
+///
+/// The interactions of a group are reduced with the powers of beta of its first interaction, and
+/// the maximum constraint degree of the trace options is ignored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogUpBackend;
+
+impl PermutationBackend for LogUpBackend {
+    fn permutation_width<F: Field>(
+        &self,
+        interactions: &[(Interaction<F>, InteractionType)],
+        _max_constraint_degree: Option<usize>,
+    ) -> usize {
+        logup_groups(interactions).len() + 1
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_permutation_trace<F, EF, B>(
+        &self,
+        preprocessed: &Option<RowMajorMatrixView<F>>,
+        main: &Option<RowMajorMatrixView<F>>,
+        public_values: &[F],
+        interactions: &[(Interaction<F>, InteractionType)],
+        random_elements: [EF; NUM_PERM_CHALLENGES],
+        options: PermutationTraceOptions,
+        beta_source: &B,
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+        B: BetaSource,
+    {
+        if interactions.is_empty() || (preprocessed.is_none() && main.is_none()) {
+            return None;
+        }
+
+        let height = trace_height(preprocessed, main);
+        let groups = logup_groups(interactions);
+        let representatives = groups.iter().map(|group| group[0]).collect::<Vec<_>>();
+        let reciprocals = generate_reciprocals(
+            preprocessed,
+            main,
+            public_values,
+            interactions,
+            &representatives,
+            random_elements,
+            options,
+            beta_source,
+        );
+
+        // Row: | h_1 | h_2 | ... | h_k | \phi |
+        let width = groups.len() + 1;
+        let mut values = Vec::with_capacity(height * width);
+        let mut phi = EF::zero();
+        for (n, reciprocals) in reciprocals.rows().enumerate() {
+            let preprocessed_row = preprocessed
+                .as_ref()
+                .map(|preprocessed| {
+                    let row = preprocessed.row_slice(n);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+            let main_row = main
+                .as_ref()
+                .map(|main| {
+                    let row = main.row_slice(n);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+
+            for (group, reciprocal) in groups.iter().zip(reciprocals) {
+                let mult = group
+                    .iter()
+                    .map(|&m| {
+                        let (interaction, interaction_type) = &interactions[m];
+                        let mult = interaction
+                            .multiplicity::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
+                        match interaction_type {
+                            InteractionType::Send => mult,
+                            InteractionType::Receive => -mult,
+                        }
+                    })
+                    .sum::<F>();
+                let h = reciprocal * mult;
+                phi += h;
+                values.push(h);
+            }
+            values.push(phi);
+        }
+
+        Some(RowMajorMatrix::new(values, width))
+    }
+
+    fn eval_permutation_constraints<A, AB, B>(&self, air: &A, builder: &mut AB, beta_source: &B)
+    where
+        A: Rap<AB> + ?Sized,
+        AB: InteractionAirBuilder,
+        B: BetaSource,
+    {
+        let interactions = air.all_interactions();
+        if interactions.is_empty() {
+            return;
+        }
+        let groups = logup_groups(&interactions);
+
+        let rand_elems = builder.permutation_randomness().to_vec();
+
+        let main = builder.main();
+        let (main_local, main_next) = (main.row_slice(0), main.row_slice(1));
+        let main_local: &[AB::Var] = (*main_local).borrow();
+        let main_next: &[AB::Var] = (*main_next).borrow();
+
+        let preprocessed = builder.preprocessed();
+        let preprocessed_local = preprocessed.row_slice(0);
+        let preprocessed_next = preprocessed.row_slice(1);
+        let preprocessed_local = (*preprocessed_local).borrow();
+        let preprocessed_next = (*preprocessed_next).borrow();
+
+        let perm = builder.permutation();
+        let perm_local = perm.row_slice(0);
+        let perm_next = perm.row_slice(1);
+        let perm_local: &[AB::VarEF] = (*perm_local).borrow();
+        let perm_next: &[AB::VarEF] = (*perm_next).borrow();
+        let perm_width = perm.width();
+
+        let phi_local = perm_local[perm_width - 1];
+        let phi_next = perm_next[perm_width - 1];
+
+        let num_buses = air.interaction_bus_max().map_or(0, |max| max + 1);
+        let random_elements: [AB::ExprEF; NUM_PERM_CHALLENGES] =
+            [rand_elems[0].into(), rand_elems[1].into()];
+        let alphas = generate_rlc_elements(num_buses, random_elements[0].clone());
+
+        let lhs = phi_next.into() - phi_local.into();
+        let mut rhs = AB::ExprEF::zero();
+        let mut phi_0 = AB::ExprEF::zero();
+        for (g, group) in groups.iter().enumerate() {
+            let representative = &interactions[group[0]].0;
+            let rlc = builder.reduce_interaction(
+                preprocessed_local,
+                main_local,
+                preprocessed_next,
+                main_next,
+                representative,
+                alphas[representative.argument_index].clone(),
+                beta_source.betas(&random_elements, group[0]),
+            );
+            let mult = group
+                .iter()
+                .map(|&m| {
+                    let (interaction, interaction_type) = &interactions[m];
+                    let mult = interaction
+                        .multiplicity::<AB::Expr, AB::Var>(preprocessed_local, main_local);
+                    match interaction_type {
+                        InteractionType::Send => mult,
+                        InteractionType::Receive => -mult,
+                    }
+                })
+                .sum::<AB::Expr>();
+
+            let h_local: AB::ExprEF = perm_local[g].into();
+            let h_next: AB::ExprEF = perm_next[g].into();
+            builder.assert_eq_ext(h_local.clone() * rlc, mult);
+
+            phi_0 += h_local;
+            rhs += h_next;
+        }
+
+        let cumulative_sum = builder.cumulative_sum();
+
+        // Running sum constraints
+        builder.when_transition().assert_eq_ext(lhs, rhs);
+        builder
+            .when_first_row()
+            .assert_eq_ext(*perm_local.last().unwrap(), phi_0);
+        builder
+            .when_last_row()
+            .assert_eq_ext(*perm_local.last().unwrap(), cumulative_sum);
+    }
+}
@@ -1,3 +1,6 @@
 use core::fmt::Display;
 
-pub trait Bus: Sized + From<usize> + Display {}
+/// A named bus. Interactions on a bus use its dense index as their `argument_index`.
+pub trait Bus: Sized + From<usize> + Display {
+    fn index(&self) -> usize;
+}
@@ -1,89 +1,514 @@
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
+use core::ops::Range;
 
 use p3_field::{ExtensionField, Field};
 use p3_matrix::{
     dense::{RowMajorMatrix, RowMajorMatrixView},
     Matrix,
 };
+use p3_maybe_rayon::prelude::{current_num_threads, ParallelSliceMut};
 
+use crate::backend::PermutationBackend;
 use crate::interaction::{Interaction, InteractionType};
-use crate::util::{batch_multiplicative_inverse_allowing_zero, generate_rlc_elements, reduce_row};
+use crate::util::{
+    batch_multiplicative_inverse_allowing_zero, generate_rlc_elements, interaction_batches,
+    interaction_bus_max, precompute_betas, reduce_rows, BetaSource, DefaultBetaSource,
+};
 
 pub const NUM_PERM_CHALLENGES: usize = 2;
 
+/// Returns the height of a chip's traces. A chip's preprocessed and main traces are required to
+/// have the same height, since both are evaluated row by row on the same domain.
+pub fn trace_height<F: Field>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+) -> usize {
+    match (preprocessed.as_ref(), main.as_ref()) {
+        (Some(preprocessed), Some(main)) => {
+            assert_eq!(
+                preprocessed.height(),
+                main.height(),
+                "Preprocessed and main traces must have the same height"
+            );
+            main.height()
+        }
+        (Some(preprocessed), None) => preprocessed.height(),
+        (None, Some(main)) => main.height(),
+        (None, None) => 0,
+    }
+}
+
+/// How an interaction whose reduced value is zero, and hence has no reciprocal, is handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroReciprocalPolicy {
+    /// Leave the reciprocal as zero. The permutation argument will then fail for that row.
+    #[default]
+    AllowZero,
+    /// Panic, reporting the offending row and interaction.
+    PanicOnZero,
+}
+
+/// Traces with at least this many rows compute their reciprocal columns in parallel.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 1 << 12;
+
+/// Options for generating a permutation trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PermutationTraceOptions {
+    pub zero_policy: ZeroReciprocalPolicy,
+    /// The minimum height from which the reciprocal columns are computed in parallel. Smaller
+    /// traces are generated sequentially, where the overhead of splitting the work dominates.
+    pub parallel_threshold: usize,
+    /// If set, the reciprocals of consecutive interactions are weighted by their multiplicities
+    /// and summed into shared columns, as many as keep the degree of the constraint on each
+    /// column within the bound. Must match
+    /// [`crate::InteractionAir::max_permutation_constraint_degree`].
+    pub max_constraint_degree: Option<usize>,
+    /// The number of buses the interactions are on, i.e. one more than the largest bus index,
+    /// e.g. as cached at setup from [`crate::InteractionAir::interaction_bus_max`]. It must exceed
+    /// the bus index of every interaction. If unset, it's computed from the interactions.
+    pub num_buses: Option<usize>,
+}
+
+impl Default for PermutationTraceOptions {
+    fn default() -> Self {
+        Self {
+            zero_policy: ZeroReciprocalPolicy::default(),
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            max_constraint_degree: None,
+            num_buses: None,
+        }
+    }
+}
+
 pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
     preprocessed: &Option<RowMajorMatrixView<F>>,
     main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
+    interactions: &[(Interaction<F>, InteractionType)],
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+) -> Option<RowMajorMatrix<EF>> {
+    generate_permutation_trace_with_options(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        random_elements,
+        PermutationTraceOptions::default(),
+    )
+}
+
+pub fn generate_permutation_trace_with_options<F: Field, EF: ExtensionField<F>>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
     interactions: &[(Interaction<F>, InteractionType)],
     random_elements: [EF; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
 ) -> Option<RowMajorMatrix<EF>> {
+    generate_permutation_trace_with_beta_source(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        random_elements,
+        options,
+        &DefaultBetaSource,
+    )
+}
+
+/// Like [`generate_permutation_trace_with_options`], but combines the fields of the interactions
+/// with the powers of beta from `beta_source`. The air must evaluate its permutation constraints
+/// with the same source, see [`crate::Rap::eval_permutation_constraints_with_beta_source`].
+pub fn generate_permutation_trace_with_beta_source<F, EF, B>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
+    interactions: &[(Interaction<F>, InteractionType)],
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
+    beta_source: &B,
+) -> Option<RowMajorMatrix<EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    B: BetaSource,
+{
     if interactions.is_empty() || (preprocessed.is_none() && main.is_none()) {
         return None;
     }
 
-    let height = preprocessed
-        .as_ref()
-        .map(|mat| mat.height())
-        .max(main.as_ref().map(|mat| mat.height()))
-        .unwrap();
-
-    let alphas = generate_rlc_elements(interactions, random_elements[0]);
-    let betas = random_elements[1].powers();
+    let height = trace_height(preprocessed, main);
 
     // Compute the reciprocal columns
     //
-    // Row: | q_1 | q_2 | q_3 | ... | q_n | \phi |
+    // Row: | q_1 | q_2 | q_3 | ... | q_n |
     // * q_i = \frac{1}{\alpha^i + \sum_j \beta^j * f_{i,j}}
     // * f_{i,j} is the jth main trace column for the ith interaction
-    // * \phi is the running sum
     //
-    // Note: We can optimize this by combining several reciprocal columns into one (the
-    // number is subject to a target constraint degree).
-    let perm_width = interactions.len() + 1;
-    let mut perm_values = Vec::with_capacity(height * perm_width);
+    // With a maximum constraint degree, the reciprocals are then combined into batched columns,
+    // see below.
+    let all_interactions = (0..interactions.len()).collect::<Vec<_>>();
+    let perm = generate_reciprocals(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        &all_interactions,
+        random_elements,
+        options,
+        beta_source,
+    );
 
-    for n in 0..height {
-        let preprocessed_row = preprocessed
-            .as_ref()
-            .map(|preprocessed| {
-                let row = preprocessed.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
-        let main_row = main
-            .as_ref()
-            .map(|main| {
-                let row = main.row_slice(n);
-                let row: &[_] = (*row).borrow();
-                row.to_vec()
-            })
-            .unwrap_or_default();
+    // Compute the running sum column, and combine the reciprocals when batching
+    //
+    // Row: | h_1 | h_2 | ... | h_k | \phi |
+    // * h_b = \sum_{i \in b} \pm m_i * q_i over the interactions in the bth batch, see
+    //   [`interaction_batches`]
+    // * m_i is the multiplicity of the ith interaction, added for sends and subtracted for receives
+    let batches = options
+        .max_constraint_degree
+        .map(|max_degree| interaction_batches(interactions, Some(max_degree)));
+    let width = batches
+        .as_ref()
+        .map_or(interactions.len() + 1, |batches| batches.len() + 1);
+    let mut values = Vec::with_capacity(height * width);
+    let mut phi = EF::zero();
+    for (n, perm_row) in perm.rows().enumerate() {
+        let reciprocals: Vec<_> = perm_row.collect();
+        phi += push_permutation_row(
+            preprocessed,
+            main,
+            interactions,
+            batches.as_deref(),
+            n,
+            reciprocals,
+            &mut values,
+        );
+        values.push(phi);
+    }
 
-        let mut row = vec![EF::zero(); perm_width];
-        for (m, (interaction, _)) in interactions.iter().enumerate() {
-            let alpha_m = alphas[interaction.argument_index];
-            row[m] = reduce_row(
-                preprocessed_row.as_slice(),
-                main_row.as_slice(),
-                &interaction.fields,
-                alpha_m,
-                betas.clone(),
-            );
+    Some(RowMajorMatrix::new(values, width))
+}
+
+/// Like [`generate_permutation_trace_with_beta_source`], but updates `previous`, the permutation
+/// trace generated with the same interactions, challenges and options before the rows in
+/// `dirty_rows` of the preprocessed or main trace changed. Only the reciprocals of the dirty rows,
+/// and of the rows before them if an interaction reads the next row, are recomputed, and the
+/// running sum is rerun from the first of them. The result is identical to regenerating the trace.
+///
+/// Only supports the layout of [`crate::ReciprocalBackend`].
+#[allow(clippy::too_many_arguments)]
+pub fn generate_permutation_trace_incremental<F, EF, B>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
+    interactions: &[(Interaction<F>, InteractionType)],
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
+    beta_source: &B,
+    previous: RowMajorMatrix<EF>,
+    dirty_rows: &[Range<usize>],
+) -> RowMajorMatrix<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    B: BetaSource,
+{
+    let height = trace_height(preprocessed, main);
+    let batches = options
+        .max_constraint_degree
+        .map(|max_degree| interaction_batches(interactions, Some(max_degree)));
+    let width = batches
+        .as_ref()
+        .map_or(interactions.len() + 1, |batches| batches.len() + 1);
+    assert_eq!(
+        previous.height(),
+        height,
+        "Permutation trace height mismatch"
+    );
+    assert_eq!(previous.width(), width, "Permutation trace width mismatch");
+
+    // A row's reciprocals also depend on the next row if an interaction references it
+    let uses_next_row = interactions
+        .iter()
+        .any(|(interaction, _)| interaction.uses_next_row());
+    let mut rows = BTreeSet::new();
+    for range in dirty_rows {
+        assert!(range.end <= height, "Dirty rows out of bounds");
+        for n in range.clone() {
+            rows.insert(n);
+            if uses_next_row {
+                rows.insert((n + height - 1) % height);
+            }
         }
-        perm_values.extend(row);
     }
-    // TODO: Switch to batch_multiplicative_inverse (not allowing zero)?
-    // Zero should be vanishingly unlikely if properly randomized?
-    let perm_values = batch_multiplicative_inverse_allowing_zero(perm_values);
-    let mut perm = RowMajorMatrix::new(perm_values, perm_width);
+    let Some(&first) = rows.first() else {
+        return previous;
+    };
+    let rows = rows.into_iter().collect::<Vec<_>>();
 
-    // Compute the running sum column
-    let mut phi = vec![EF::zero(); perm.height()];
-    for (n, perm_row) in perm.rows().enumerate() {
-        let preprocessed_row = preprocessed
+    let all_interactions = (0..interactions.len()).collect::<Vec<_>>();
+    let reducer = RowReducer::new(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        &all_interactions,
+        random_elements,
+        options,
+        beta_source,
+    );
+    let mut reduced = vec![EF::zero(); rows.len() * interactions.len()];
+    for (&n, row) in rows.iter().zip(reduced.chunks_mut(interactions.len())) {
+        reducer.reduce(n, row);
+    }
+    let reciprocals = batch_multiplicative_inverse_allowing_zero(reduced);
+
+    // Overwrite the dirty rows, keeping the sum of each row's terms
+    let mut values = previous.values;
+    let mut row_sums = BTreeMap::new();
+    let mut row = Vec::with_capacity(width);
+    for (&n, reciprocals) in rows.iter().zip(reciprocals.chunks(interactions.len())) {
+        row.clear();
+        let sum = push_permutation_row(
+            preprocessed,
+            main,
+            interactions,
+            batches.as_deref(),
+            n,
+            reciprocals.to_vec(),
+            &mut row,
+        );
+        values[n * width..(n + 1) * width - 1].copy_from_slice(&row);
+        row_sums.insert(n, sum);
+    }
+
+    // Rerun the running sum from the first dirty row. The clean rows add the same terms as
+    // before, which are the differences of the previous running sums.
+    let phi_at = |values: &[EF], n: usize| values[(n + 1) * width - 1];
+    let mut previous_phi = if first == 0 {
+        EF::zero()
+    } else {
+        phi_at(&values, first - 1)
+    };
+    let mut phi = previous_phi;
+    for n in first..height {
+        let old_phi = phi_at(&values, n);
+        phi += match row_sums.get(&n) {
+            Some(&sum) => sum,
+            None => old_phi - previous_phi,
+        };
+        previous_phi = old_phi;
+        values[(n + 1) * width - 1] = phi;
+    }
+
+    RowMajorMatrix::new(values, width)
+}
+
+/// Like [`generate_permutation_trace_with_beta_source`], but lays out the permutation trace as
+/// `backend` does. The air must evaluate its permutation constraints with the same backend, see
+/// [`crate::Rap::eval_permutation_constraints_with_backend`].
+#[allow(clippy::too_many_arguments)]
+pub fn generate_permutation_trace_with_backend<F, EF, P, B>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
+    interactions: &[(Interaction<F>, InteractionType)],
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
+    backend: &P,
+    beta_source: &B,
+) -> Option<RowMajorMatrix<EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    P: PermutationBackend,
+    B: BetaSource,
+{
+    backend.generate_permutation_trace(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        random_elements,
+        options,
+        beta_source,
+    )
+}
+
+/// Returns the reciprocals of the reduced tuples of the interactions at `indices` on every row,
+/// with a column per index. Each interaction is reduced with its own powers of beta from
+/// `beta_source`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate_reciprocals<F, EF, B>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    public_values: &[F],
+    interactions: &[(Interaction<F>, InteractionType)],
+    indices: &[usize],
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
+    beta_source: &B,
+) -> RowMajorMatrix<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    B: BetaSource,
+{
+    let height = trace_height(preprocessed, main);
+    let reducer = RowReducer::new(
+        preprocessed,
+        main,
+        public_values,
+        interactions,
+        indices,
+        random_elements,
+        options,
+        beta_source,
+    );
+
+    // The rows are independent, so they're split into chunks that are reduced and inverted in
+    // parallel. Traces below the threshold are handled as a single chunk.
+    let perm_width = indices.len();
+    let mut perm_values = vec![EF::zero(); height * perm_width];
+    let rows_per_chunk = if height < options.parallel_threshold {
+        height.max(1)
+    } else {
+        height.div_ceil(current_num_threads())
+    };
+
+    perm_values
+        .par_chunks_mut(rows_per_chunk * perm_width)
+        .enumerate()
+        .for_each(|(chunk, chunk_values)| {
+            for (i, row) in chunk_values.chunks_mut(perm_width).enumerate() {
+                reducer.reduce(chunk * rows_per_chunk + i, row);
+            }
+            // Reductions are only checked for zeros under `PanicOnZero`
+            let inverses = batch_multiplicative_inverse_allowing_zero(chunk_values.to_vec());
+            chunk_values.copy_from_slice(&inverses);
+        });
+    RowMajorMatrix::new(perm_values, perm_width)
+}
+
+/// Pushes the columns of row `n` of the permutation trace besides the running sum, given the
+/// reciprocals of all the interactions on that row, and returns the sum of its terms.
+fn push_permutation_row<F, EF>(
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    main: &Option<RowMajorMatrixView<F>>,
+    interactions: &[(Interaction<F>, InteractionType)],
+    batches: Option<&[Range<usize>]>,
+    n: usize,
+    reciprocals: Vec<EF>,
+    values: &mut Vec<EF>,
+) -> EF
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    let preprocessed_row = preprocessed
+        .as_ref()
+        .map(|preprocessed| {
+            let row = preprocessed.row_slice(n);
+            let row: &[_] = (*row).borrow();
+            row.to_vec()
+        })
+        .unwrap_or_default();
+    let main_row = main
+        .as_ref()
+        .map(|main| {
+            let row = main.row_slice(n);
+            let row: &[_] = (*row).borrow();
+            row.to_vec()
+        })
+        .unwrap_or_default();
+
+    let terms = interactions
+        .iter()
+        .zip(reciprocals.iter())
+        .map(|((interaction, interaction_type), &reciprocal)| {
+            let mult =
+                interaction.multiplicity::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
+            match interaction_type {
+                InteractionType::Send => reciprocal * mult,
+                InteractionType::Receive => -reciprocal * mult,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    match batches {
+        Some(batches) => values.extend(
+            batches
+                .iter()
+                .map(|batch| terms[batch.clone()].iter().copied().sum::<EF>()),
+        ),
+        None => values.extend(reciprocals),
+    }
+    terms.into_iter().sum()
+}
+
+/// Reduces the tuples of the interactions at `indices` on single rows, before they're inverted.
+struct RowReducer<'a, F: Field, EF> {
+    preprocessed: &'a Option<RowMajorMatrixView<'a, F>>,
+    main: &'a Option<RowMajorMatrixView<'a, F>>,
+    public_values: &'a [F],
+    interactions: &'a [(Interaction<F>, InteractionType)],
+    indices: &'a [usize],
+    alphas: Vec<EF>,
+    /// The powers of beta of each index, computed once for all the rows
+    betas: Vec<Vec<EF>>,
+    uses_next_row: bool,
+    zero_policy: ZeroReciprocalPolicy,
+    height: usize,
+}
+
+impl<'a, F, EF> RowReducer<'a, F, EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    #[allow(clippy::too_many_arguments)]
+    fn new<B: BetaSource>(
+        preprocessed: &'a Option<RowMajorMatrixView<'a, F>>,
+        main: &'a Option<RowMajorMatrixView<'a, F>>,
+        public_values: &'a [F],
+        interactions: &'a [(Interaction<F>, InteractionType)],
+        indices: &'a [usize],
+        random_elements: [EF; NUM_PERM_CHALLENGES],
+        options: PermutationTraceOptions,
+        beta_source: &B,
+    ) -> Self {
+        let num_buses = match options.num_buses {
+            Some(num_buses) => {
+                debug_assert!(
+                    interaction_bus_max(interactions) < Some(num_buses),
+                    "Interactions are on buses beyond the given number of buses"
+                );
+                num_buses
+            }
+            None => interaction_bus_max(interactions).map_or(0, |max| max + 1),
+        };
+        Self {
+            preprocessed,
+            main,
+            public_values,
+            interactions,
+            indices,
+            alphas: generate_rlc_elements(num_buses, random_elements[0]),
+            betas: precompute_betas(interactions, indices, &random_elements, beta_source),
+            uses_next_row: indices.iter().any(|&m| interactions[m].0.uses_next_row()),
+            zero_policy: options.zero_policy,
+            height: trace_height(preprocessed, main),
+        }
+    }
+
+    /// Writes the reduced tuples on row `n` to `row`, with a column per index.
+    fn reduce(&self, n: usize, row: &mut [EF]) {
+        let preprocessed_row = self
+            .preprocessed
             .as_ref()
             .map(|preprocessed| {
                 let row = preprocessed.row_slice(n);
@@ -91,7 +516,8 @@ pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
                 row.to_vec()
             })
             .unwrap_or_default();
-        let main_row = main
+        let main_row = self
+            .main
             .as_ref()
             .map(|main| {
                 let row = main.row_slice(n);
@@ -99,29 +525,49 @@ pub fn generate_permutation_trace<F: Field, EF: ExtensionField<F>>(
                 row.to_vec()
             })
             .unwrap_or_default();
-        let perm_row: Vec<_> = perm_row.collect();
+        // The next row is only fetched if an interaction references it. It wraps around on the
+        // last row, matching the evaluation of the constraints on the cyclic subgroup.
+        let (preprocessed_next_row, main_next_row) = if self.uses_next_row {
+            let n_next = (n + 1) % self.height;
+            let preprocessed_next_row = self
+                .preprocessed
+                .as_ref()
+                .map(|preprocessed| {
+                    let row = preprocessed.row_slice(n_next);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+            let main_next_row = self
+                .main
+                .as_ref()
+                .map(|main| {
+                    let row = main.row_slice(n_next);
+                    let row: &[_] = (*row).borrow();
+                    row.to_vec()
+                })
+                .unwrap_or_default();
+            (preprocessed_next_row, main_next_row)
+        } else {
+            (vec![], vec![])
+        };
 
-        if n > 0 {
-            phi[n] = phi[n - 1];
-        }
-        for (m, (interaction, interaction_type)) in interactions.iter().enumerate() {
-            let mult = interaction
-                .count
-                .apply::<F, F>(preprocessed_row.as_slice(), main_row.as_slice());
-            match interaction_type {
-                InteractionType::Send => {
-                    phi[n] += perm_row[m] * mult;
-                }
-                InteractionType::Receive => {
-                    phi[n] -= perm_row[m] * mult;
-                }
+        for (k, &m) in self.indices.iter().enumerate() {
+            let interaction = &self.interactions[m].0;
+            let alpha_m = self.alphas[interaction.argument_index];
+            row[k] = reduce_rows(
+                preprocessed_row.as_slice(),
+                main_row.as_slice(),
+                preprocessed_next_row.as_slice(),
+                main_next_row.as_slice(),
+                self.public_values,
+                interaction,
+                alpha_m,
+                self.betas[k].iter().copied(),
+            );
+            if self.zero_policy == ZeroReciprocalPolicy::PanicOnZero && row[k].is_zero() {
+                panic!("Interaction {} reduces to zero on row {}", m, n);
             }
         }
     }
-
-    for (n, row) in perm.as_view_mut().rows_mut().enumerate() {
-        *row.last_mut().unwrap() = phi[n];
-    }
-
-    Some(perm)
 }
@@ -1,7 +1,10 @@
 use alloc::vec::Vec;
+use core::ops::Mul;
 
 use p3_air::VirtualPairCol;
-use p3_field::Field;
+use p3_field::{AbstractField, Field};
+
+use crate::bus::Bus;
 
 #[derive(Clone, Debug)]
 pub enum InteractionType {
@@ -12,6 +15,67 @@ pub enum InteractionType {
 #[derive(Clone, Debug)]
 pub struct Interaction<F: Field> {
     pub fields: Vec<VirtualPairCol<F>>,
+    /// Fields applied to the next row, appended to `fields` to form the interaction tuple. On the
+    /// last row, the next row wraps around to the first.
+    pub next_fields: Vec<VirtualPairCol<F>>,
+    /// Indices of the air's public values appended to the interaction tuple after `fields` and
+    /// `next_fields`, e.g. to key a lookup by a constant of the chip's configuration.
+    pub public_fields: Vec<usize>,
     pub count: VirtualPairCol<F>,
+    /// Gates the interaction on each row, separately from its multiplicity. The interaction only
+    /// contributes on rows where the filter is non-zero, and `None` applies it on every row.
+    pub filter: Option<VirtualPairCol<F>>,
     pub argument_index: usize,
 }
+
+impl<F: Field> Interaction<F> {
+    pub fn new<B: Bus>(fields: Vec<VirtualPairCol<F>>, count: VirtualPairCol<F>, bus: B) -> Self {
+        Self {
+            fields,
+            next_fields: Vec::new(),
+            public_fields: Vec::new(),
+            count,
+            filter: None,
+            argument_index: bus.index(),
+        }
+    }
+
+    /// Only applies the interaction on rows where `filter` is non-zero.
+    pub fn with_filter(mut self, filter: VirtualPairCol<F>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Appends the public values at `public_fields` to the interaction tuple.
+    pub fn with_public_fields(mut self, public_fields: Vec<usize>) -> Self {
+        self.public_fields = public_fields;
+        self
+    }
+
+    pub fn bus<B: Bus>(&self) -> B {
+        B::from(self.argument_index)
+    }
+
+    pub fn uses_next_row(&self) -> bool {
+        !self.next_fields.is_empty()
+    }
+
+    /// The number of elements in the interaction tuple.
+    pub fn num_fields(&self) -> usize {
+        self.fields.len() + self.next_fields.len() + self.public_fields.len()
+    }
+
+    /// The multiplicity of the interaction on a row, i.e. its count multiplied by its filter.
+    pub fn multiplicity<Expr, Var>(&self, preprocessed_row: &[Var], main_row: &[Var]) -> Expr
+    where
+        F: Into<Expr>,
+        Expr: AbstractField + Mul<F, Output = Expr>,
+        Var: Into<Expr> + Copy,
+    {
+        let count = self.count.apply::<Expr, Var>(preprocessed_row, main_row);
+        match &self.filter {
+            Some(filter) => count * filter.apply::<Expr, Var>(preprocessed_row, main_row),
+            None => count,
+        }
+    }
+}
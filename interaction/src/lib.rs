@@ -3,13 +3,17 @@
 extern crate alloc;
 
 mod air;
+mod backend;
 mod bus;
 mod generation;
 mod interaction;
+mod permutation;
 mod util;
 
 pub use air::*;
+pub use backend::*;
 pub use bus::*;
 pub use generation::*;
 pub use interaction::*;
+pub use permutation::*;
 pub use util::*;
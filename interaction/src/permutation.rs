@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+
+use p3_field::{ExtensionField, Field};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+/// Why a matrix is not a valid permutation trace.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PermutationTraceError {
+    /// The trace needs one reciprocal column per interaction and the running sum.
+    WidthMismatch { expected: usize, actual: usize },
+    /// A base field trace doesn't hold a whole number of extension elements per row.
+    IncompleteExtensionElement { width: usize, degree: usize },
+    /// Traces are committed over a two-adic domain, so their height must be a power of two.
+    NonPowerOfTwoHeight { height: usize },
+}
+
+/// A permutation trace with the layout the constraints and the verifier expect: one reciprocal
+/// column per interaction followed by the running sum, over the extension field. It's committed
+/// flattened to the base field, with the `EF::D` coordinates of each column in consecutive
+/// columns.
+#[derive(Clone, Debug)]
+pub struct PermutationTrace<EF> {
+    matrix: RowMajorMatrix<EF>,
+}
+
+impl<EF: Field> PermutationTrace<EF> {
+    pub fn new(
+        matrix: RowMajorMatrix<EF>,
+        num_interactions: usize,
+    ) -> Result<Self, PermutationTraceError> {
+        Self::validate(&matrix, num_interactions)?;
+        Ok(Self { matrix })
+    }
+
+    /// Builds a permutation trace from its base field flattening, where every `EF::D` consecutive
+    /// columns hold the coordinates of one extension column.
+    pub fn from_base<F>(
+        matrix: RowMajorMatrix<F>,
+        num_interactions: usize,
+    ) -> Result<Self, PermutationTraceError>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        if matrix.width() % EF::D != 0 {
+            return Err(PermutationTraceError::IncompleteExtensionElement {
+                width: matrix.width(),
+                degree: EF::D,
+            });
+        }
+        let width = matrix.width() / EF::D;
+        let values: Vec<EF> = matrix
+            .values
+            .chunks_exact(EF::D)
+            .map(EF::from_base_slice)
+            .collect();
+        Self::new(RowMajorMatrix::new(values, width), num_interactions)
+    }
+
+    /// Checks that `matrix` is laid out as a permutation trace for `num_interactions`
+    /// interactions.
+    pub fn validate(
+        matrix: &RowMajorMatrix<EF>,
+        num_interactions: usize,
+    ) -> Result<(), PermutationTraceError> {
+        let expected = num_interactions + 1;
+        if matrix.width() != expected {
+            return Err(PermutationTraceError::WidthMismatch {
+                expected,
+                actual: matrix.width(),
+            });
+        }
+        if !matrix.height().is_power_of_two() {
+            return Err(PermutationTraceError::NonPowerOfTwoHeight {
+                height: matrix.height(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The running sum on the last row.
+    pub fn cumulative_sum(&self) -> EF {
+        *self
+            .matrix
+            .row_slice(self.matrix.height() - 1)
+            .last()
+            .unwrap()
+    }
+
+    /// The trace flattened to the base field, as it is committed.
+    pub fn flatten_to_base<F>(&self) -> RowMajorMatrix<F>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        self.matrix.flatten_to_base()
+    }
+
+    pub fn matrix(&self) -> &RowMajorMatrix<EF> {
+        &self.matrix
+    }
+
+    pub fn into_matrix(self) -> RowMajorMatrix<EF> {
+        self.matrix
+    }
+}
@@ -1,31 +1,177 @@
+use alloc::vec;
 use alloc::vec::Vec;
-use core::ops::Mul;
+use core::ops::{Mul, Range};
 
-use p3_air::VirtualPairCol;
-use p3_field::{AbstractExtensionField, AbstractField, Field, Powers};
+use p3_air::{PairCol, VirtualPairCol};
+use p3_field::{AbstractExtensionField, AbstractField, ExtensionField, Field, PackedField, Powers};
 
 use crate::interaction::{Interaction, InteractionType};
+use crate::NUM_PERM_CHALLENGES;
 
-pub fn generate_rlc_elements<F, EF>(
+/// Returns the largest bus index the interactions are sent or received on.
+pub fn interaction_bus_max<F: Field>(
     interactions: &[(Interaction<F>, InteractionType)],
-    random_element: EF,
-) -> Vec<EF>
+) -> Option<usize> {
+    interactions
+        .iter()
+        .map(|(interaction, _)| interaction.argument_index)
+        .max()
+}
+
+/// Returns one RLC element per bus, for buses `0..num_buses`.
+pub fn generate_rlc_elements<EF>(num_buses: usize, random_element: EF) -> Vec<EF>
 where
-    F: Field,
     EF: AbstractField,
 {
-    random_element
-        .powers()
-        .skip(1)
-        .take(
-            interactions
-                .iter()
-                .map(|(interaction, _)| interaction.argument_index)
-                .max()
-                .unwrap_or(0)
-                + 1,
-        )
-        .collect()
+    random_element.powers().skip(1).take(num_buses).collect()
+}
+
+/// Returns the randomness interactions are reduced with under [`DefaultBetaSource`]: one `alpha`
+/// per bus and the powers of `beta` for the fields.
+pub fn generate_rlc_randomness<EF>(
+    num_buses: usize,
+    random_elements: [EF; NUM_PERM_CHALLENGES],
+) -> (Vec<EF>, Powers<EF>)
+where
+    EF: AbstractField,
+{
+    let [alpha, beta] = random_elements;
+    (generate_rlc_elements(num_buses, alpha), beta.powers())
+}
+
+/// Returns the degrees of the denominator and the multiplicity of `interaction` in the trace
+/// columns. A filter multiplies the count, so it adds to the degree of the multiplicity.
+fn interaction_degrees<F: Field>(interaction: &Interaction<F>) -> (usize, usize) {
+    let degree = |column: &VirtualPairCol<F>| usize::from(!column.column_weights.is_empty());
+    let denominator = interaction
+        .fields
+        .iter()
+        .chain(&interaction.next_fields)
+        .map(degree)
+        .max()
+        .unwrap_or(0);
+    let multiplicity = degree(&interaction.count) + interaction.filter.as_ref().map_or(0, degree);
+    (denominator, multiplicity)
+}
+
+/// Returns the degree of the constraint on a column of a batched permutation trace, given the
+/// degrees of the denominators `d_i` and multiplicities `m_i` of the batch, see
+/// [`interaction_batches`]. The column is constrained by
+/// `h * d_1 * ... * d_k = sum_i m_i * prod_{j != i} d_j`.
+pub fn reciprocal_batch_degree(degrees: &[(usize, usize)]) -> usize {
+    let denominators = degrees.iter().map(|(d, _)| d).sum::<usize>();
+    degrees
+        .iter()
+        .map(|(d, m)| m + denominators - d)
+        .fold(denominators + 1, usize::max)
+}
+
+/// Splits the interactions into the batches that share a column of the permutation trace. A batch
+/// holds consecutive interactions on the same bus, as many as keep the degree of its constraint,
+/// see [`reciprocal_batch_degree`], within `max_constraint_degree`, so that every column can still
+/// be attributed to a single bus. An interaction whose constraint alone exceeds the degree gets a
+/// column of its own, as does every interaction without a maximum constraint degree.
+pub fn interaction_batches<F: Field>(
+    interactions: &[(Interaction<F>, InteractionType)],
+    max_constraint_degree: Option<usize>,
+) -> Vec<Range<usize>> {
+    let Some(max_constraint_degree) = max_constraint_degree else {
+        return (0..interactions.len()).map(|i| i..i + 1).collect();
+    };
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    let mut batch_degrees = vec![];
+    for (i, (interaction, _)) in interactions.iter().enumerate() {
+        let degrees = interaction_degrees(interaction);
+        match batches.last_mut() {
+            Some(batch)
+                if interactions[batch.start].0.argument_index == interaction.argument_index
+                    && reciprocal_batch_degree(
+                        &[batch_degrees.as_slice(), &[degrees]].concat(),
+                    ) <= max_constraint_degree =>
+            {
+                batch.end = i + 1;
+                batch_degrees.push(degrees);
+            }
+            _ => {
+                batches.push(i..i + 1);
+                batch_degrees = vec![degrees];
+            }
+        }
+    }
+    batches
+}
+
+/// Groups the interactions that reduce to the same value on every row, i.e. those sending or
+/// receiving the same tuple on the same bus, in the order of their first occurrence. The
+/// interactions of a group share a column of a [`crate::LogUpBackend`] permutation trace.
+pub fn logup_groups<F: Field>(
+    interactions: &[(Interaction<F>, InteractionType)],
+) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, (interaction, _)) in interactions.iter().enumerate() {
+        let group = groups.iter_mut().find(|group| {
+            let representative = &interactions[group[0]].0;
+            representative.argument_index == interaction.argument_index
+                && same_columns(&representative.fields, &interaction.fields)
+                && same_columns(&representative.next_fields, &interaction.next_fields)
+                && representative.public_fields == interaction.public_fields
+        });
+        match group {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    groups
+}
+
+/// Whether the virtual columns are the same affine combinations of the same columns, in order.
+fn same_columns<F: Field>(a: &[VirtualPairCol<F>], b: &[VirtualPairCol<F>]) -> bool {
+    let same_column = |a: &VirtualPairCol<F>, b: &VirtualPairCol<F>| {
+        a.constant == b.constant
+            && a.column_weights.len() == b.column_weights.len()
+            && a.column_weights.iter().zip(b.column_weights.iter()).all(
+                |((col_a, weight_a), (col_b, weight_b))| {
+                    weight_a == weight_b
+                        && match (col_a, col_b) {
+                            (PairCol::Preprocessed(i), PairCol::Preprocessed(j))
+                            | (PairCol::Main(i), PairCol::Main(j)) => i == j,
+                            _ => false,
+                        }
+                },
+            )
+    };
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_column(a, b))
+}
+
+/// Provides the powers of beta that the fields of each interaction are combined with. Trace
+/// generation and constraint evaluation must use the same source, or the reciprocals won't match.
+pub trait BetaSource: Sync {
+    /// Returns the powers of beta for the interaction at `interaction_index` in
+    /// [`crate::InteractionAir::all_interactions`].
+    fn betas<EF>(
+        &self,
+        random_elements: &[EF; NUM_PERM_CHALLENGES],
+        interaction_index: usize,
+    ) -> Powers<EF>
+    where
+        EF: AbstractField;
+}
+
+/// Combines the fields of every interaction with the powers of the second permutation challenge.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultBetaSource;
+
+impl BetaSource for DefaultBetaSource {
+    fn betas<EF>(
+        &self,
+        random_elements: &[EF; NUM_PERM_CHALLENGES],
+        _interaction_index: usize,
+    ) -> Powers<EF>
+    where
+        EF: AbstractField,
+    {
+        random_elements[1].clone().powers()
+    }
 }
 
 pub fn reduce_row<F, Var, Expr, ExprEF>(
@@ -33,7 +179,7 @@ pub fn reduce_row<F, Var, Expr, ExprEF>(
     main_row: &[Var],
     fields: &[VirtualPairCol<F>],
     alpha: ExprEF,
-    betas: Powers<ExprEF>,
+    betas: impl IntoIterator<Item = ExprEF>,
 ) -> ExprEF
 where
     F: Field,
@@ -49,6 +195,143 @@ where
     rlc
 }
 
+/// Reduces the tuple of an interaction whose `next_fields` reference the next row. The
+/// `next_fields` continue the powers of beta after the local `fields`, followed by the
+/// `public_fields`, which index `public_values`. `betas` is either a [`Powers`] iterator or powers
+/// precomputed once for every row, see [`precompute_betas`].
+#[allow(clippy::too_many_arguments)]
+pub fn reduce_rows<F, Var, PubVar, Expr, ExprEF>(
+    preprocessed_local: &[Var],
+    main_local: &[Var],
+    preprocessed_next: &[Var],
+    main_next: &[Var],
+    public_values: &[PubVar],
+    interaction: &Interaction<F>,
+    alpha: ExprEF,
+    betas: impl Iterator<Item = ExprEF> + Clone,
+) -> ExprEF
+where
+    F: Field,
+    Var: Into<Expr> + Copy,
+    PubVar: Into<Expr> + Copy,
+    Expr: AbstractField + From<F> + Mul<F, Output = Expr>,
+    ExprEF: AbstractExtensionField<Expr>,
+{
+    let mut rlc = reduce_row(
+        preprocessed_local,
+        main_local,
+        &interaction.fields,
+        alpha,
+        betas.clone(),
+    );
+    let mut next_betas = betas.skip(interaction.fields.len());
+    for (columns, beta) in interaction.next_fields.iter().zip(next_betas.by_ref()) {
+        rlc += beta * columns.apply::<Expr, Var>(preprocessed_next, main_next)
+    }
+    for (&index, beta) in interaction.public_fields.iter().zip(next_betas) {
+        let value: Expr = public_values[index].into();
+        rlc += beta * value
+    }
+    rlc
+}
+
+/// Packed counterpart of [`reduce_row`]. The randomness is the same on every lane, so `alpha` and
+/// `betas` are kept as scalars and each field is folded into the base coefficients of the result
+/// instead of going through packed extension multiplications.
+pub fn reduce_row_packed<F, EF, P, PE>(
+    preprocessed_row: &[P],
+    main_row: &[P],
+    fields: &[VirtualPairCol<F>],
+    alpha: EF,
+    betas: impl IntoIterator<Item = EF>,
+) -> PE
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    P: PackedField<Scalar = F>,
+    PE: AbstractExtensionField<P>,
+{
+    let alpha = alpha.as_base_slice();
+    let mut rlc = PE::from_base_fn(|i| P::from_f(alpha[i]));
+    for (columns, beta) in fields.iter().zip(betas) {
+        let value = columns.apply::<P, P>(preprocessed_row, main_row);
+        let beta = beta.as_base_slice();
+        rlc += PE::from_base_fn(|i| value * beta[i]);
+    }
+    rlc
+}
+
+/// Packed counterpart of [`reduce_rows`]. The public values are the same on every lane.
+#[allow(clippy::too_many_arguments)]
+pub fn reduce_rows_packed<F, EF, P, PE>(
+    preprocessed_local: &[P],
+    main_local: &[P],
+    preprocessed_next: &[P],
+    main_next: &[P],
+    public_values: &[F],
+    interaction: &Interaction<F>,
+    alpha: EF,
+    betas: impl Iterator<Item = EF> + Clone,
+) -> PE
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    P: PackedField<Scalar = F>,
+    PE: AbstractExtensionField<P>,
+{
+    let mut rlc = reduce_row_packed(
+        preprocessed_local,
+        main_local,
+        &interaction.fields,
+        alpha,
+        betas.clone(),
+    );
+    let mut next_betas = betas.skip(interaction.fields.len());
+    for (columns, beta) in interaction.next_fields.iter().zip(next_betas.by_ref()) {
+        let value = columns.apply::<P, P>(preprocessed_next, main_next);
+        let beta = beta.as_base_slice();
+        rlc += PE::from_base_fn(|i| value * beta[i]);
+    }
+    // The public terms are the same on every lane, so they're summed as scalars
+    if !interaction.public_fields.is_empty() {
+        let public_rlc = interaction
+            .public_fields
+            .iter()
+            .zip(next_betas)
+            .map(|(&index, beta)| beta * public_values[index])
+            .sum::<EF>();
+        let public_rlc = public_rlc.as_base_slice();
+        rlc += PE::from_base_fn(|i| P::from_f(public_rlc[i]));
+    }
+    rlc
+}
+
+/// Returns the powers of beta for each interaction at `indices`, as many as its tuple has
+/// elements. Generating traces reduces the same interactions on every row, so the powers are
+/// computed once instead of on each row.
+pub fn precompute_betas<F, EF, B>(
+    interactions: &[(Interaction<F>, InteractionType)],
+    indices: &[usize],
+    random_elements: &[EF; NUM_PERM_CHALLENGES],
+    beta_source: &B,
+) -> Vec<Vec<EF>>
+where
+    F: Field,
+    EF: AbstractField,
+    B: BetaSource,
+{
+    indices
+        .iter()
+        .map(|&m| {
+            let interaction = &interactions[m].0;
+            beta_source
+                .betas(random_elements, m)
+                .take(interaction.num_fields())
+                .collect()
+        })
+        .collect()
+}
+
 /// Calculates and returns the multiplicative inverses of each field element, with zero
 /// values remaining unchanged.
 pub fn batch_multiplicative_inverse_allowing_zero<F: Field>(values: Vec<F>) -> Vec<F> {
@@ -0,0 +1,171 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::get_quotient_degree;
+use p3_baby_bear::{BabyBear, DiffusionMatrixBabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_fri::{FriConfig, TwoAdicFriPcs};
+use p3_interaction::{BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::quotient::quotient_values;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_merkle_tree::FieldMerkleTreeMmcs;
+use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{PackedChallenge, StarkConfig, StarkGenericConfig};
+use rand::{thread_rng, Rng};
+
+type Val = BabyBear;
+type Challenge = BinomialExtensionField<Val, 4>;
+type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixBabyBear, 16, 7>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    FieldMerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel;
+type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<MyPcs, Challenge, Challenger>;
+
+const LOG_HEIGHTS: [usize; 3] = [12, 14, 16];
+
+/// A Fibonacci chip which sends its first column and receives its second column on a single bus.
+struct FibonacciChip;
+
+impl<F: Field> BaseAir<F> for FibonacciChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for FibonacciChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        builder.when_transition().assert_eq(next[0], local[1]);
+        builder
+            .when_transition()
+            .assert_eq(next[1], local[0] + local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for FibonacciChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![VirtualPairCol::single_main(main_indices[1])],
+            next_fields: vec![],
+            public_fields: vec![],
+            count: VirtualPairCol::constant(F::one()),
+            filter: None,
+            argument_index: 0,
+        }]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![VirtualPairCol::single_main(main_indices[0])],
+            next_fields: vec![],
+            public_fields: vec![],
+            count: VirtualPairCol::constant(F::one()),
+            filter: None,
+            argument_index: 0,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for FibonacciChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for FibonacciChip {}
+
+fn config() -> MyConfig {
+    let perm = Perm::new_from_rng_128(
+        Poseidon2ExternalMatrixGeneral,
+        DiffusionMatrixBabyBear::default(),
+        &mut thread_rng(),
+    );
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm);
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        num_queries: 100,
+        proof_of_work_bits: 16,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = MyPcs::new(Dft {}, val_mmcs, fri_config);
+    MyConfig::new(pcs)
+}
+
+fn bench_quotient_values(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let config = config();
+    let pcs = config.pcs();
+    let chip = FibonacciChip;
+
+    let quotient_degree = get_quotient_degree::<Val, _>(&chip, 0);
+    let perm_width = InteractionAir::<Val>::permutation_width(&chip).unwrap();
+    let perm_challenges =
+        [rng.gen::<Challenge>(), rng.gen::<Challenge>()].map(PackedChallenge::<MyConfig>::from_f);
+    let alpha = PackedChallenge::<MyConfig>::from_f(rng.gen::<Challenge>());
+    let cumulative_sum = PackedChallenge::<MyConfig>::from_f(rng.gen::<Challenge>());
+
+    let mut group = c.benchmark_group("quotient_values");
+    for log_height in LOG_HEIGHTS {
+        let trace_domain = pcs.natural_domain_for_degree(1 << log_height);
+        let quotient_domain =
+            trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
+        let quotient_size = quotient_domain.size();
+
+        let main = RowMajorMatrix::<Val>::rand(&mut rng, quotient_size, 2);
+        let perm = RowMajorMatrix::<Val>::rand(
+            &mut rng,
+            quotient_size,
+            perm_width * <Challenge as AbstractExtensionField<Val>>::D,
+        );
+
+        group.bench_function(BenchmarkId::from_parameter(log_height), |b| {
+            b.iter(|| {
+                quotient_values::<MyConfig, _, RowMajorMatrix<Val>, _, RowMajorMatrix<Val>, _>(
+                    &chip,
+                    trace_domain,
+                    quotient_domain,
+                    None,
+                    vec![main.as_view()],
+                    None,
+                    Some(perm.as_view()),
+                    perm_challenges,
+                    alpha,
+                    cumulative_sum,
+                    &[],
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_quotient_values);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use p3_air::PairCol;
+use p3_air_util::{folders::rap::SymbolicAirBuilder, get_symbolic_constraints};
+use p3_field::Field;
+use p3_interaction::{InteractionAir, InteractionType, Rap};
+use p3_uni_stark::{Entry, SymbolicExpression};
+
+/// The static checks of a single chip.
+#[derive(Clone, Debug)]
+pub struct ChipAudit {
+    pub chip: String,
+    pub num_constraints: usize,
+    pub max_constraint_degree: usize,
+    /// Main columns which appear in neither a constraint nor an interaction.
+    pub unconstrained_main_columns: Vec<usize>,
+}
+
+/// The static checks of a bus across all chips.
+#[derive(Clone, Debug)]
+pub struct BusAudit {
+    pub bus: String,
+    pub num_sends: usize,
+    pub num_receives: usize,
+    /// The distinct numbers of fields of the bus' interactions.
+    pub arities: BTreeSet<usize>,
+}
+
+impl BusAudit {
+    /// Whether the bus is sent to and received from. A bus with only one side can never balance.
+    pub fn is_balanced(&self) -> bool {
+        (self.num_sends == 0) == (self.num_receives == 0)
+    }
+}
+
+/// The result of checking a machine without traces. See [`crate::machine::Machine::audit`].
+#[derive(Clone, Debug)]
+pub struct AuditReport {
+    /// Chips in canonical order.
+    pub chips: Vec<ChipAudit>,
+    pub buses: Vec<BusAudit>,
+    /// The bound on the constraint degree, if the machine declares one.
+    pub max_constraint_degree: Option<usize>,
+}
+
+impl AuditReport {
+    /// Chips whose constraints exceed the machine's degree bound.
+    pub fn degree_violations(&self) -> impl Iterator<Item = &ChipAudit> {
+        self.chips.iter().filter(|chip| {
+            self.max_constraint_degree
+                .is_some_and(|bound| chip.max_constraint_degree > bound)
+        })
+    }
+
+    /// Buses which are only sent to or only received from.
+    pub fn unbalanced_buses(&self) -> impl Iterator<Item = &BusAudit> {
+        self.buses.iter().filter(|bus| !bus.is_balanced())
+    }
+
+    /// Buses whose interactions have differing numbers of fields.
+    pub fn arity_mismatches(&self) -> impl Iterator<Item = &BusAudit> {
+        self.buses.iter().filter(|bus| bus.arities.len() > 1)
+    }
+
+    /// Chips with main columns that nothing constrains.
+    pub fn unconstrained_chips(&self) -> impl Iterator<Item = &ChipAudit> {
+        self.chips
+            .iter()
+            .filter(|chip| !chip.unconstrained_main_columns.is_empty())
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.degree_violations().next().is_none()
+            && self.unbalanced_buses().next().is_none()
+            && self.arity_mismatches().next().is_none()
+            && self.unconstrained_chips().next().is_none()
+    }
+}
+
+/// Inserts the main columns `expr` references into `columns`.
+fn collect_main_columns<F: Field>(expr: &SymbolicExpression<F>, columns: &mut BTreeSet<usize>) {
+    match expr {
+        SymbolicExpression::Variable(variable) => {
+            if let Entry::Main { .. } = variable.entry {
+                columns.insert(variable.index);
+            }
+        }
+        SymbolicExpression::Add { x, y, .. }
+        | SymbolicExpression::Sub { x, y, .. }
+        | SymbolicExpression::Mul { x, y, .. } => {
+            collect_main_columns(x, columns);
+            collect_main_columns(y, columns);
+        }
+        SymbolicExpression::Neg { x, .. } => collect_main_columns(x, columns),
+        SymbolicExpression::IsFirstRow
+        | SymbolicExpression::IsLastRow
+        | SymbolicExpression::IsTransition
+        | SymbolicExpression::Constant(_) => {}
+    }
+}
+
+pub(crate) fn audit_chip<F, A>(air: &A, name: String, num_public_values: usize) -> ChipAudit
+where
+    F: Field,
+    A: Rap<SymbolicAirBuilder<F>>,
+{
+    let constraints = get_symbolic_constraints(air, num_public_values);
+
+    let mut constrained = BTreeSet::new();
+    for constraint in constraints.iter() {
+        collect_main_columns(constraint, &mut constrained);
+    }
+    for (interaction, _) in air.all_interactions() {
+        for column in interaction
+            .fields
+            .iter()
+            .chain(interaction.next_fields.iter())
+            .chain(core::iter::once(&interaction.count))
+            .chain(interaction.filter.iter())
+        {
+            for (col, _) in column.column_weights.iter() {
+                if let PairCol::Main(i) = col {
+                    constrained.insert(*i);
+                }
+            }
+        }
+    }
+
+    ChipAudit {
+        chip: name,
+        num_constraints: constraints.len(),
+        max_constraint_degree: constraints
+            .iter()
+            .map(|constraint| constraint.degree_multiple())
+            .max()
+            .unwrap_or(0),
+        unconstrained_main_columns: (0..air.width())
+            .filter(|i| !constrained.contains(i))
+            .collect(),
+    }
+}
+
+/// Groups the interactions of `airs` by bus.
+pub(crate) fn audit_buses<F, A>(airs: &[A], bus_name: impl Fn(usize) -> String) -> Vec<BusAudit>
+where
+    F: Field,
+    A: InteractionAir<F>,
+{
+    let mut buses: BTreeMap<usize, BusAudit> = BTreeMap::new();
+    for air in airs.iter() {
+        for (bus_index, interactions) in air.interactions_by_bus() {
+            let bus = buses.entry(bus_index).or_insert_with(|| BusAudit {
+                bus: bus_name(bus_index),
+                num_sends: 0,
+                num_receives: 0,
+                arities: BTreeSet::new(),
+            });
+            for (interaction, interaction_type) in interactions.iter() {
+                match interaction_type {
+                    InteractionType::Send => bus.num_sends += 1,
+                    InteractionType::Receive => bus.num_receives += 1,
+                }
+                bus.arities.insert(interaction.num_fields());
+            }
+        }
+    }
+    buses.into_values().collect()
+}
@@ -0,0 +1,208 @@
+#[cfg(feature = "debug-transcript")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "debug-transcript")]
+use core::fmt::{self, Debug, Formatter};
+
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use crate::proof::Com;
+
+/// The commitment rounds of the proving protocol, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProverRound {
+    Preprocessed,
+    Main,
+    Permutation,
+    Quotient,
+}
+
+/// The channel through which a prover exchanges messages with a verifier. After each commitment
+/// round the prover yields its commitment and waits for the verifier's challenges:
+///
+/// 1. public values, preprocessed and main commitments → permutation challenges
+/// 2. permutation commitment → `alpha`
+/// 3. quotient commitment → `zeta`
+/// 4. opening argument
+///
+/// The verifier replays the prover's messages through a channel in the same rounds to rederive
+/// the challenges.
+pub trait ProverChannel<SC: StarkGenericConfig> {
+    fn send_public_values(&mut self, public_values: &[Val<SC>]);
+
+    fn send_commitment(&mut self, round: ProverRound, commitment: &Com<SC>);
+
+    /// Receives a challenge from the verifier after `round`.
+    fn receive_challenge(&mut self, round: ProverRound) -> SC::Challenge;
+
+    /// The challenger the opening argument is generated with.
+    fn opening_challenger(&mut self) -> &mut SC::Challenger;
+}
+
+/// A non-interactive channel which derives the verifier's challenges with Fiat-Shamir.
+pub struct FiatShamirChannel<'a, SC: StarkGenericConfig> {
+    challenger: &'a mut SC::Challenger,
+}
+
+impl<'a, SC: StarkGenericConfig> FiatShamirChannel<'a, SC> {
+    pub fn new(challenger: &'a mut SC::Challenger) -> Self {
+        Self { challenger }
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> ProverChannel<SC> for FiatShamirChannel<'a, SC> {
+    fn send_public_values(&mut self, public_values: &[Val<SC>]) {
+        self.challenger.observe_slice(public_values);
+    }
+
+    fn send_commitment(&mut self, _round: ProverRound, commitment: &Com<SC>) {
+        self.challenger.observe(commitment.clone());
+    }
+
+    fn receive_challenge(&mut self, _round: ProverRound) -> SC::Challenge {
+        self.challenger.sample_ext_element()
+    }
+
+    fn opening_challenger(&mut self) -> &mut SC::Challenger {
+        self.challenger
+    }
+}
+
+/// A message of the Fiat-Shamir transcript, as recorded by [`TranscriptChannel`].
+#[cfg(feature = "debug-transcript")]
+pub enum TranscriptEvent<SC: StarkGenericConfig> {
+    PublicValues(Vec<Val<SC>>),
+    Commitment {
+        round: ProverRound,
+        commitment: Com<SC>,
+    },
+    Challenge {
+        round: ProverRound,
+        challenge: SC::Challenge,
+    },
+}
+
+#[cfg(feature = "debug-transcript")]
+impl<SC: StarkGenericConfig> Clone for TranscriptEvent<SC> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::PublicValues(public_values) => Self::PublicValues(public_values.clone()),
+            Self::Commitment { round, commitment } => Self::Commitment {
+                round: *round,
+                commitment: commitment.clone(),
+            },
+            Self::Challenge { round, challenge } => Self::Challenge {
+                round: *round,
+                challenge: *challenge,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "debug-transcript")]
+impl<SC: StarkGenericConfig> PartialEq for TranscriptEvent<SC>
+where
+    Com<SC>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::PublicValues(a), Self::PublicValues(b)) => a == b,
+            (
+                Self::Commitment {
+                    round: round_a,
+                    commitment: a,
+                },
+                Self::Commitment {
+                    round: round_b,
+                    commitment: b,
+                },
+            ) => round_a == round_b && a == b,
+            (
+                Self::Challenge {
+                    round: round_a,
+                    challenge: a,
+                },
+                Self::Challenge {
+                    round: round_b,
+                    challenge: b,
+                },
+            ) => round_a == round_b && a == b,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "debug-transcript")]
+impl<SC: StarkGenericConfig> Debug for TranscriptEvent<SC>
+where
+    Com<SC>: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::PublicValues(public_values) => {
+                f.debug_tuple("PublicValues").field(public_values).finish()
+            }
+            Self::Commitment { round, commitment } => f
+                .debug_struct("Commitment")
+                .field("round", round)
+                .field("commitment", commitment)
+                .finish(),
+            Self::Challenge { round, challenge } => f
+                .debug_struct("Challenge")
+                .field("round", round)
+                .field("challenge", challenge)
+                .finish(),
+        }
+    }
+}
+
+/// A [`FiatShamirChannel`] which also records the messages of the transcript. Proving and
+/// verifying through transcript channels and comparing their logs pinpoints the first message at
+/// which the prover and the verifier diverge. The messages of the opening argument aren't recorded.
+#[cfg(feature = "debug-transcript")]
+pub struct TranscriptChannel<'a, SC: StarkGenericConfig> {
+    inner: FiatShamirChannel<'a, SC>,
+    log: Vec<TranscriptEvent<SC>>,
+}
+
+#[cfg(feature = "debug-transcript")]
+impl<'a, SC: StarkGenericConfig> TranscriptChannel<'a, SC> {
+    pub fn new(challenger: &'a mut SC::Challenger) -> Self {
+        Self {
+            inner: FiatShamirChannel::new(challenger),
+            log: vec![],
+        }
+    }
+
+    pub fn transcript_log(&self) -> &[TranscriptEvent<SC>] {
+        &self.log
+    }
+}
+
+#[cfg(feature = "debug-transcript")]
+impl<'a, SC: StarkGenericConfig> ProverChannel<SC> for TranscriptChannel<'a, SC> {
+    fn send_public_values(&mut self, public_values: &[Val<SC>]) {
+        self.log
+            .push(TranscriptEvent::PublicValues(public_values.to_vec()));
+        self.inner.send_public_values(public_values);
+    }
+
+    fn send_commitment(&mut self, round: ProverRound, commitment: &Com<SC>) {
+        self.log.push(TranscriptEvent::Commitment {
+            round,
+            commitment: commitment.clone(),
+        });
+        self.inner.send_commitment(round, commitment);
+    }
+
+    fn receive_challenge(&mut self, round: ProverRound) -> SC::Challenge {
+        let challenge = self.inner.receive_challenge(round);
+        self.log
+            .push(TranscriptEvent::Challenge { round, challenge });
+        challenge
+    }
+
+    fn opening_challenger(&mut self) -> &mut SC::Challenger {
+        self.inner.opening_challenger()
+    }
+}
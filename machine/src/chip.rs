@@ -1,10 +1,119 @@
+use alloc::vec;
+use alloc::vec::Vec;
 use core::fmt::{Debug, Display};
+use core::ops::Range;
+
+use itertools::Itertools;
+use p3_air::BaseAir;
+use p3_matrix::dense::RowMajorMatrix;
 
 #[cfg(feature = "air-logger")]
 use p3_air_util::AirLogger;
 
+use crate::error::TraceGenError;
+
+/// The supertraits of [`Chip`] that depend on the enabled features, i.e. [`AirLogger`] with the
+/// `air-logger` feature.
 #[cfg(not(feature = "air-logger"))]
-pub trait Chip: Clone + Debug + Display {}
+pub trait ChipFeatures {}
 
+#[cfg(not(feature = "air-logger"))]
+impl<T> ChipFeatures for T {}
+
+/// The supertraits of [`Chip`] that depend on the enabled features, i.e. [`AirLogger`] with the
+/// `air-logger` feature.
 #[cfg(feature = "air-logger")]
-pub trait Chip: Clone + Debug + Display + AirLogger {}
+pub trait ChipFeatures: AirLogger {}
+
+#[cfg(feature = "air-logger")]
+impl<T: AirLogger> ChipFeatures for T {}
+
+pub trait Chip: Clone + Debug + Display + ChipFeatures {
+    /// The window of the machine's public values that this chip's constraints are evaluated
+    /// against. `None` means the chip sees all public values.
+    fn public_value_range(&self) -> Option<Range<usize>> {
+        None
+    }
+
+    /// The main column which is one on real rows and zero on padding rows, if the chip pads its
+    /// trace. Debug builds then check that every constraint vanishes on padding rows, which fails
+    /// if a constraint isn't gated by the column.
+    fn is_real_column(&self) -> Option<usize> {
+        None
+    }
+
+    /// The number of leading main columns that are committed to. The remaining columns are
+    /// scratch space that is only used while generating the trace, and is dropped before the main
+    /// trace is committed. Constraints and interactions may only reference committed columns.
+    /// `None` means every main column is committed.
+    fn committed_width(&self) -> Option<usize> {
+        None
+    }
+
+    /// The widths of the segments the committed main columns are generated and committed in, in
+    /// column order, e.g. a segment that is cached across proofs followed by one generated per
+    /// proof. They add up to the committed width. `None` means the main trace is a single
+    /// segment.
+    fn main_segment_widths(&self) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Checks the inputs the chip computes its preprocessed trace from, for chips whose inputs may
+    /// be inconsistent. It runs before [`BaseAir::preprocessed_trace`], see
+    /// [`chip_preprocessed_trace`], which may then assume the inputs are consistent.
+    fn check_preprocessed_inputs(&self) -> Result<(), TraceGenError> {
+        Ok(())
+    }
+}
+
+/// Returns the width of the main trace of `chip` that is committed to and opened.
+pub fn chip_committed_width<F, C: Chip + BaseAir<F>>(chip: &C) -> usize {
+    chip.committed_width().unwrap_or_else(|| chip.width())
+}
+
+/// Returns the widths of the segments of the main trace of `chip` that are committed to.
+pub fn chip_main_segment_widths<F, C: Chip + BaseAir<F>>(chip: &C) -> Vec<usize> {
+    chip.main_segment_widths()
+        .unwrap_or_else(|| vec![chip_committed_width(chip)])
+}
+
+/// Returns the preprocessed trace of `chip`, or the error of [`Chip::check_preprocessed_inputs`].
+pub fn chip_preprocessed_trace<F, C: Chip + BaseAir<F>>(
+    chip: &C,
+) -> Result<Option<RowMajorMatrix<F>>, TraceGenError> {
+    chip.check_preprocessed_inputs()?;
+    Ok(chip.preprocessed_trace())
+}
+
+/// Returns the slice of `public_values` that `chip` is evaluated against, or `None` if the chip's
+/// public value range is out of bounds.
+pub fn chip_public_values<'a, C: Chip, T>(chip: &C, public_values: &'a [T]) -> Option<&'a [T]> {
+    match chip.public_value_range() {
+        Some(range) => public_values.get(range),
+        None => Some(public_values),
+    }
+}
+
+/// Sorts `chips` by name, which is the order they're committed to and opened in. The sort is
+/// stable. The order of the chips a trace is built from must be deterministic, so chips collected
+/// in an arbitrary order, e.g. from a `HashMap`, should be canonicalized first.
+pub fn canonicalize<C: Chip>(chips: &mut [&C]) {
+    chips.sort_by_cached_key(|chip| chip.to_string());
+}
+
+/// Returns the indices of `chips` sorted by chip name. Chips are committed to and opened in this
+/// order, so it must not depend on the order in which a machine lists its chips. Chip names are
+/// expected to be unique.
+pub fn canonical_chip_order<C: Chip>(chips: &[C]) -> Vec<usize> {
+    let order = (0..chips.len())
+        .sorted_by_cached_key(|&i| chips[i].to_string())
+        .collect_vec();
+    debug_assert!(
+        order
+            .iter()
+            .tuple_windows()
+            .all(|(&a, &b)| chips[a].to_string() != chips[b].to_string()),
+        "Chip names must be unique"
+    );
+    order
+}
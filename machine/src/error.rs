@@ -1,10 +1,376 @@
+use alloc::string::String;
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air_util::debug::rap::ConstraintError;
+
 #[derive(Debug)]
 pub enum VerificationError {
-    InvalidProofShape,
-    /// An error occurred while verifying the claimed openings.
-    InvalidOpeningArgument,
+    /// The proof's shape doesn't match the machine. `chip` is `None` if the mismatch isn't
+    /// specific to a chip.
+    ShapeMismatch {
+        chip: Option<usize>,
+        detail: &'static str,
+    },
+    /// A chip with traces has no quotient openings.
+    MissingQuotient { chip: usize },
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
     /// `quotient(zeta) Z_H(zeta)`.
-    OodEvaluationMismatch,
-    NonZeroCumulativeSum,
+    ConstraintMismatch { chip: usize },
+    /// The cumulative sums of the chips don't add up to zero.
+    CumulativeSumMismatch { total: String },
+    /// The opened values don't match the commitments.
+    CommitmentMismatch,
+    /// The number of public values differs from the number the machine declares.
+    PublicValueCountMismatch { expected: usize, actual: usize },
+    /// A chip's public value range lies outside of the public values.
+    PublicValueRangeOutOfBounds { chip: usize },
+    /// Two chips were evaluated against different values at the same public value index.
+    InconsistentPublicValues { chips: (usize, usize), index: usize },
+    /// A chip was proven against a different value at a public value index than the public values
+    /// the proof is verified against.
+    PublicValuesMismatch { chip: usize, index: usize },
+    /// The public values can't be laid out across the chips.
+    PublicValueLayout(PublicValueLayoutError),
+    /// The proof claims traces the machine can't have.
+    Machine(MachineError),
+    /// The verifying key or the proof was made by an incompatible version of the prover.
+    UnsupportedFormatVersion { found: u32, expected: u32 },
 }
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::ShapeMismatch {
+                chip: Some(chip),
+                detail,
+            } => write!(f, "proof shape mismatch for chip {}: {}", chip, detail),
+            Self::ShapeMismatch { chip: None, detail } => {
+                write!(f, "proof shape mismatch: {}", detail)
+            }
+            Self::MissingQuotient { chip } => write!(f, "quotient of chip {} isn't opened", chip),
+            Self::ConstraintMismatch { chip } => {
+                write!(f, "constraints of chip {} don't match its quotient", chip)
+            }
+            Self::CumulativeSumMismatch { total } => {
+                write!(f, "cumulative sums add up to {} instead of zero", total)
+            }
+            Self::CommitmentMismatch => write!(f, "opened values don't match the commitments"),
+            Self::PublicValueCountMismatch { expected, actual } => {
+                write!(f, "expected {} public values, got {}", expected, actual)
+            }
+            Self::PublicValueRangeOutOfBounds { chip } => {
+                write!(f, "public value range of chip {} is out of bounds", chip)
+            }
+            Self::InconsistentPublicValues { chips, index } => write!(
+                f,
+                "chips {} and {} read different values at public value {}",
+                chips.0, chips.1, index
+            ),
+            Self::PublicValuesMismatch { chip, index } => write!(
+                f,
+                "chip {} was proven against a different value at public value {}",
+                chip, index
+            ),
+            Self::PublicValueLayout(err) => write!(f, "{}", err),
+            Self::Machine(err) => write!(f, "{}", err),
+            Self::UnsupportedFormatVersion { found, expected } => write!(
+                f,
+                "unsupported format version {}, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl core::error::Error for VerificationError {}
+
+impl VerificationError {
+    /// Attributes an error about a chip to the chip at canonical index `chip` instead.
+    pub(crate) fn reindex_chip(self, chip: usize) -> Self {
+        match self {
+            Self::ShapeMismatch {
+                chip: Some(_),
+                detail,
+            } => Self::ShapeMismatch {
+                chip: Some(chip),
+                detail,
+            },
+            Self::MissingQuotient { .. } => Self::MissingQuotient { chip },
+            Self::ConstraintMismatch { .. } => Self::ConstraintMismatch { chip },
+            Self::PublicValueRangeOutOfBounds { .. } => Self::PublicValueRangeOutOfBounds { chip },
+            Self::PublicValuesMismatch { index, .. } => Self::PublicValuesMismatch { chip, index },
+            Self::Machine(MachineError::UnsupportedTraceHeight { height, .. }) => {
+                Self::Machine(MachineError::UnsupportedTraceHeight { chip, height })
+            }
+            err => err,
+        }
+    }
+}
+
+/// A proof of a batch that doesn't verify. See [`crate::verify::verify_batch`].
+#[derive(Debug)]
+pub struct BatchVerificationError {
+    /// The index of the proof in the batch.
+    pub proof: usize,
+    pub error: VerificationError,
+}
+
+impl Display for BatchVerificationError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "proof {}: {}", self.proof, self.error)
+    }
+}
+
+impl core::error::Error for BatchVerificationError {}
+
+impl From<MachineError> for VerificationError {
+    fn from(err: MachineError) -> Self {
+        Self::Machine(err)
+    }
+}
+
+impl From<PublicValueLayoutError> for VerificationError {
+    fn from(err: PublicValueLayoutError) -> Self {
+        match err {
+            PublicValueLayoutError::OutOfBounds { chip } => {
+                Self::PublicValueRangeOutOfBounds { chip }
+            }
+            err => Self::PublicValueLayout(err),
+        }
+    }
+}
+
+/// Errors from laying out the public values across chips, see
+/// [`crate::public::PublicValueLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicValueLayoutError {
+    /// A chip's public value range lies outside of the public values.
+    OutOfBounds { chip: usize },
+    /// The ranges of two chips share the public value at `index`.
+    Overlap { chips: (usize, usize), index: usize },
+    /// No chip's range covers the public value at `index`.
+    Gap { index: usize },
+}
+
+impl Display for PublicValueLayoutError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::OutOfBounds { chip } => {
+                write!(f, "public value range of chip {} is out of bounds", chip)
+            }
+            Self::Overlap { chips, index } => write!(
+                f,
+                "chips {} and {} share public value {}",
+                chips.0, chips.1, index
+            ),
+            Self::Gap { index } => write!(f, "no chip reads public value {}", index),
+        }
+    }
+}
+
+impl core::error::Error for PublicValueLayoutError {}
+
+/// Errors from loading traces, which are attributed to the chip at its canonical index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineError {
+    /// The trace height isn't a power of two within the two-adicity of the field, so the PCS
+    /// can't build a domain for it.
+    UnsupportedTraceHeight { chip: usize, height: usize },
+    /// The chip failed to generate its trace.
+    TraceGeneration { chip: usize, error: TraceGenError },
+    /// The main trace of the chip isn't given as one segment per
+    /// [`crate::chip::Chip::main_segment_widths`].
+    MainSegmentCount {
+        chip: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A segment of the main trace of a chip with a main trace is `None`.
+    MissingMainSegment { chip: usize, segment: usize },
+    /// A main trace segment other than the last doesn't have its declared width.
+    MainSegmentWidth {
+        chip: usize,
+        segment: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The main trace segments of the chip have different heights.
+    MainSegmentHeightMismatch { chip: usize },
+    /// The chip commits to more main columns than its main trace has.
+    CommittedWidthExceeded {
+        chip: usize,
+        committed_width: usize,
+        width: usize,
+    },
+    /// The main extension trace of the chip doesn't have the width of
+    /// [`p3_interaction::InteractionAir::main_ext_width`]. A missing trace has width zero.
+    MainExtWidth {
+        chip: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The permutation trace of the chip doesn't have the width of
+    /// [`p3_interaction::InteractionAir::permutation_width`]. A missing trace has width zero.
+    PermutationWidth {
+        chip: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl Display for MachineError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::UnsupportedTraceHeight { chip, height } => {
+                write!(f, "chip {} has unsupported trace height {}", chip, height)
+            }
+            Self::TraceGeneration { chip, error } => {
+                write!(f, "chip {} failed to generate its trace: {}", chip, error)
+            }
+            Self::MainSegmentCount {
+                chip,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "chip {} has {} main trace segments, expected {}",
+                chip, actual, expected
+            ),
+            Self::MissingMainSegment { chip, segment } => {
+                write!(
+                    f,
+                    "main trace segment {} of chip {} is missing",
+                    segment, chip
+                )
+            }
+            Self::MainSegmentWidth {
+                chip,
+                segment,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "main trace segment {} of chip {} has width {}, expected {}",
+                segment, chip, actual, expected
+            ),
+            Self::MainSegmentHeightMismatch { chip } => write!(
+                f,
+                "main trace segments of chip {} have different heights",
+                chip
+            ),
+            Self::CommittedWidthExceeded {
+                chip,
+                committed_width,
+                width,
+            } => write!(
+                f,
+                "chip {} commits to {} main columns, but its main trace has {}",
+                chip, committed_width, width
+            ),
+            Self::MainExtWidth {
+                chip,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "main extension trace of chip {} has width {}, expected {}",
+                chip, actual, expected
+            ),
+            Self::PermutationWidth {
+                chip,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "permutation trace of chip {} has width {}, expected {}",
+                chip, actual, expected
+            ),
+        }
+    }
+}
+
+impl core::error::Error for MachineError {}
+
+/// Errors a chip can return from [`crate::chip::Chip::check_preprocessed_inputs`] instead of
+/// panicking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceGenError {
+    /// The witness inputs are inconsistent. `row` is the row they were found on, if any.
+    InconsistentWitness {
+        row: Option<usize>,
+        detail: &'static str,
+    },
+    /// The trace needs more rows than the chip supports.
+    CapacityExceeded { rows: usize, capacity: usize },
+}
+
+impl Display for TraceGenError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::InconsistentWitness {
+                row: Some(row),
+                detail,
+            } => write!(f, "inconsistent witness on row {}: {}", row, detail),
+            Self::InconsistentWitness { row: None, detail } => {
+                write!(f, "inconsistent witness: {}", detail)
+            }
+            Self::CapacityExceeded { rows, capacity } => {
+                write!(f, "{} rows exceed the capacity of {}", rows, capacity)
+            }
+        }
+    }
+}
+
+impl core::error::Error for TraceGenError {}
+
+/// Errors from decoding a [`crate::proof::VersionedProof`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofDecodeError {
+    /// The bytes don't start with [`crate::proof::PROOF_MAGIC`], so they aren't an encoded proof.
+    MissingMagic,
+    /// The proof was encoded by an incompatible version of the prover.
+    UnsupportedFormatVersion { found: u32, expected: u32 },
+    /// The proof doesn't deserialize.
+    Malformed(String),
+}
+
+impl Display for ProofDecodeError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::MissingMagic => write!(f, "not an encoded proof"),
+            Self::UnsupportedFormatVersion { found, expected } => write!(
+                f,
+                "unsupported format version {}, expected {}",
+                found, expected
+            ),
+            Self::Malformed(err) => write!(f, "malformed proof: {}", err),
+        }
+    }
+}
+
+impl core::error::Error for ProofDecodeError {}
+
+/// A constraint of a chip that doesn't vanish on the chip's traces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChipConstraintError {
+    pub chip: String,
+    pub error: ConstraintError,
+    /// Whether the constraint fails on a padding row, see
+    /// [`crate::chip::Chip::is_real_column`], in which case it may be missing an `is_real` gate.
+    pub padding: bool,
+}
+
+impl Display for ChipConstraintError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "chip {}: {}", self.chip, self.error)?;
+        if self.padding {
+            write!(
+                f,
+                ", which is a padding row, so it may be missing an is_real gate"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ChipConstraintError {}
@@ -0,0 +1,673 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::Pcs;
+use p3_field::{PrimeField32, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use p3_air_util::folders::rap::{
+    DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder, TrackingConstraintBuilder,
+    VerifierConstraintFolder,
+};
+use p3_air_util::proof::Commitments;
+use p3_interaction::{Rap, NUM_PERM_CHALLENGES};
+
+#[cfg(feature = "memory-log")]
+use crate::memory::StageMemory;
+#[cfg(debug_assertions)]
+use crate::trace::MachineTraceChecker;
+#[cfg(feature = "air-logger")]
+use crate::trace::MachineTraceDebugger;
+use crate::{
+    channel::{FiatShamirChannel, ProverChannel, ProverRound},
+    chip::{canonical_chip_order, Chip},
+    error::VerificationError,
+    machine::Machine,
+    proof::{ChipSubProof, Com, MachineProof, ProvingKey, VerifyingKey, FORMAT_VERSION},
+    trace::{
+        ChipTraceOpening, MachineTrace, MachineTraceBuilder, MachineTraceCommiter,
+        MachineTraceConstraintVerifier, MachineTraceLoader, MachineTraceOpener,
+        MachineTraceOpening, MachineTraceOpeningBuilder, MachineTraceOpeningLoader,
+        MachineTraceOpeningVerifier, PaddingStrategy,
+    },
+    verify::ConstraintReport,
+};
+
+/// A machine to be proven jointly with others, along with its witness.
+pub struct ProvingInstance<'a, M, SC: StarkGenericConfig> {
+    pub machine: &'a M,
+    pub pk: &'a ProvingKey<SC>,
+    /// The segments of the main traces, in the order of `Machine::chips`. See
+    /// [`crate::chip::Chip::main_segment_widths`].
+    pub main_traces: Vec<Vec<Option<RowMajorMatrix<Val<SC>>>>>,
+    /// The extension field columns of the main traces, in the order of `Machine::chips`, or empty
+    /// if no chip has any. See [`p3_interaction::InteractionAir::main_ext_width`].
+    pub main_ext_traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+    pub public_values: &'a [Val<SC>],
+}
+
+impl<'a, M, SC: StarkGenericConfig> ProvingInstance<'a, M, SC> {
+    /// Creates an instance of a machine whose chips' main traces are a single segment each.
+    pub fn new(
+        machine: &'a M,
+        pk: &'a ProvingKey<SC>,
+        main_traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        public_values: &'a [Val<SC>],
+    ) -> Self {
+        Self {
+            machine,
+            pk,
+            main_traces: main_traces.into_iter().map(|trace| vec![trace]).collect(),
+            main_ext_traces: vec![],
+            public_values,
+        }
+    }
+
+    /// Sets the extension field columns of the main traces, in the order of `Machine::chips`.
+    pub fn with_main_ext(
+        mut self,
+        main_ext_traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+    ) -> Self {
+        self.main_ext_traces = main_ext_traces;
+        self
+    }
+}
+
+/// A machine proof to be verified jointly with others.
+pub struct VerifyingInstance<'a, M, SC: StarkGenericConfig> {
+    pub machine: &'a M,
+    pub vk: &'a VerifyingKey<SC>,
+    pub proof: &'a MachineProof<SC>,
+    pub public_values: &'a [Val<SC>],
+}
+
+/// Proves several machines against one shared transcript. The commitments of every machine are
+/// observed before each challenge is sampled, so the challenges bind all the machines together.
+pub fn prove_joint<'a, SC, M>(
+    config: &'a SC,
+    challenger: &mut SC::Challenger,
+    instances: Vec<ProvingInstance<'a, M, SC>>,
+) -> Vec<MachineProof<SC>>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    M::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+        + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+        // TODO: Put behind air-logger feature
+        + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+    Val<SC>: PrimeField32 + TwoAdicField,
+{
+    prove_joint_with_channel(config, &mut FiatShamirChannel::new(challenger), instances)
+}
+
+/// Proves several machines round by round, sending the commitments of every machine through
+/// `channel` before receiving each challenge.
+pub fn prove_joint_with_channel<'a, SC, M, Ch>(
+    config: &'a SC,
+    channel: &mut Ch,
+    instances: Vec<ProvingInstance<'a, M, SC>>,
+) -> Vec<MachineProof<SC>>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    Ch: ProverChannel<SC>,
+    M::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+        + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+        + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+        + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+        // TODO: Put behind air-logger feature
+        + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+    Val<SC>: PrimeField32 + TwoAdicField,
+{
+    let pcs = config.pcs();
+
+    let mut traces: Vec<MachineTrace<SC, M::Chip>> = vec![];
+    let mut pks = vec![];
+    let mut main_traces = vec![];
+    let mut main_ext_traces = vec![];
+    let mut public_values = vec![];
+    for instance in instances {
+        // TODO: Use fixed size array instead of Vecs
+        assert_eq!(
+            instance.pk.format_version, FORMAT_VERSION,
+            "Unsupported proving key format version"
+        );
+        let chips = instance.machine.chips();
+        assert_eq!(instance.main_traces.len(), chips.len(), "Length mismatch");
+        if let Some(expected) = instance.machine.num_public_values() {
+            assert_eq!(
+                instance.public_values.len(),
+                expected,
+                "Public value count mismatch"
+            );
+        }
+
+        // Main traces are supplied in the order of `chips`, reorder them to the canonical order
+        let order = canonical_chip_order(&chips);
+        let mut instance_main_traces = instance.main_traces.into_iter().map(Some).collect_vec();
+        main_traces.push(
+            order
+                .iter()
+                .map(|&i| instance_main_traces[i].take().unwrap())
+                .collect_vec(),
+        );
+        let mut instance_main_ext_traces = instance.main_ext_traces;
+        if instance_main_ext_traces.is_empty() {
+            instance_main_ext_traces.resize(chips.len(), None);
+        }
+        assert_eq!(
+            instance_main_ext_traces.len(),
+            chips.len(),
+            "Length mismatch"
+        );
+        main_ext_traces.push(
+            order
+                .iter()
+                .map(|&i| instance_main_ext_traces[i].take())
+                .collect_vec(),
+        );
+        let chips = order.into_iter().map(|i| chips[i].clone()).collect_vec();
+
+        traces.push(MachineTraceBuilder::new(&chips));
+        pks.push(instance.pk);
+        public_values.push(instance.public_values);
+    }
+
+    // 1. Send public values
+    for public_values in public_values.iter() {
+        channel.send_public_values(public_values);
+    }
+
+    // 2. Send preprocessed commitments
+    for (trace, pk) in traces.iter_mut().zip_eq(pks.iter()) {
+        tracing::info_span!("load preprocessed traces")
+            .in_scope(|| {
+                trace.load_preprocessed(
+                    pcs,
+                    pk.preprocessed.traces.as_slice(),
+                    PaddingStrategy::None,
+                )
+            })
+            .unwrap_or_else(|err| panic!("{}", err));
+        if let Some(commit) = &pk.preprocessed.commitment {
+            channel.send_commitment(ProverRound::Preprocessed, commit);
+        }
+    }
+    #[cfg(feature = "memory-log")]
+    StageMemory::sample(ProverRound::Preprocessed, &traces).log();
+
+    // 3. Generate and commit to main traces
+    let mut main_commits = vec![];
+    let mut main_data = vec![];
+    for ((trace, main_traces), main_ext_traces) in traces
+        .iter_mut()
+        .zip_eq(main_traces)
+        .zip_eq(main_ext_traces)
+    {
+        tracing::info_span!("load main traces")
+            .in_scope(|| {
+                trace.load_main(pcs, main_traces, PaddingStrategy::None)?;
+                trace.load_main_ext(pcs, main_ext_traces, PaddingStrategy::None)
+            })
+            .unwrap_or_else(|err| panic!("{}", err));
+        let (main_commit, data) =
+            tracing::info_span!("commit to main traces").in_scope(|| trace.commit_main(pcs));
+        if let Some(main_commit) = &main_commit {
+            channel.send_commitment(ProverRound::Main, main_commit);
+        }
+        main_commits.push(main_commit);
+        main_data.push(data);
+    }
+    #[cfg(feature = "memory-log")]
+    StageMemory::sample(ProverRound::Main, &traces).log();
+
+    // 4. Sample permutation challenges
+    let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
+        .map(|_| channel.receive_challenge(ProverRound::Main))
+        .collect_vec()
+        .try_into()
+        .unwrap();
+
+    // 5. Generate and commit to permutation traces
+    let mut permutation_commits = vec![];
+    let mut permutation_data = vec![];
+    for (i, trace) in traces.iter_mut().enumerate() {
+        tracing::info_span!("generate permutation traces")
+            .in_scope(|| {
+                trace.generate_permutation(
+                    pcs,
+                    &pks[i].num_buses,
+                    perm_challenges,
+                    public_values[i],
+                )
+            })
+            .unwrap_or_else(|err| panic!("{}", err));
+        let (permutation_commit, data) = tracing::info_span!("commit to permutation traces")
+            .in_scope(|| trace.commit_permutation(pcs));
+        if let Some(permutation_commit) = &permutation_commit {
+            channel.send_commitment(ProverRound::Permutation, permutation_commit);
+        }
+        permutation_commits.push(permutation_commit);
+        permutation_data.push(data);
+    }
+    #[cfg(feature = "memory-log")]
+    StageMemory::sample(ProverRound::Permutation, &traces).log();
+    let alpha = channel.receive_challenge(ProverRound::Permutation);
+
+    // 6. Generate and commit to quotient traces
+    let mut quotient_commits = vec![];
+    let mut quotient_data = vec![];
+    for (i, trace) in traces.iter_mut().enumerate() {
+        #[cfg(feature = "air-logger")]
+        let _ = tracing::info_span!("writing traces to file")
+            .in_scope(|| trace.write_traces_to_file("trace.xlsx", perm_challenges));
+
+        // Verify constraints
+        #[cfg(debug_assertions)]
+        tracing::info_span!("checking constraints")
+            .in_scope(|| trace.check_constraints::<M::Bus>(perm_challenges, public_values[i]));
+
+        tracing::info_span!("generate quotient trace").in_scope(|| {
+            trace.generate_quotient(
+                pcs,
+                &pks[i].preprocessed.data,
+                &main_data[i],
+                &permutation_data[i],
+                &pks[i].quotient_domains,
+                perm_challenges,
+                alpha,
+                public_values[i],
+            )
+        });
+        // TODO: Panic if this is None
+        let (quotient_commit, data) = tracing::info_span!("commit to quotient chunks")
+            .in_scope(|| trace.commit_quotient(pcs));
+        if let Some(quotient_commit) = &quotient_commit {
+            channel.send_commitment(ProverRound::Quotient, quotient_commit);
+        }
+        quotient_commits.push(quotient_commit);
+        quotient_data.push(data);
+    }
+    #[cfg(feature = "memory-log")]
+    StageMemory::sample(ProverRound::Quotient, &traces).log();
+
+    // 7. Sample OOD point and generate opening proofs
+    let zeta = channel.receive_challenge(ProverRound::Quotient);
+    let mut proofs = vec![];
+    for (i, ((main_commit, permutation_commit), quotient_commit)) in main_commits
+        .into_iter()
+        .zip_eq(permutation_commits)
+        .zip_eq(quotient_commits)
+        .enumerate()
+    {
+        let trace = &traces[i];
+        let rounds = trace.generate_rounds(
+            zeta,
+            &pks[i].preprocessed.data,
+            &main_data[i],
+            &permutation_data[i],
+            &quotient_data[i],
+        );
+        // A machine without any traces has nothing to open
+        let (opening_values, opening_proof) = if rounds.is_empty() {
+            (vec![], None)
+        } else {
+            let (opening_values, opening_proof) = pcs.open(rounds, channel.opening_challenger());
+            (opening_values, Some(opening_proof))
+        };
+
+        // Unflatten quotient openings
+        let opening_values = trace.unflatten_openings(
+            opening_values,
+            &pks[i].preprocessed.data,
+            &main_data[i],
+            &permutation_data[i],
+            &quotient_data[i],
+        );
+
+        proofs.push(MachineProof {
+            format_version: FORMAT_VERSION,
+            commitments: Commitments {
+                main: main_commit,
+                permutation: permutation_commit,
+                quotient_chunks: quotient_commit,
+            },
+            opening_proof,
+            chip_proofs: trace.generate_proofs(opening_values),
+            chip_public_values: trace.generate_public_values(public_values[i]),
+        });
+    }
+
+    proofs
+}
+
+/// Verifies machine proofs generated by [`prove_joint`] against the same shared transcript.
+pub fn verify_joint<'a, SC, M>(
+    config: &'a SC,
+    challenger: &mut SC::Challenger,
+    instances: Vec<VerifyingInstance<'a, M, SC>>,
+) -> Result<(), VerificationError>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip:
+        for<'b> Rap<VerifierConstraintFolder<'b, SC>> + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+{
+    verify_joint_with_reports(config, challenger, instances).map(|_| ())
+}
+
+/// Like [`verify_joint`], but also returns the constraint evaluations of every chip of every
+/// instance, with chips in canonical order.
+#[allow(clippy::type_complexity)]
+pub fn verify_joint_with_reports<'a, SC, M>(
+    config: &'a SC,
+    challenger: &mut SC::Challenger,
+    instances: Vec<VerifyingInstance<'a, M, SC>>,
+) -> Result<Vec<Vec<Option<ConstraintReport<SC::Challenge>>>>, VerificationError>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip:
+        for<'b> Rap<VerifierConstraintFolder<'b, SC>> + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+{
+    verify_joint_with_channel(config, &mut FiatShamirChannel::new(challenger), instances)
+}
+
+/// Like [`verify_joint_with_reports`], but replays the prover's messages through `channel` to
+/// derive the challenges, in the same rounds as [`prove_joint_with_channel`].
+#[allow(clippy::type_complexity)]
+pub fn verify_joint_with_channel<'a, SC, M, Ch>(
+    config: &'a SC,
+    channel: &mut Ch,
+    instances: Vec<VerifyingInstance<'a, M, SC>>,
+) -> Result<Vec<Vec<Option<ConstraintReport<SC::Challenge>>>>, VerificationError>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    Ch: ProverChannel<SC>,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip:
+        for<'b> Rap<VerifierConstraintFolder<'b, SC>> + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+{
+    let pcs = config.pcs();
+
+    let mut traces: Vec<MachineTraceOpening<SC, M::Chip>> = vec![];
+    for instance in instances.iter() {
+        for found in [instance.vk.format_version, instance.proof.format_version] {
+            if found != FORMAT_VERSION {
+                return Err(VerificationError::UnsupportedFormatVersion {
+                    found,
+                    expected: FORMAT_VERSION,
+                });
+            }
+        }
+        if let Some(expected) = instance.machine.num_public_values() {
+            if instance.public_values.len() != expected {
+                return Err(VerificationError::PublicValueCountMismatch {
+                    expected,
+                    actual: instance.public_values.len(),
+                });
+            }
+        }
+
+        let chips = instance.machine.canonical_chips();
+        let chip_names: Vec<String> = chips.iter().map(ToString::to_string).collect();
+        if chip_names != instance.vk.chips {
+            return Err(VerificationError::ShapeMismatch {
+                chip: None,
+                detail: "chips don't match the verifying key",
+            });
+        }
+
+        let mut trace: MachineTraceOpening<SC, _> = MachineTraceOpeningBuilder::new(&chips);
+
+        let mut preprocessed_degrees = (0..trace.len()).map(|_| 0usize).collect_vec();
+        if let Some(preprocessed) = &instance.vk.preprocessed {
+            for (i, degree) in preprocessed.degrees.iter() {
+                preprocessed_degrees[*i] = *degree;
+            }
+        }
+        // TODO: Avoid clone
+        trace.load_openings(
+            pcs,
+            instance.proof.chip_proofs.clone(),
+            preprocessed_degrees,
+        )?;
+
+        // Verify proof shape
+        trace.verify_shapes()?;
+        verify_commitment_presence(&trace, instance.vk, &instance.proof.commitments)?;
+
+        traces.push(trace);
+    }
+
+    // Observe public values and commitments
+    for instance in instances.iter() {
+        channel.send_public_values(instance.public_values);
+    }
+    for instance in instances.iter() {
+        if let Some(preprocessed) = &instance.vk.preprocessed {
+            channel.send_commitment(ProverRound::Preprocessed, &preprocessed.commitment);
+        }
+    }
+    for instance in instances.iter() {
+        if let Some(main) = &instance.proof.commitments.main {
+            channel.send_commitment(ProverRound::Main, main);
+        }
+    }
+    let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
+        .map(|_| channel.receive_challenge(ProverRound::Main))
+        .collect_vec()
+        .try_into()
+        .unwrap();
+    for instance in instances.iter() {
+        if let Some(permutation) = &instance.proof.commitments.permutation {
+            channel.send_commitment(ProverRound::Permutation, permutation);
+        }
+    }
+    let alpha = channel.receive_challenge(ProverRound::Permutation);
+    for instance in instances.iter() {
+        if let Some(quotient_chunks) = &instance.proof.commitments.quotient_chunks {
+            channel.send_commitment(ProverRound::Quotient, quotient_chunks);
+        }
+    }
+
+    let zeta = channel.receive_challenge(ProverRound::Quotient);
+
+    for (trace, instance) in traces.iter().zip_eq(instances.iter()) {
+        let commitments = &instance.proof.commitments;
+        // TODO: Remove clone
+        let rounds = trace.generate_rounds(
+            zeta,
+            &instance
+                .vk
+                .preprocessed
+                .as_ref()
+                .map(|preprocessed| preprocessed.commitment.clone()),
+            &commitments.main,
+            &commitments.permutation,
+            &commitments.quotient_chunks,
+        );
+
+        match (rounds.is_empty(), &instance.proof.opening_proof) {
+            (false, Some(opening_proof)) => pcs
+                .verify(rounds, opening_proof, channel.opening_challenger())
+                .map_err(|_| VerificationError::CommitmentMismatch)?,
+            (true, None) => {}
+            _ => {
+                return Err(VerificationError::ShapeMismatch {
+                    chip: None,
+                    detail: "opening proof presence doesn't match the opened traces",
+                })
+            }
+        }
+    }
+
+    let mut reports = Vec::with_capacity(traces.len());
+    for (trace, instance) in traces.iter().zip_eq(instances.iter()) {
+        // Verify that chips sharing public values are evaluated against the same values
+        trace.verify_public_values(instance.public_values, &instance.proof.chip_public_values)?;
+
+        // Verify constraints at zeta
+        reports.push(trace.verify_constraints(
+            zeta,
+            alpha,
+            perm_challenges,
+            instance.public_values,
+        )?);
+
+        // Verify cumulative sum adds to zero
+        trace.verify_cumulative_sums()?;
+    }
+
+    Ok(reports)
+}
+
+/// Checks that `commitments` hold a commitment for exactly the rounds some chip of `trace` is
+/// opened in. Rounds without a commitment are left out of the opening proof, so openings without
+/// one would otherwise be taken at face value.
+fn verify_commitment_presence<SC, C>(
+    trace: &MachineTraceOpening<SC, C>,
+    vk: &VerifyingKey<SC>,
+    commitments: &Commitments<Com<SC>>,
+) -> Result<(), VerificationError>
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    let opened = |round: fn(&ChipTraceOpening<SC, C>) -> bool| trace.iter().any(round);
+    if vk.preprocessed.is_none() && opened(|chip_trace| chip_trace.preprocessed.is_some()) {
+        return Err(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "preprocessed openings without a preprocessed commitment",
+        });
+    }
+    if commitments.main.is_some()
+        != opened(|chip_trace| chip_trace.main.is_some() || chip_trace.main_ext.is_some())
+    {
+        return Err(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "main commitment doesn't match the main openings",
+        });
+    }
+    if commitments.permutation.is_some() != opened(|chip_trace| chip_trace.permutation.is_some()) {
+        return Err(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "permutation commitment doesn't match the permutation openings",
+        });
+    }
+    if commitments.quotient_chunks.is_some()
+        != opened(|chip_trace| chip_trace.quotient_chunks.is_some())
+    {
+        return Err(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "quotient commitment doesn't match the quotient openings",
+        });
+    }
+    Ok(())
+}
+
+/// Checks the constraints of a single chip of a proof of `machine` alone, without the other chips
+/// or the balance of the buses. The challenges are rederived from the commitments of `subproof`
+/// as for a machine proven on its own, and the opened values aren't checked against the
+/// commitments, since the opening proof covers all the chips at once.
+pub fn verify_chip_subproof<SC, M>(
+    config: &SC,
+    challenger: &mut SC::Challenger,
+    machine: &M,
+    vk: &VerifyingKey<SC>,
+    subproof: &ChipSubProof<SC>,
+    public_values: &[Val<SC>],
+) -> Result<ConstraintReport<SC::Challenge>, VerificationError>
+where
+    SC: StarkGenericConfig,
+    M: Machine,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip:
+        for<'b> Rap<VerifierConstraintFolder<'b, SC>> + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+{
+    let pcs = config.pcs();
+
+    if vk.format_version != FORMAT_VERSION {
+        return Err(VerificationError::UnsupportedFormatVersion {
+            found: vk.format_version,
+            expected: FORMAT_VERSION,
+        });
+    }
+    let chips = machine.canonical_chips();
+    let chip_names: Vec<String> = chips.iter().map(ToString::to_string).collect();
+    if chip_names != vk.chips {
+        return Err(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "chips don't match the verifying key",
+        });
+    }
+    let chip = chips
+        .get(subproof.chip)
+        .ok_or(VerificationError::ShapeMismatch {
+            chip: None,
+            detail: "chip index out of bounds",
+        })?;
+
+    let preprocessed_degree = vk
+        .preprocessed
+        .as_ref()
+        .and_then(|preprocessed| {
+            preprocessed
+                .degrees
+                .iter()
+                .find(|(i, _)| *i == subproof.chip)
+        })
+        .map_or(0, |(_, degree)| *degree);
+    let mut trace: MachineTraceOpening<SC, _> = MachineTraceOpeningBuilder::new(&[chip.clone()]);
+    trace
+        .load_openings(
+            pcs,
+            vec![Some(subproof.chip_proof.clone())],
+            vec![preprocessed_degree],
+        )
+        .map_err(|err| err.reindex_chip(subproof.chip))?;
+    trace
+        .verify_shapes()
+        .map_err(|err| err.reindex_chip(subproof.chip))?;
+
+    // Replay the transcript of the machine's proof
+    challenger.observe_slice(public_values);
+    if let Some(preprocessed) = &vk.preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
+    if let Some(main) = &subproof.commitments.main {
+        challenger.observe(main.clone());
+    }
+    let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
+        .map(|_| challenger.sample_ext_element::<SC::Challenge>())
+        .collect_vec()
+        .try_into()
+        .unwrap();
+    if let Some(permutation) = &subproof.commitments.permutation {
+        challenger.observe(permutation.clone());
+    }
+    let alpha = challenger.sample_ext_element::<SC::Challenge>();
+    if let Some(quotient_chunks) = &subproof.commitments.quotient_chunks {
+        challenger.observe(quotient_chunks.clone());
+    }
+    let zeta: SC::Challenge = challenger.sample_ext_element();
+
+    trace
+        .verify_constraints(zeta, alpha, perm_challenges, public_values)
+        .map_err(|err| err.reindex_chip(subproof.chip))?
+        .pop()
+        .flatten()
+        .ok_or(VerificationError::ShapeMismatch {
+            chip: Some(subproof.chip),
+            detail: "chip has no traces",
+        })
+}
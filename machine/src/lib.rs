@@ -3,10 +3,19 @@
 
 extern crate alloc;
 
+pub mod audit;
+pub mod channel;
 pub mod chip;
 pub mod error;
+pub mod joint;
 pub mod machine;
+#[cfg(feature = "memory-log")]
+pub mod memory;
 pub mod proof;
+pub mod public;
 pub mod quotient;
+pub mod report;
+pub mod size;
 pub mod trace;
+pub mod util;
 pub mod verify;
@@ -1,11 +1,9 @@
+use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 
-use itertools::Itertools;
-use p3_challenger::{CanObserve, FieldChallenger};
-use p3_commit::{Pcs, PolynomialSpace};
-#[cfg(feature = "schema")]
-use p3_field::Field;
-use p3_field::PrimeField32;
+use p3_commit::PolynomialSpace;
+use p3_field::{Field, PrimeField32, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_uni_stark::{StarkGenericConfig, Val};
 use tracing::instrument;
@@ -14,26 +12,25 @@ use p3_air_util::folders::rap::{
     DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder, TrackingConstraintBuilder,
     VerifierConstraintFolder,
 };
-use p3_air_util::proof::Commitments;
-#[cfg(feature = "schema")]
-use p3_interaction::InteractionAir;
-use p3_interaction::{Bus, Rap, NUM_PERM_CHALLENGES};
+use p3_air_util::{get_max_constraint_degree, get_quotient_degree};
+use p3_interaction::{interaction_bus_max, Bus, InteractionAir, Rap};
 
-#[cfg(debug_assertions)]
-use crate::trace::MachineTraceChecker;
-#[cfg(feature = "air-logger")]
-use crate::trace::MachineTraceDebugger;
 use crate::{
-    chip::Chip,
-    error::VerificationError,
-    proof::{
-        MachineProof, ProverPreprocessedData, ProvingKey, VerifierPreprocessedData, VerifyingKey,
+    audit::{audit_buses, audit_chip, AuditReport},
+    channel::{FiatShamirChannel, ProverChannel},
+    chip::{canonical_chip_order, Chip},
+    error::{MachineError, PublicValueLayoutError, VerificationError},
+    joint::{
+        prove_joint_with_channel, verify_chip_subproof, verify_joint_with_channel,
+        verify_joint_with_reports, ProvingInstance, VerifyingInstance,
     },
-    trace::{
-        MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceConstraintVerifier,
-        MachineTraceLoader, MachineTraceOpener, MachineTraceOpening, MachineTraceOpeningBuilder,
-        MachineTraceOpeningLoader, MachineTraceOpeningVerifier,
+    proof::{
+        ChipSubProof, MachineProof, ProverPreprocessedData, ProvingKey, QuotientDomains,
+        VerifierPreprocessedData, VerifyingKey, FORMAT_VERSION,
     },
+    public::PublicValueLayout,
+    trace::{MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceLoader},
+    verify::ConstraintReport,
 };
 
 pub trait Machine {
@@ -41,8 +38,69 @@ pub trait Machine {
 
     type Bus: Bus;
 
+    /// The machine's chips, in any order. Chips are proven in canonical order, see
+    /// [`Machine::canonical_chips`].
     fn chips(&self) -> Vec<Self::Chip>;
 
+    /// The number of public values the machine is proven against, if fixed. When set, proofs
+    /// with a different number of public values are rejected before any constraint is evaluated.
+    fn num_public_values(&self) -> Option<usize> {
+        None
+    }
+
+    /// The bound on the degree of the machine's constraints, if any. Only checked by
+    /// [`Machine::audit`].
+    fn max_constraint_degree(&self) -> Option<usize> {
+        None
+    }
+
+    /// Checks the machine's chips symbolically, without traces: the constraint degrees against
+    /// [`Machine::max_constraint_degree`], that every bus is both sent to and received from with
+    /// a consistent number of fields, and that every main column is constrained.
+    fn audit<F>(&self) -> AuditReport
+    where
+        F: Field,
+        Self::Chip: Rap<SymbolicAirBuilder<F>>,
+    {
+        let chips = self.canonical_chips();
+        let chip_audits = chips
+            .iter()
+            .map(|chip| {
+                let num_public_values = chip
+                    .public_value_range()
+                    .map(|range| range.len())
+                    .or(self.num_public_values())
+                    .unwrap_or_default();
+                audit_chip::<F, _>(chip, chip.to_string(), num_public_values)
+            })
+            .collect();
+        AuditReport {
+            chips: chip_audits,
+            buses: audit_buses::<F, _>(&chips, |i| Self::Bus::from(i).to_string()),
+            max_constraint_degree: self.max_constraint_degree(),
+        }
+    }
+
+    /// The machine's chips in canonical order. Traces, proofs and keys are laid out in this order.
+    fn canonical_chips(&self) -> Vec<Self::Chip> {
+        let chips = self.chips();
+        canonical_chip_order(&chips)
+            .into_iter()
+            .map(|i| chips[i].clone())
+            .collect()
+    }
+
+    /// Lays out `public_values` across the machine's chips in canonical order. The prover and the
+    /// verifier evaluate each chip against the same slices.
+    fn public_value_layout<'a, T>(
+        &self,
+        public_values: &'a [T],
+    ) -> Result<PublicValueLayout<'a, T>, PublicValueLayoutError> {
+        PublicValueLayout::from_chips(&self.canonical_chips(), public_values)
+    }
+
+    /// Generates and commits to the preprocessed traces. Panics if a chip fails to generate its
+    /// preprocessed trace, see [`Machine::try_setup`].
     fn setup<'a, SC>(&self, config: &'a SC) -> (ProvingKey<SC>, VerifyingKey<SC>)
     where
         SC: StarkGenericConfig,
@@ -50,14 +108,46 @@ pub trait Machine {
             + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
             + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
             + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+        Val<SC>: TwoAdicField,
+    {
+        self.try_setup(config)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like [`Machine::setup`], but returns the error of a chip that fails to generate its
+    /// preprocessed trace, see [`Chip::check_preprocessed_inputs`].
+    fn try_setup<'a, SC>(
+        &self,
+        config: &'a SC,
+    ) -> Result<(ProvingKey<SC>, VerifyingKey<SC>), MachineError>
+    where
+        SC: StarkGenericConfig,
+        Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+            + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+        Val<SC>: TwoAdicField,
     {
         let pcs = config.pcs();
-        let chips = self.chips();
+        let chips = self.canonical_chips();
+        debug_assert!(
+            chips.iter().all(|chip| {
+                InteractionAir::<Val<SC>>::interaction_bus_max(chip)
+                    == interaction_bus_max(&InteractionAir::<Val<SC>>::all_interactions(chip))
+            }),
+            "Chip bus indices must match its interactions"
+        );
+        let num_buses = chips
+            .iter()
+            .map(|chip| {
+                InteractionAir::<Val<SC>>::interaction_bus_max(chip).map_or(0, |max| max + 1)
+            })
+            .collect();
         let mut trace: MachineTrace<SC, _> = MachineTraceBuilder::new(chips.as_slice());
 
         // 1. Generate and commit to preprocessed traces
         tracing::info_span!("generate preprocessed traces")
-            .in_scope(|| trace.generate_preprocessed(pcs));
+            .in_scope(|| trace.generate_preprocessed(pcs))?;
 
         let traces = trace
             .iter()
@@ -68,6 +158,28 @@ pub trait Machine {
                     .map(|preprocessed| preprocessed.trace.value.clone())
             })
             .collect();
+        // The quotient domains of chips with a preprocessed trace only depend on its height
+        let quotient_domains = trace
+            .iter()
+            .map(|chip_trace| {
+                let trace_domain = chip_trace.preprocessed.as_ref()?.trace.domain;
+                let num_public_values = chip_trace
+                    .chip
+                    .public_value_range()
+                    .map(|range| range.len())
+                    .or(self.num_public_values())?;
+                let quotient_degree =
+                    get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, num_public_values);
+                let quotient_domain =
+                    trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
+                Some(QuotientDomains {
+                    quotient_degree,
+                    trace_domain,
+                    quotient_domain,
+                    chunk_domains: quotient_domain.split_domains(quotient_degree),
+                })
+            })
+            .collect();
         let indexed_degrees: Vec<(usize, usize)> = trace
             .iter()
             .enumerate()
@@ -97,13 +209,18 @@ pub trait Machine {
         };
 
         let vk = VerifyingKey {
+            format_version: FORMAT_VERSION,
+            chips: chips.iter().map(ToString::to_string).collect(),
             preprocessed: verifier_data,
         };
         let pk = ProvingKey {
+            format_version: FORMAT_VERSION,
             preprocessed: prover_data,
+            quotient_domains,
+            num_buses,
         };
 
-        (pk, vk)
+        Ok((pk, vk))
     }
 
     fn prove<'a, SC>(
@@ -116,6 +233,7 @@ pub trait Machine {
         public_values: &'a [Val<SC>],
     ) -> MachineProof<SC>
     where
+        Self: Sized,
         SC: StarkGenericConfig,
         Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
             + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
@@ -123,113 +241,47 @@ pub trait Machine {
             + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
             // TODO: Put behind air-logger feature
             + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
-        Val<SC>: PrimeField32,
+        Val<SC>: PrimeField32 + TwoAdicField,
     {
-        // TODO: Use fixed size array instead of Vecs
-        let chips = self.chips();
-        assert_eq!(main_traces.len(), chips.len(), "Length mismatch");
-
-        let pcs = config.pcs();
-
-        // 1. Observe public values
-        challenger.observe_slice(public_values);
-
-        let mut trace: MachineTrace<SC, _> = MachineTraceBuilder::new(&chips);
-
-        // 2. Observe preprocessed commitment
-        tracing::info_span!("load preprocessed traces")
-            .in_scope(|| trace.load_preprocessed(pcs, pk.preprocessed.traces.as_slice()));
-        if let Some(commit) = &pk.preprocessed.commitment {
-            challenger.observe(commit.clone());
-        }
-
-        // 3. Generate and commit to main trace
-        tracing::info_span!("load main traces").in_scope(|| trace.load_main(pcs, main_traces));
-        let (main_commit, main_data) =
-            tracing::info_span!("commit to main traces").in_scope(|| trace.commit_main(pcs));
-        if let Some(main_commit) = &main_commit {
-            challenger.observe(main_commit.clone());
-        }
-
-        // 4. Sample permutation challenges
-        let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
-            .map(|_| challenger.sample_ext_element::<SC::Challenge>())
-            .collect_vec()
-            .try_into()
-            .unwrap();
-
-        // 5. Generate and commit to permutation trace
-        tracing::info_span!("generate permutation traces")
-            .in_scope(|| trace.generate_permutation(pcs, perm_challenges));
-        let (permutation_commit, permutation_data) =
-            tracing::info_span!("commit to permutation traces")
-                .in_scope(|| trace.commit_permutation(pcs));
-        if let Some(permutation_commit) = &permutation_commit {
-            challenger.observe(permutation_commit.clone());
-        }
-        let alpha: SC::Challenge = challenger.sample_ext_element();
-
-        #[cfg(feature = "air-logger")]
-        let _ = tracing::info_span!("writing traces to file")
-            .in_scope(|| trace.write_traces_to_file("trace.xlsx", perm_challenges));
-
-        // Verify constraints
-        #[cfg(debug_assertions)]
-        tracing::info_span!("checking constraints")
-            .in_scope(|| trace.check_constraints::<Self::Bus>(perm_challenges, &[]));
-
-        // 6. Generate and commit to quotient traces
-        tracing::info_span!("generate quotient trace").in_scope(|| {
-            trace.generate_quotient(
-                pcs,
-                &pk.preprocessed.data,
-                &main_data,
-                &permutation_data,
-                perm_challenges,
-                alpha,
-                public_values,
-            )
-        });
-        // TODO: Panic if this is None
-        let (quotient_commit, quotient_data) = tracing::info_span!("commit to quotient chunks")
-            .in_scope(|| trace.commit_quotient(pcs));
-        if let Some(quotient_commit) = &quotient_commit {
-            challenger.observe(quotient_commit.clone());
-        }
-
-        let commitments = Commitments {
-            main: main_commit,
-            permutation: permutation_commit,
-            quotient_chunks: quotient_commit,
-        };
-
-        // 7. Sample OOD point and generate opening proof
-        let zeta: SC::Challenge = challenger.sample_ext_element();
-        let rounds = trace.generate_rounds(
-            zeta,
-            &pk.preprocessed.data,
-            &main_data,
-            &permutation_data,
-            &quotient_data,
-        );
-        let (opening_values, opening_proof) = pcs.open(rounds, challenger);
-
-        // Unflatten quotient openings
-        let opening_values = trace.unflatten_openings(
-            opening_values,
-            &pk.preprocessed.data,
-            &main_data,
-            &permutation_data,
-            &quotient_data,
-        );
-
-        let chip_proofs = trace.generate_proofs(opening_values);
+        self.prove_with_channel(
+            config,
+            &mut FiatShamirChannel::new(challenger),
+            pk,
+            main_traces,
+            public_values,
+        )
+    }
 
-        MachineProof {
-            commitments,
-            opening_proof,
-            chip_proofs,
-        }
+    /// Proves the machine round by round, exchanging commitments and challenges with a verifier
+    /// through `channel`.
+    fn prove_with_channel<'a, SC, Ch>(
+        &self,
+        config: &'a SC,
+        channel: &mut Ch,
+        pk: &'a ProvingKey<SC>,
+        main_traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        // TODO: Change to 2d vector?
+        public_values: &'a [Val<SC>],
+    ) -> MachineProof<SC>
+    where
+        Self: Sized,
+        SC: StarkGenericConfig,
+        Ch: ProverChannel<SC>,
+        Self::Chip: for<'b> Rap<ProverConstraintFolder<'b, SC>>
+            + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>
+            + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>
+            // TODO: Put behind air-logger feature
+            + for<'b> Rap<TrackingConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
+        Val<SC>: PrimeField32 + TwoAdicField,
+    {
+        prove_joint_with_channel(
+            config,
+            channel,
+            vec![ProvingInstance::new(self, pk, main_traces, public_values)],
+        )
+        .pop()
+        .unwrap()
     }
 
     #[instrument(skip_all)]
@@ -242,77 +294,99 @@ pub trait Machine {
         public_values: &'a [Val<SC>],
     ) -> Result<(), VerificationError>
     where
+        Self: Sized,
         SC: StarkGenericConfig,
-        Val<SC>: PrimeField32,
+        Val<SC>: PrimeField32 + TwoAdicField,
         Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
             + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
     {
-        let chips = self.chips();
-        let pcs = config.pcs();
-
-        let mut trace: MachineTraceOpening<SC, _> = MachineTraceOpeningBuilder::new(&chips);
-
-        let MachineProof {
-            commitments,
-            opening_proof,
-            chip_proofs,
-        } = proof;
-
-        let mut preprocessed_degrees = (0..trace.len()).map(|_| 0usize).collect_vec();
-        if let Some(preprocessed) = &vk.preprocessed {
-            for (i, degree) in preprocessed.degrees.iter() {
-                preprocessed_degrees[*i] = *degree;
-            }
-        }
-        // TODO: Avoid clone
-        trace.load_openings(pcs, chip_proofs.clone(), preprocessed_degrees);
-
-        // Verify proof shape
-        trace.verify_shapes()?;
-
-        // Observe commitments
-        if let Some(preprocessed) = &vk.preprocessed {
-            challenger.observe(preprocessed.commitment.clone());
-        }
-        if let Some(main) = &commitments.main {
-            challenger.observe(main.clone());
-        }
-        let perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES] = (0..NUM_PERM_CHALLENGES)
-            .map(|_| challenger.sample_ext_element::<SC::Challenge>())
-            .collect_vec()
-            .try_into()
-            .unwrap();
-        if let Some(permutation) = &commitments.permutation {
-            challenger.observe(permutation.clone());
-        }
-        let alpha = challenger.sample_ext_element::<SC::Challenge>();
-        if let Some(quotient_chunks) = &commitments.quotient_chunks {
-            challenger.observe(quotient_chunks.clone());
-        }
-
-        let zeta: SC::Challenge = challenger.sample_ext_element();
-
-        // TODO: Remove clone
-        let rounds = trace.generate_rounds(
-            zeta,
-            &vk.preprocessed
-                .as_ref()
-                .map(|preprocessed| preprocessed.commitment.clone()),
-            &commitments.main,
-            &commitments.permutation,
-            &commitments.quotient_chunks,
-        );
-
-        pcs.verify(rounds, opening_proof, challenger)
-            .map_err(|_| VerificationError::InvalidOpeningArgument)?;
+        self.verify_with_report(config, challenger, vk, proof, public_values)
+            .map(|_| ())
+    }
 
-        // Verify constraints at zeta
-        trace.verify_constraints(zeta, alpha, perm_challenges, public_values)?;
+    /// Like [`Machine::verify`], but also returns the constraint evaluations of each chip in
+    /// canonical order, or `None` for chips without traces.
+    #[instrument(skip_all)]
+    fn verify_with_report<'a, SC>(
+        &self,
+        config: &'a SC,
+        challenger: &'a mut SC::Challenger,
+        vk: &'a VerifyingKey<SC>,
+        proof: &MachineProof<SC>,
+        public_values: &'a [Val<SC>],
+    ) -> Result<Vec<Option<ConstraintReport<SC::Challenge>>>, VerificationError>
+    where
+        Self: Sized,
+        SC: StarkGenericConfig,
+        Val<SC>: PrimeField32 + TwoAdicField,
+        Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    {
+        let mut reports = verify_joint_with_reports(
+            config,
+            challenger,
+            vec![VerifyingInstance {
+                machine: self,
+                vk,
+                proof,
+                public_values,
+            }],
+        )?;
+        Ok(reports.pop().unwrap())
+    }
 
-        // Verify cumulative sum adds to zero
-        trace.verify_cumulative_sums()?;
+    /// Like [`Machine::verify_with_report`], but derives the challenges through `channel`, see
+    /// [`verify_joint_with_channel`].
+    #[instrument(skip_all)]
+    fn verify_with_channel<'a, SC, Ch>(
+        &self,
+        config: &'a SC,
+        channel: &mut Ch,
+        vk: &'a VerifyingKey<SC>,
+        proof: &MachineProof<SC>,
+        public_values: &'a [Val<SC>],
+    ) -> Result<Vec<Option<ConstraintReport<SC::Challenge>>>, VerificationError>
+    where
+        Self: Sized,
+        SC: StarkGenericConfig,
+        Ch: ProverChannel<SC>,
+        Val<SC>: PrimeField32 + TwoAdicField,
+        Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    {
+        let mut reports = verify_joint_with_channel(
+            config,
+            channel,
+            vec![VerifyingInstance {
+                machine: self,
+                vk,
+                proof,
+                public_values,
+            }],
+        )?;
+        Ok(reports.pop().unwrap())
+    }
 
-        Ok(())
+    /// Checks the constraints of a single chip of a proof, extracted with
+    /// [`MachineProof::chip_subproof`], in isolation from the other chips. See
+    /// [`verify_chip_subproof`].
+    #[instrument(skip_all)]
+    fn verify_chip_subproof<'a, SC>(
+        &self,
+        config: &'a SC,
+        challenger: &'a mut SC::Challenger,
+        vk: &'a VerifyingKey<SC>,
+        subproof: &ChipSubProof<SC>,
+        public_values: &'a [Val<SC>],
+    ) -> Result<ConstraintReport<SC::Challenge>, VerificationError>
+    where
+        Self: Sized,
+        SC: StarkGenericConfig,
+        Val<SC>: PrimeField32 + TwoAdicField,
+        Self::Chip: for<'b> Rap<VerifierConstraintFolder<'b, SC>>
+            + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    {
+        verify_chip_subproof(config, challenger, self, vk, subproof, public_values)
     }
 
     #[cfg(feature = "schema")]
@@ -326,6 +400,7 @@ pub trait Machine {
         use alloc::vec;
         use alloc::vec::Vec;
         use core::iter::once;
+        use itertools::Itertools;
         use p3_air::PairCol;
         use p3_air_util::AirLogger;
         use p3_interaction::InteractionType;
@@ -361,21 +436,32 @@ pub trait Machine {
             let table = format!("Table {} {{\n{}\n}}\n\n", chip, body);
             f.write_all(table.as_bytes()).expect("Unable to write data");
 
-            for (interaction, ty) in chip.all_interactions().iter() {
-                let bus = Self::Bus::from(interaction.argument_index);
+            for (bus_index, interactions) in chip.interactions_by_bus() {
+                let num_fields = interactions
+                    .iter()
+                    .map(|(interaction, _)| interaction.num_fields())
+                    .max()
+                    .unwrap_or_default();
                 bus_lengths
-                    .entry(bus as usize)
+                    .entry(bus_index)
                     .and_modify(|existing_length| {
-                        *existing_length =
-                            core::cmp::max(*existing_length, interaction.fields.len());
+                        *existing_length = core::cmp::max(*existing_length, num_fields);
                     })
-                    .or_insert(interaction.fields.len());
-
+                    .or_insert(num_fields);
+            }
+            for (interaction, ty) in chip.all_interactions().iter() {
+                let bus = Self::Bus::from(interaction.argument_index);
                 let direction = match ty {
                     InteractionType::Receive => '<',
                     InteractionType::Send => '>',
                 };
-                for (col, _) in interaction.count.column_weights.iter() {
+                // The filter gates the multiplicity, so its columns count towards it
+                for (col, _) in interaction.count.column_weights.iter().chain(
+                    interaction
+                        .filter
+                        .iter()
+                        .flat_map(|filter| &filter.column_weights),
+                ) {
                     let header = match col {
                         PairCol::Preprocessed(k) => &preprocessed_headers[*k],
                         PairCol::Main(k) => &main_headers[*k],
@@ -390,7 +476,12 @@ pub trait Machine {
                         chip_rows.insert(row, direction);
                     }
                 }
-                for (j, field) in interaction.fields.iter().enumerate() {
+                for (j, field) in interaction
+                    .fields
+                    .iter()
+                    .chain(interaction.next_fields.iter())
+                    .enumerate()
+                {
                     for (col, _) in field.column_weights.iter() {
                         let header = match col {
                             PairCol::Preprocessed(k) => &preprocessed_headers[*k],
@@ -426,3 +517,36 @@ pub trait Machine {
         }
     }
 }
+
+/// Returns the maximum degree among the constraints of `chip`, including its permutation
+/// constraints. Like [`chip_quotient_degrees`], it only evaluates the constraints symbolically.
+pub fn chip_constraint_degree<F, C>(chip: &C, num_public_values: usize) -> usize
+where
+    F: Field,
+    C: Chip + Rap<SymbolicAirBuilder<F>>,
+{
+    let num_public_values = chip
+        .public_value_range()
+        .map_or(num_public_values, |range| range.len());
+    get_max_constraint_degree::<F, _>(chip, num_public_values)
+}
+
+/// Returns the quotient degree of each of `chips`, i.e. the number of chunks its quotient is split
+/// into, from the chips' constraints alone. It doesn't need any traces, so it can be used to size
+/// commitments before setup. A chip is evaluated against as many public values as its public value
+/// range covers, and `num_public_values` otherwise.
+pub fn chip_quotient_degrees<F, C>(chips: &[&C], num_public_values: usize) -> Vec<usize>
+where
+    F: Field,
+    C: Chip + Rap<SymbolicAirBuilder<F>>,
+{
+    chips
+        .iter()
+        .map(|chip| {
+            let num_public_values = chip
+                .public_value_range()
+                .map_or(num_public_values, |range| range.len());
+            get_quotient_degree::<F, _>(*chip, num_public_values)
+        })
+        .collect()
+}
@@ -0,0 +1,96 @@
+use core::mem::size_of;
+
+use p3_uni_stark::{StarkGenericConfig, Val};
+
+use crate::{channel::ProverRound, chip::Chip, trace::MachineTrace};
+
+/// Memory usage of the prover after it committed to the traces of a round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StageMemory {
+    pub round: ProverRound,
+    /// Bytes held by the trace matrices the prover has loaded so far, summed over all machines.
+    /// The low-degree extensions kept by the PCS aren't included.
+    pub trace_bytes: usize,
+    /// Peak resident set size of the process, if the platform reports it.
+    pub peak_resident_bytes: Option<usize>,
+}
+
+impl StageMemory {
+    pub fn sample<SC, C>(round: ProverRound, traces: &[MachineTrace<SC, C>]) -> Self
+    where
+        SC: StarkGenericConfig,
+        C: Chip,
+    {
+        Self {
+            round,
+            trace_bytes: traces.iter().map(trace_bytes).sum(),
+            peak_resident_bytes: peak_resident_bytes(),
+        }
+    }
+
+    /// Emits the sample as a `tracing` event.
+    pub fn log(&self) {
+        tracing::info!(
+            round = ?self.round,
+            trace_bytes = self.trace_bytes,
+            peak_resident_bytes = ?self.peak_resident_bytes,
+            "stage memory"
+        );
+    }
+}
+
+/// Returns the number of bytes held by the trace matrices of `trace`.
+pub fn trace_bytes<SC, C>(trace: &MachineTrace<SC, C>) -> usize
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    let base_bytes = |len: usize| len * size_of::<Val<SC>>();
+    let ext_bytes = |len: usize| len * size_of::<SC::Challenge>();
+    trace
+        .iter()
+        .map(|chip_trace| {
+            let preprocessed = chip_trace.preprocessed.as_ref().map_or(0, |preprocessed| {
+                base_bytes(preprocessed.trace.value.values.len())
+            });
+            let main = chip_trace
+                .main
+                .iter()
+                .map(|main| base_bytes(main.trace.value.values.len()))
+                .sum::<usize>();
+            let main_ext = chip_trace
+                .main_ext
+                .as_ref()
+                .map_or(0, |main_ext| ext_bytes(main_ext.trace.value.values.len()));
+            let permutation = chip_trace.permutation.as_ref().map_or(0, |permutation| {
+                ext_bytes(permutation.trace.value.values.len())
+            });
+            let quotient_chunks = chip_trace.quotient_chunks.as_ref().map_or(0, |quotient| {
+                quotient
+                    .traces
+                    .iter()
+                    .map(|chunk| base_bytes(chunk.value.values.len()))
+                    .sum::<usize>()
+            });
+            let quotient_values = chip_trace
+                .quotient_values
+                .as_ref()
+                .map_or(0, |quotient| ext_bytes(quotient.value.values.len()));
+            preprocessed + main + main_ext + permutation + quotient_chunks + quotient_values
+        })
+        .sum()
+}
+
+/// Reads the peak resident set size from `/proc`, which is only available on Linux.
+fn peak_resident_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
@@ -1,17 +1,40 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
 
 use p3_commit::Pcs;
 use p3_matrix::dense::RowMajorMatrix;
-use p3_uni_stark::{StarkGenericConfig, Val};
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use p3_uni_stark::{Domain, StarkGenericConfig, Val};
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use p3_air_util::proof::{Commitments, InteractionAirProof};
 
+#[cfg(feature = "std")]
+use crate::error::ProofDecodeError;
+use crate::size::{serialized_size, ChipProofSize, ProofSize};
+
 pub type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Commitment;
+/// The version of the key and proof formats. Bump this whenever their layout or meaning changes.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Rejects serialized keys and proofs of another format version. Data from before versioning
+/// defaults to version 0 and is rejected when verifying.
+fn deserialize_format_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version != FORMAT_VERSION {
+        return Err(D::Error::custom(format_args!(
+            "unsupported format version {}, expected {}",
+            version, FORMAT_VERSION
+        )));
+    }
+    Ok(version)
+}
+
 pub type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
@@ -25,9 +48,127 @@ pub type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "SC::Challenge: Serialize + DeserializeOwned")]
 pub struct MachineProof<SC: StarkGenericConfig> {
+    #[serde(default, deserialize_with = "deserialize_format_version")]
+    pub format_version: u32,
     pub commitments: Commitments<Com<SC>>,
-    pub opening_proof: PcsProof<SC>,
+    /// `None` if the machine has no traces to open.
+    pub opening_proof: Option<PcsProof<SC>>,
     pub chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
+    /// The public values each chip in canonical order was proven against, see
+    /// [`crate::chip::Chip::public_value_range`].
+    pub chip_public_values: Vec<Vec<Val<SC>>>,
+}
+
+impl<SC: StarkGenericConfig> MachineProof<SC>
+where
+    SC::Challenge: Serialize,
+{
+    /// Breaks down the size of the proof in a compact binary encoding, see [`serialized_size`].
+    pub fn size_estimate(&self) -> ProofSize {
+        ProofSize {
+            commitments: serialized_size(&self.commitments),
+            opening_proof: serialized_size(&self.opening_proof),
+            public_values: serialized_size(&self.chip_public_values),
+            chips: self
+                .chip_proofs
+                .iter()
+                .map(|chip_proof| {
+                    let chip_proof = chip_proof.as_ref()?;
+                    let opened_values = &chip_proof.opened_values;
+                    Some(ChipProofSize {
+                        preprocessed: serialized_size(&opened_values.preprocessed),
+                        main: serialized_size(&opened_values.main),
+                        permutation: serialized_size(&opened_values.permutation),
+                        quotient: serialized_size(&opened_values.quotient_chunks),
+                        other: serialized_size(&chip_proof.degree)
+                            + serialized_size(&chip_proof.cumulative_sum),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> MachineProof<SC> {
+    /// Extracts the part of the proof that concerns the chip at canonical index `chip`, or `None`
+    /// if the chip has no traces.
+    pub fn chip_subproof(&self, chip: usize) -> Option<ChipSubProof<SC>> {
+        let chip_proof = self.chip_proofs.get(chip)?.clone()?;
+        Some(ChipSubProof {
+            chip,
+            commitments: self.commitments.clone(),
+            chip_proof,
+        })
+    }
+}
+
+/// The bytes every encoded [`VersionedProof`] starts with.
+pub const PROOF_MAGIC: [u8; 4] = *b"P3MP";
+
+/// A [`MachineProof`] along with the format version it's encoded with. See
+/// [`VersionedProof::encode`].
+#[cfg(feature = "std")]
+pub struct VersionedProof<SC: StarkGenericConfig> {
+    pub format_version: u32,
+    pub proof: MachineProof<SC>,
+}
+
+#[cfg(feature = "std")]
+impl<SC> VersionedProof<SC>
+where
+    SC: StarkGenericConfig,
+    SC::Challenge: Serialize + DeserializeOwned,
+{
+    pub fn new(proof: MachineProof<SC>) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            proof,
+        }
+    }
+
+    /// Encodes the proof as [`PROOF_MAGIC`], followed by the format version in little-endian and
+    /// the proof in `bincode`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = PROOF_MAGIC.to_vec();
+        bytes.extend(self.format_version.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &self.proof).expect("Proofs are serializable");
+        bytes
+    }
+
+    /// Decodes a proof encoded by [`Self::encode`]. The magic and the format version are checked
+    /// before the proof is deserialized.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProofDecodeError> {
+        let bytes = bytes
+            .strip_prefix(&PROOF_MAGIC)
+            .ok_or(ProofDecodeError::MissingMagic)?;
+        let (format_version, bytes) = bytes
+            .split_first_chunk()
+            .ok_or_else(|| ProofDecodeError::Malformed("missing format version".into()))?;
+        let format_version = u32::from_le_bytes(*format_version);
+        if format_version != FORMAT_VERSION {
+            return Err(ProofDecodeError::UnsupportedFormatVersion {
+                found: format_version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        let proof = bincode::deserialize(bytes)
+            .map_err(|err| ProofDecodeError::Malformed(err.to_string()))?;
+        Ok(Self {
+            format_version,
+            proof,
+        })
+    }
+}
+
+/// The commitments of a [`MachineProof`] along with the openings of a single chip, which is enough
+/// to check that chip's constraints in isolation. See [`crate::machine::Machine::verify_chip_subproof`].
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound = "SC::Challenge: Serialize + DeserializeOwned")]
+pub struct ChipSubProof<SC: StarkGenericConfig> {
+    /// The canonical index of the chip.
+    pub chip: usize,
+    pub commitments: Commitments<Com<SC>>,
+    pub chip_proof: InteractionAirProof<SC::Challenge>,
 }
 
 pub struct ProverPreprocessedData<SC: StarkGenericConfig> {
@@ -43,11 +184,103 @@ pub struct VerifierPreprocessedData<SC: StarkGenericConfig> {
     pub degrees: Vec<(usize, usize)>,
 }
 
+/// The domains a chip's quotient is computed and split over.
+pub struct QuotientDomains<SC: StarkGenericConfig> {
+    pub quotient_degree: usize,
+    pub trace_domain: Domain<SC>,
+    pub quotient_domain: Domain<SC>,
+    pub chunk_domains: Vec<Domain<SC>>,
+}
+
 pub struct ProvingKey<SC: StarkGenericConfig> {
+    pub format_version: u32,
     pub preprocessed: ProverPreprocessedData<SC>,
+    /// Quotient domains of the chips in canonical order, for chips whose height is fixed by their
+    /// preprocessed trace and whose number of public values is known at setup.
+    pub quotient_domains: Vec<Option<QuotientDomains<SC>>>,
+    /// Number of buses each chip in canonical order interacts on, i.e. one more than its largest
+    /// bus index, which sizes the RLC elements of its permutation trace.
+    pub num_buses: Vec<usize>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct VerifyingKey<SC: StarkGenericConfig> {
+    #[serde(default, deserialize_with = "deserialize_format_version")]
+    pub format_version: u32,
+    /// Names of the chips in canonical order.
+    pub chips: Vec<String>,
     pub preprocessed: Option<VerifierPreprocessedData<SC>>,
 }
+
+/// A difference between two verifying keys. See [`VerifyingKey::compatibility_report`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyMismatch {
+    FormatVersion,
+    /// The keys are for different chips.
+    Chips,
+    /// Only one of the keys commits to preprocessed traces.
+    PreprocessedPresence,
+    PreprocessedCommitment,
+    /// The preprocessed trace of the chip at this canonical index has a different degree, or is
+    /// only present in one of the keys.
+    PreprocessedDegree {
+        chip: usize,
+    },
+}
+
+impl Display for KeyMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::FormatVersion => write!(f, "Format versions differ"),
+            Self::Chips => write!(f, "Chips differ"),
+            Self::PreprocessedPresence => write!(f, "Preprocessed commitment is only in one key"),
+            Self::PreprocessedCommitment => write!(f, "Preprocessed commitment differs"),
+            Self::PreprocessedDegree { chip } => {
+                write!(f, "Preprocessed degree of chip {} differs", chip)
+            }
+        }
+    }
+}
+
+impl<SC> VerifyingKey<SC>
+where
+    SC: StarkGenericConfig,
+    Com<SC>: PartialEq,
+{
+    /// Whether proofs made with the proving key of `other` verify against this key.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.compatibility_report(other).is_empty()
+    }
+
+    /// Lists every difference between this key and `other`. An empty report means the keys are
+    /// compatible.
+    pub fn compatibility_report(&self, other: &Self) -> Vec<KeyMismatch> {
+        let mut mismatches = Vec::new();
+        if self.format_version != other.format_version {
+            mismatches.push(KeyMismatch::FormatVersion);
+        }
+        if self.chips != other.chips {
+            mismatches.push(KeyMismatch::Chips);
+        }
+        match (&self.preprocessed, &other.preprocessed) {
+            (Some(preprocessed), Some(other_preprocessed)) => {
+                if preprocessed.commitment != other_preprocessed.commitment {
+                    mismatches.push(KeyMismatch::PreprocessedCommitment);
+                }
+                let degrees: BTreeMap<_, _> = preprocessed.degrees.iter().copied().collect();
+                let other_degrees: BTreeMap<_, _> =
+                    other_preprocessed.degrees.iter().copied().collect();
+                let chips: BTreeSet<_> = degrees.keys().chain(other_degrees.keys()).collect();
+                mismatches.extend(
+                    chips
+                        .into_iter()
+                        .filter(|chip| degrees.get(*chip) != other_degrees.get(*chip))
+                        .map(|&chip| KeyMismatch::PreprocessedDegree { chip }),
+                );
+            }
+            (None, None) => {}
+            _ => mismatches.push(KeyMismatch::PreprocessedPresence),
+        }
+        mismatches
+    }
+}
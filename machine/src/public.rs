@@ -0,0 +1,122 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::chip::Chip;
+use crate::error::PublicValueLayoutError;
+
+/// The slice of a machine's public values that each of its chips is evaluated against. The prover
+/// and the verifier both slice the public values through the layout, so they can't disagree on
+/// what a chip sees.
+#[derive(Clone, Debug)]
+pub struct PublicValueLayout<'a, T> {
+    public_values: &'a [T],
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'a, T> PublicValueLayout<'a, T> {
+    /// Lays out `public_values` by the [`Chip::public_value_range`] of each chip, in the order of
+    /// `chips`. A chip without a range sees all public values, and chips may share public values.
+    pub fn from_chips<'c, C>(
+        chips: impl IntoIterator<Item = &'c C>,
+        public_values: &'a [T],
+    ) -> Result<Self, PublicValueLayoutError>
+    where
+        C: Chip + 'c,
+    {
+        let ranges = chips
+            .into_iter()
+            .map(|chip| chip.public_value_range().unwrap_or(0..public_values.len()))
+            .collect();
+        Self::new(ranges, public_values)
+    }
+
+    /// Returns a builder of a layout in which every public value belongs to exactly one chip.
+    pub fn builder() -> PublicValueLayoutBuilder {
+        PublicValueLayoutBuilder::default()
+    }
+
+    fn new(
+        ranges: Vec<Range<usize>>,
+        public_values: &'a [T],
+    ) -> Result<Self, PublicValueLayoutError> {
+        if let Some(chip) = ranges
+            .iter()
+            .position(|range| range.start > range.end || range.end > public_values.len())
+        {
+            return Err(PublicValueLayoutError::OutOfBounds { chip });
+        }
+        Ok(Self {
+            public_values,
+            ranges,
+        })
+    }
+
+    /// The public values the chip at `chip` is evaluated against.
+    pub fn public_values_for(&self, chip: usize) -> &'a [T] {
+        &self.public_values[self.ranges[chip].clone()]
+    }
+
+    /// The range of the public values the chip at `chip` is evaluated against.
+    pub fn range(&self, chip: usize) -> Range<usize> {
+        self.ranges[chip].clone()
+    }
+
+    pub fn num_chips(&self) -> usize {
+        self.ranges.len()
+    }
+}
+
+/// Builds a [`PublicValueLayout`] from the range of every chip, see
+/// [`PublicValueLayoutBuilder::build`].
+#[derive(Clone, Debug, Default)]
+pub struct PublicValueLayoutBuilder {
+    ranges: Vec<Range<usize>>,
+}
+
+impl PublicValueLayoutBuilder {
+    /// Adds the next chip, which is evaluated against the public values in `range`.
+    pub fn with_chip(mut self, range: Range<usize>) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    /// Lays out `public_values` by the ranges of the chips, checking that every public value
+    /// belongs to exactly one chip.
+    pub fn build<T>(
+        self,
+        public_values: &[T],
+    ) -> Result<PublicValueLayout<'_, T>, PublicValueLayoutError> {
+        let layout = PublicValueLayout::new(self.ranges, public_values)?;
+
+        // Chip indices sorted by the start of their ranges, skipping chips without public values
+        let mut order = (0..layout.num_chips())
+            .filter(|&i| !layout.ranges[i].is_empty())
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&i| layout.ranges[i].start);
+
+        let mut end = 0;
+        let mut last = None;
+        for i in order {
+            let range = &layout.ranges[i];
+            match last {
+                Some(last) if range.start < end => {
+                    return Err(PublicValueLayoutError::Overlap {
+                        chips: (last, i),
+                        index: range.start,
+                    });
+                }
+                _ if range.start > end => {
+                    return Err(PublicValueLayoutError::Gap { index: end });
+                }
+                _ => {}
+            }
+            end = range.end;
+            last = Some(i);
+        }
+        if end < public_values.len() {
+            return Err(PublicValueLayoutError::Gap { index: end });
+        }
+
+        Ok(layout)
+    }
+}
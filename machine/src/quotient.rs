@@ -1,6 +1,8 @@
+use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
+use p3_air::Air;
 use p3_commit::PolynomialSpace;
 use p3_field::{AbstractExtensionField, AbstractField, PackedValue};
 use p3_interaction::{Rap, NUM_PERM_CHALLENGES};
@@ -9,15 +11,24 @@ use p3_maybe_rayon::prelude::{IntoParallelIterator, ParIterExt};
 use p3_uni_stark::{Domain, PackedChallenge, PackedVal, StarkGenericConfig, Val};
 use p3_util::log2_strict_usize;
 
-use p3_air_util::folders::rap::ProverConstraintFolder;
+use p3_air_util::folders::{rap::ProverConstraintFolder, window};
 
-pub fn quotient_values<SC, A, Mat>(
+use crate::trace::trace_generator_inv;
+
+/// Evaluates the quotient of `air` on `quotient_domain`. The traces are only read row by row, so
+/// they can be lazy views of the committed low-degree extensions, and nothing proportional to the
+/// size of the quotient domain is allocated besides the selectors and the returned values. The main
+/// trace is given as its segments, and is absent if there are none. The extension field columns of
+/// the main trace, like the permutation trace, are given flattened to the base field.
+#[allow(clippy::too_many_arguments)]
+pub fn quotient_values<SC, A, PMat, MMat, EMat, QMat>(
     air: &A,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
-    preprocessed_trace_on_quotient_domain: Mat,
-    main_trace_on_quotient_domain: Mat,
-    perm_trace_on_quotient_domain: Mat,
+    preprocessed_trace_on_quotient_domain: Option<PMat>,
+    main_trace_on_quotient_domain: Vec<MMat>,
+    main_ext_trace_on_quotient_domain: Option<EMat>,
+    perm_trace_on_quotient_domain: Option<QMat>,
     perm_challenges: [PackedChallenge<SC>; NUM_PERM_CHALLENGES],
     alpha: PackedChallenge<SC>,
     cumulative_sum: PackedChallenge<SC>,
@@ -26,14 +37,18 @@ pub fn quotient_values<SC, A, Mat>(
 where
     SC: StarkGenericConfig,
     A: for<'a> Rap<ProverConstraintFolder<'a, SC>>,
-    Mat: Matrix<Val<SC>> + Sync,
+    PMat: Matrix<Val<SC>>,
+    MMat: Matrix<Val<SC>>,
+    EMat: Matrix<Val<SC>>,
+    QMat: Matrix<Val<SC>>,
 {
     let quotient_size = quotient_domain.size();
-    let perm_width = perm_trace_on_quotient_domain.width();
     let mut sels = trace_domain.selectors_on_coset(quotient_domain);
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
+    let main_rotations = air.main_rotations();
+    let trace_generator_inv = trace_generator_inv(trace_domain);
 
     // assert!(quotient_size >= PackedVal::<SC>::WIDTH);
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
@@ -49,7 +64,6 @@ where
         .into_par_iter()
         .step_by(PackedVal::<SC>::WIDTH)
         .flat_map_iter(|i_start| {
-            let wrap = |i| i % quotient_size;
             let i_range = i_start..i_start + PackedVal::<SC>::WIDTH;
 
             let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
@@ -57,54 +71,48 @@ where
             let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
             let inv_zeroifier = *PackedVal::<SC>::from_slice(&sels.inv_zeroifier[i_range.clone()]);
 
-            // TODO: Any way to do it without collect?
-            let preprocessed_local = preprocessed_trace_on_quotient_domain
-                .vertically_packed_row(i_start)
-                .collect_vec();
-            let preprocessed_next = preprocessed_trace_on_quotient_domain
-                .vertically_packed_row(i_start + next_step)
-                .collect_vec();
-
-            let main_local = main_trace_on_quotient_domain
-                .vertically_packed_row(i_start)
-                .collect_vec();
-            let main_next = main_trace_on_quotient_domain
-                .vertically_packed_row(i_start + next_step)
-                .collect_vec();
+            let preprocessed_local =
+                packed_row::<SC, _>(preprocessed_trace_on_quotient_domain.as_slice(), i_start);
+            let preprocessed_next = packed_row::<SC, _>(
+                preprocessed_trace_on_quotient_domain.as_slice(),
+                i_start + next_step,
+            );
 
-            // TODO: Use vertically_packed
-            let perm_local = (0..perm_width)
-                .step_by(SC::Challenge::D)
-                .map(|col| {
-                    PackedChallenge::<SC>::from_base_fn(|i| {
-                        PackedVal::<SC>::from_fn(|offset| {
-                            perm_trace_on_quotient_domain.get(wrap(i_start + offset), col + i)
-                        })
-                    })
-                })
-                .collect_vec();
-            let perm_next = (0..perm_width)
-                .step_by(SC::Challenge::D)
-                .map(|col| {
-                    PackedChallenge::<SC>::from_base_fn(|i| {
-                        PackedVal::<SC>::from_fn(|offset| {
-                            perm_trace_on_quotient_domain
-                                .get(wrap(i_start + next_step + offset), col + i)
-                        })
-                    })
+            let main_local = packed_row::<SC, _>(&main_trace_on_quotient_domain, i_start);
+            // The next row followed by the rows at the air's rotations
+            let main_next = [1]
+                .into_iter()
+                .chain(main_rotations.iter().copied())
+                .flat_map(|offset| {
+                    packed_row::<SC, _>(
+                        &main_trace_on_quotient_domain,
+                        i_start + offset * next_step,
+                    )
                 })
                 .collect_vec();
 
+            let main_ext_local =
+                packed_ext_row::<SC, _>(main_ext_trace_on_quotient_domain.as_ref(), i_start);
+            let main_ext_next = packed_ext_row::<SC, _>(
+                main_ext_trace_on_quotient_domain.as_ref(),
+                i_start + next_step,
+            );
+
+            let perm_local =
+                packed_ext_row::<SC, _>(perm_trace_on_quotient_domain.as_ref(), i_start);
+            let perm_next = packed_ext_row::<SC, _>(
+                perm_trace_on_quotient_domain.as_ref(),
+                i_start + next_step,
+            );
+
             let accumulator = PackedChallenge::<SC>::zero();
             let mut folder = ProverConstraintFolder {
                 preprocessed: VerticalPair::new(
                     RowMajorMatrixView::new_row(&preprocessed_local),
                     RowMajorMatrixView::new_row(&preprocessed_next),
                 ),
-                main: VerticalPair::new(
-                    RowMajorMatrixView::new_row(&main_local),
-                    RowMajorMatrixView::new_row(&main_next),
-                ),
+                main: window(&main_local, &main_next),
+                main_ext: window(&main_ext_local, &main_ext_next),
                 perm: VerticalPair::new(
                     RowMajorMatrixView::new_row(&perm_local),
                     RowMajorMatrixView::new_row(&perm_next),
@@ -115,10 +123,17 @@ where
                 is_first_row,
                 is_last_row,
                 is_transition,
+                trace_generator_inv,
                 alpha,
                 accumulator,
+                num_constraints: 0,
             };
-            air.eval_all(&mut folder);
+            // Chips without interactions have no permutation trace, nor constraints on it
+            if perm_trace_on_quotient_domain.is_some() {
+                air.eval_all(&mut folder);
+            } else {
+                air.eval(&mut folder);
+            }
 
             // quotient(x) = constraints(x) / Z_H(x)
             let quotient = folder.accumulator * inv_zeroifier;
@@ -134,3 +149,81 @@ where
         })
         .collect()
 }
+
+/// Packs the rows `r..r + PackedVal::<SC>::WIDTH` of the trace made of `segments` side by side,
+/// wrapping around its end.
+fn packed_row<SC, Mat>(segments: &[Mat], r: usize) -> Vec<PackedVal<SC>>
+where
+    SC: StarkGenericConfig,
+    Mat: Matrix<Val<SC>>,
+{
+    segments
+        .iter()
+        .flat_map(|segment| segment.vertically_packed_row(r))
+        .collect()
+}
+
+/// Packs the rows `r..r + PackedVal::<SC>::WIDTH` of an extension field trace that was flattened
+/// to the base field, wrapping around its end. A missing trace has empty rows.
+fn packed_ext_row<SC, Mat>(trace: Option<&Mat>, r: usize) -> Vec<PackedChallenge<SC>>
+where
+    SC: StarkGenericConfig,
+    Mat: Matrix<Val<SC>>,
+{
+    // TODO: Use vertically_packed
+    trace.map_or_else(Vec::new, |trace| {
+        let height = trace.height();
+        (0..trace.width())
+            .step_by(SC::Challenge::D)
+            .map(|col| {
+                PackedChallenge::<SC>::from_base_fn(|i| {
+                    PackedVal::<SC>::from_fn(|offset| trace.get((r + offset) % height, col + i))
+                })
+            })
+            .collect_vec()
+    })
+}
+
+/// Returns the number of constraints the prover folds for `air`, by evaluating them once on rows
+/// of zeros of the given widths. `perm_width` is in extension field elements.
+pub fn num_prover_constraints<SC, A>(
+    air: &A,
+    preprocessed_width: usize,
+    main_width: usize,
+    perm_width: usize,
+    public_values: &[Val<SC>],
+) -> usize
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Rap<ProverConstraintFolder<'a, SC>>,
+{
+    let preprocessed = vec![PackedVal::<SC>::zero(); preprocessed_width];
+    let main_local = vec![PackedVal::<SC>::zero(); main_width];
+    let main_next = vec![PackedVal::<SC>::zero(); main_width * (1 + air.main_rotations().len())];
+    let main_ext = vec![PackedChallenge::<SC>::zero(); air.main_ext_width()];
+    let perm = vec![PackedChallenge::<SC>::zero(); perm_width];
+    let mut folder = ProverConstraintFolder {
+        preprocessed: VerticalPair::new(
+            RowMajorMatrixView::new_row(&preprocessed),
+            RowMajorMatrixView::new_row(&preprocessed),
+        ),
+        main: window(&main_local, &main_next),
+        main_ext: window(&main_ext, &main_ext),
+        perm: VerticalPair::new(
+            RowMajorMatrixView::new_row(&perm),
+            RowMajorMatrixView::new_row(&perm),
+        ),
+        perm_challenges: [PackedChallenge::<SC>::zero(); NUM_PERM_CHALLENGES],
+        public_values,
+        cumulative_sum: PackedChallenge::<SC>::zero(),
+        is_first_row: PackedVal::<SC>::zero(),
+        is_last_row: PackedVal::<SC>::zero(),
+        is_transition: PackedVal::<SC>::zero(),
+        trace_generator_inv: Val::<SC>::one(),
+        alpha: PackedChallenge::<SC>::zero(),
+        accumulator: PackedChallenge::<SC>::zero(),
+        num_constraints: 0,
+    };
+    air.eval_all(&mut folder);
+    folder.num_constraints
+}
@@ -0,0 +1,106 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter, Result};
+
+use p3_matrix::Matrix;
+use serde::{Deserialize, Serialize};
+
+/// The height and width of a trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceDimensions {
+    pub height: usize,
+    pub width: usize,
+}
+
+impl TraceDimensions {
+    pub fn of<T: Send + Sync>(matrix: &impl Matrix<T>) -> Self {
+        Self {
+            height: matrix.height(),
+            width: matrix.width(),
+        }
+    }
+}
+
+impl Display for TraceDimensions {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}x{}", self.height, self.width)
+    }
+}
+
+/// The traces of a single chip, as they will be committed to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChipTraceReport {
+    pub chip: String,
+    pub preprocessed: Option<TraceDimensions>,
+    /// The main trace with its segments side by side.
+    pub main: Option<TraceDimensions>,
+    /// The width is in extension field elements.
+    pub permutation: Option<TraceDimensions>,
+    /// The dimensions of each of the `quotient_degree` chunks of the quotient, in base field
+    /// elements.
+    pub quotient_chunk: Option<TraceDimensions>,
+    pub quotient_degree: Option<usize>,
+    pub has_cumulative_sum: bool,
+}
+
+/// The shapes of the traces of a machine. See
+/// [`crate::trace::MachineTraceReporter::report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineTraceReport {
+    /// Chips in the order of the machine trace.
+    pub chips: Vec<ChipTraceReport>,
+}
+
+impl Display for MachineTraceReport {
+    /// Writes the report as a table with a row per chip, and a dash for traces that aren't loaded.
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let dimensions = |dimensions: Option<TraceDimensions>| {
+            dimensions.map_or("-".to_string(), |dimensions| dimensions.to_string())
+        };
+        let header = [
+            "chip",
+            "preprocessed",
+            "main",
+            "permutation",
+            "quotient chunk",
+            "quotient degree",
+            "cumulative sum",
+        ]
+        .map(ToString::to_string);
+        let rows = self
+            .chips
+            .iter()
+            .map(|chip| {
+                [
+                    chip.chip.clone(),
+                    dimensions(chip.preprocessed),
+                    dimensions(chip.main),
+                    dimensions(chip.permutation),
+                    dimensions(chip.quotient_chunk),
+                    chip.quotient_degree
+                        .map_or("-".to_string(), |degree| degree.to_string()),
+                    if chip.has_cumulative_sum { "yes" } else { "no" }.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut column_widths = vec![0; header.len()];
+        for row in core::iter::once(&header).chain(&rows) {
+            for (width, cell) in column_widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        for row in core::iter::once(&header).chain(&rows) {
+            let line = row
+                .iter()
+                .zip(&column_widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,383 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use serde::ser::{self, Serializer};
+use serde::{Deserialize, Serialize};
+
+/// The sizes in bytes of the parts of a [`crate::proof::MachineProof`], see
+/// [`crate::proof::MachineProof::size_estimate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofSize {
+    pub commitments: usize,
+    pub opening_proof: usize,
+    /// The public values of all chips.
+    pub public_values: usize,
+    /// Chips in canonical order. `None` for chips without traces.
+    pub chips: Vec<Option<ChipProofSize>>,
+}
+
+impl ProofSize {
+    /// The size of the commitments, the opening proof, the public values and the openings of all
+    /// chips. The encoded proof is larger by a few bytes for its format version and the lengths
+    /// and tags of its fields.
+    pub fn total(&self) -> usize {
+        self.commitments
+            + self.opening_proof
+            + self.public_values
+            + self
+                .chips
+                .iter()
+                .flatten()
+                .map(ChipProofSize::total)
+                .sum::<usize>()
+    }
+}
+
+impl Display for ProofSize {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "commitments: {} bytes", self.commitments)?;
+        writeln!(f, "opening proof: {} bytes", self.opening_proof)?;
+        writeln!(f, "public values: {} bytes", self.public_values)?;
+        for (i, chip) in self.chips.iter().enumerate() {
+            if let Some(chip) = chip {
+                writeln!(f, "chip {}: {} bytes", i, chip.total())?;
+            }
+        }
+        write!(f, "total: {} bytes", self.total())
+    }
+}
+
+/// The sizes in bytes of the openings of a single chip.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChipProofSize {
+    pub preprocessed: usize,
+    pub main: usize,
+    pub permutation: usize,
+    pub quotient: usize,
+    /// The degree and the cumulative sum.
+    pub other: usize,
+}
+
+impl ChipProofSize {
+    pub fn total(&self) -> usize {
+        self.preprocessed + self.main + self.permutation + self.quotient + self.other
+    }
+}
+
+/// Returns the number of bytes `value` takes in a compact binary encoding: fixed-width integers,
+/// `u64` lengths and `u32` variant indices, as in `bincode`'s default configuration.
+pub fn serialized_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    let mut counter = SizeCounter(0);
+    value
+        .serialize(&mut counter)
+        .expect("Counting bytes doesn't fail");
+    counter.0
+}
+
+const LENGTH_SIZE: usize = 8;
+const VARIANT_SIZE: usize = 4;
+
+struct SizeCounter(usize);
+
+#[derive(Debug)]
+struct SizeError;
+
+impl Display for SizeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "failed to serialize")
+    }
+}
+
+impl ser::StdError for SizeError {}
+
+impl ser::Error for SizeError {
+    fn custom<T: Display>(_msg: T) -> Self {
+        SizeError
+    }
+}
+
+impl Serializer for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, _v: bool) -> Result<(), SizeError> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<(), SizeError> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), SizeError> {
+        self.0 += 2;
+        Ok(())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<(), SizeError> {
+        self.0 += 4;
+        Ok(())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), SizeError> {
+        self.0 += 8;
+        Ok(())
+    }
+
+    fn serialize_i128(self, _v: i128) -> Result<(), SizeError> {
+        self.0 += 16;
+        Ok(())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<(), SizeError> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), SizeError> {
+        self.0 += 2;
+        Ok(())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), SizeError> {
+        self.0 += 4;
+        Ok(())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), SizeError> {
+        self.0 += 8;
+        Ok(())
+    }
+
+    fn serialize_u128(self, _v: u128) -> Result<(), SizeError> {
+        self.0 += 16;
+        Ok(())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), SizeError> {
+        self.0 += 4;
+        Ok(())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), SizeError> {
+        self.0 += 8;
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SizeError> {
+        self.0 += v.len_utf8();
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SizeError> {
+        self.0 += LENGTH_SIZE + v.len();
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SizeError> {
+        self.0 += LENGTH_SIZE + v.len();
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SizeError> {
+        self.0 += 1;
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SizeError> {
+        self.0 += 1;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SizeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), SizeError> {
+        self.0 += VARIANT_SIZE;
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SizeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), SizeError> {
+        self.0 += VARIANT_SIZE;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, SizeError> {
+        self.0 += LENGTH_SIZE;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, SizeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, SizeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SizeError> {
+        self.0 += VARIANT_SIZE;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, SizeError> {
+        self.0 += LENGTH_SIZE;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SizeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SizeError> {
+        self.0 += VARIANT_SIZE;
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SizeError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut SizeCounter {
+    type Ok = ();
+    type Error = SizeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SizeError> {
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
+use alloc::borrow::Cow;
 #[cfg(feature = "air-logger")]
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 #[cfg(feature = "air-logger")]
 use alloc::format;
+use alloc::string::ToString;
 use alloc::vec;
 use alloc::vec::Vec;
 #[cfg(feature = "air-logger")]
@@ -12,7 +15,10 @@ use p3_air::BaseAir;
 #[cfg(feature = "air-logger")]
 use p3_air_util::folders::{rap::TrackingConstraintBuilder, EntriesLog};
 use p3_air_util::{
-    debug::rap::{check_constraints, check_cumulative_sums},
+    debug::rap::{
+        check_bus_counts, check_constraint_values, check_constraints, check_cumulative_sums,
+        collect_constraint_values, try_check_constraints, ConstraintError,
+    },
     folders::rap::{
         DebugConstraintBuilder, ProverConstraintFolder, SymbolicAirBuilder,
         VerifierConstraintFolder,
@@ -28,16 +34,93 @@ use p3_air_util::{
 use p3_commit::{OpenedValuesForRound, Pcs, PolynomialSpace};
 #[cfg(feature = "air-logger")]
 use p3_field::PrimeField32;
-use p3_field::{AbstractExtensionField, AbstractField, ExtensionField, Field};
-use p3_interaction::{generate_permutation_trace, Bus, Rap, NUM_PERM_CHALLENGES};
-use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_field::{AbstractExtensionField, AbstractField, ExtensionField, Field, TwoAdicField};
+use p3_interaction::{
+    generate_permutation_trace_with_backend, generate_permutation_trace_with_options, Bus,
+    DefaultBetaSource, InteractionAir, LogUpBackend, PermutationBackendKind,
+    PermutationTraceOptions, Rap, ZeroReciprocalPolicy, NUM_PERM_CHALLENGES,
+};
+use p3_matrix::{
+    dense::{RowMajorMatrix, RowMajorMatrixView},
+    Matrix,
+};
+use p3_maybe_rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator};
 use p3_uni_stark::{Domain, PackedChallenge, StarkGenericConfig, Val};
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "air-logger")]
+use crate::error::PublicValueLayoutError;
 use crate::{
-    chip::Chip, error::VerificationError, proof::Com, proof::PcsProverData,
-    quotient::quotient_values, verify::verify_constraints,
+    chip::{chip_committed_width, chip_main_segment_widths, chip_preprocessed_trace, Chip},
+    error::{ChipConstraintError, MachineError, VerificationError},
+    proof::Com,
+    proof::PcsProverData,
+    proof::QuotientDomains,
+    public::PublicValueLayout,
+    quotient::{num_prover_constraints, quotient_values},
+    report::{ChipTraceReport, MachineTraceReport, TraceDimensions},
+    util::concat_columns,
+    verify::{evaluate_constraints, num_verifier_constraints, ConstraintReport},
 };
 
+/// Creates a span for an operation on a single chip if the `trace-spans` feature is enabled, and a
+/// disabled span otherwise, in which case the fields aren't evaluated. Chips are often processed
+/// on other threads than the caller, so the parent span can be passed explicitly, and otherwise
+/// defaults to the current span.
+macro_rules! chip_span {
+    (parent: $parent:expr, $name:literal, $($fields:tt)*) => {{
+        #[cfg(feature = "trace-spans")]
+        let span = tracing::info_span!(parent: $parent, $name, $($fields)*);
+        #[cfg(not(feature = "trace-spans"))]
+        let span = {
+            let _ = $parent;
+            tracing::Span::none()
+        };
+        span
+    }};
+    ($name:literal, $($fields:tt)*) => {
+        chip_span!(parent: &tracing::Span::current(), $name, $($fields)*)
+    };
+}
+
+/// How [`MachineTraceLoader`] pads traces whose height isn't a power of two.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaddingStrategy {
+    /// Traces are loaded as is, and must already have a power-of-two height.
+    #[default]
+    None,
+    /// Repeats the last row of the trace.
+    RepeatLastRow,
+    /// Appends rows of zeros.
+    Zeros,
+}
+
+impl PaddingStrategy {
+    /// Pads `trace` up to the next power of two. Empty traces and traces whose height already is
+    /// a power of two are returned untouched.
+    pub fn pad<F: Field>(self, mut trace: RowMajorMatrix<F>) -> RowMajorMatrix<F> {
+        let height = trace.height();
+        if height == 0 || height.is_power_of_two() {
+            return trace;
+        }
+        let width = trace.width();
+        let padded_len = height.next_power_of_two() * width;
+        match self {
+            Self::None => {}
+            Self::RepeatLastRow => {
+                let last_row = (height - 1) * width..height * width;
+                trace.values.reserve(padded_len - height * width);
+                while trace.values.len() < padded_len {
+                    trace.values.extend_from_within(last_row.clone());
+                }
+            }
+            Self::Zeros => trace.values.resize(padded_len, F::zero()),
+        }
+        trace
+    }
+}
+
 #[derive(Clone)]
 pub struct Trace<F, Domain>
 where
@@ -64,6 +147,50 @@ where
     }
 }
 
+/// A trace without its domain, which generally can't be serialized, e.g. to cache generated traces
+/// on disk. The domain is rebuilt from the degree by [`Self::to_trace`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceData<F> {
+    pub value: RowMajorMatrix<F>,
+    pub degree: usize,
+}
+
+impl<F: Field> TraceData<F> {
+    pub fn new(value: RowMajorMatrix<F>) -> Self {
+        let degree = value.height();
+        Self { value, degree }
+    }
+
+    /// Rebuilds the trace on the natural domain of its degree, as it's loaded by the prover.
+    pub fn to_trace<SC>(self, pcs: &SC::Pcs) -> Trace<F, Domain<SC>>
+    where
+        SC: StarkGenericConfig,
+    {
+        assert_eq!(
+            self.value.height(),
+            self.degree,
+            "Trace height doesn't match its degree"
+        );
+        Trace {
+            domain: pcs.natural_domain_for_degree(self.degree),
+            value: self.value,
+        }
+    }
+}
+
+impl<F, Domain> From<&Trace<F, Domain>> for TraceData<F>
+where
+    F: Field,
+    Domain: PolynomialSpace,
+{
+    fn from(trace: &Trace<F, Domain>) -> Self {
+        Self {
+            value: trace.value.clone(),
+            degree: trace.domain.size(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IndexedTrace<F, Domain>
 where
@@ -83,6 +210,20 @@ where
     pub opening_index: usize,
 }
 
+/// The quotient of a chip evaluated on its quotient domain, before it's split into chunks.
+pub struct QuotientEvaluation<SC: StarkGenericConfig> {
+    pub quotient_degree: usize,
+    pub quotient_domain: Domain<SC>,
+    /// The domains of the chunks the quotient is split into.
+    pub chunk_domains: Vec<Domain<SC>>,
+    /// The quotient over `quotient_domain`, as a single column.
+    pub values: RowMajorMatrix<SC::Challenge>,
+}
+
+/// The quotient evaluations of a machine's chips in canonical order, with `None` for chips without
+/// traces.
+pub type QuotientBatch<SC> = Vec<Option<QuotientEvaluation<SC>>>;
+
 #[derive(Clone)]
 pub struct ChipTrace<SC, C>
 where
@@ -92,13 +233,20 @@ where
     pub chip: C,
 
     pub preprocessed: Option<IndexedTrace<Val<SC>, Domain<SC>>>,
-    pub main: Option<IndexedTrace<Val<SC>, Domain<SC>>>,
+    /// The segments of the main trace, in column order. Empty if the chip has no main trace.
+    pub main: Vec<IndexedTrace<Val<SC>, Domain<SC>>>,
+    /// The extension field columns of the main trace, see [`InteractionAir::main_ext_width`]. It's
+    /// flattened to the base field when committed, after the segments of the main trace.
+    pub main_ext: Option<IndexedTrace<SC::Challenge, Domain<SC>>>,
     pub permutation: Option<IndexedTrace<SC::Challenge, Domain<SC>>>,
 
     pub cumulative_sum: Option<SC::Challenge>,
 
     pub quotient_chunks: Option<QuotientTrace<Domain<SC>>>,
     pub quotient_degree: Option<usize>,
+    /// The quotient on the quotient domain before it's flattened to the base field and split into
+    /// chunks. Only kept in debug builds, for comparing against the verifier's reconstruction.
+    pub quotient_values: Option<Trace<SC::Challenge, Domain<SC>>>,
 }
 
 impl<SC, C> ChipTrace<SC, C>
@@ -110,29 +258,68 @@ where
         Self {
             chip,
             preprocessed: None,
-            main: None,
+            main: vec![],
+            main_ext: None,
             permutation: None,
             cumulative_sum: None,
             quotient_chunks: None,
             quotient_degree: None,
+            quotient_values: None,
         }
     }
 
     // TODO: Change to be just main degree
     pub fn domain(&self) -> Option<Domain<SC>> {
-        match (&self.preprocessed, &self.main) {
-            (Some(preprocessed), Some(main)) => {
-                let preprocessed_domain = preprocessed.trace.domain;
-                let main_domain = main.trace.domain;
-                if main_domain.size() > preprocessed_domain.size() {
-                    Some(main_domain)
-                } else {
-                    Some(preprocessed_domain)
-                }
+        self.preprocessed
+            .iter()
+            .chain(&self.main)
+            .map(|trace| trace.trace.domain)
+            .max_by_key(|domain| domain.size())
+    }
+
+    /// Returns the main trace with its segments side by side, or `None` if the chip has no main
+    /// trace. Only a trace of several segments is copied.
+    pub fn main_trace(&self) -> Option<Cow<'_, RowMajorMatrix<Val<SC>>>> {
+        match self.main.as_slice() {
+            [] => None,
+            [main] => Some(Cow::Borrowed(&main.trace.value)),
+            segments => {
+                let segments = segments
+                    .iter()
+                    .map(|segment| segment.trace.value.as_view())
+                    .collect_vec();
+                Some(Cow::Owned(concat_columns(&segments)))
             }
-            (Some(preprocessed), None) => Some(preprocessed.trace.domain),
-            (None, Some(main)) => Some(main.trace.domain),
-            (None, None) => None,
+        }
+    }
+
+    /// Summarizes the dimensions of the chip's traces. See [`MachineTraceReporter::report`].
+    pub fn report(&self) -> ChipTraceReport {
+        ChipTraceReport {
+            chip: self.chip.to_string(),
+            preprocessed: self
+                .preprocessed
+                .as_ref()
+                .map(|preprocessed| TraceDimensions::of(&preprocessed.trace.value)),
+            main: self.main.first().map(|first| TraceDimensions {
+                height: first.trace.value.height(),
+                width: self
+                    .main
+                    .iter()
+                    .map(|segment| segment.trace.value.width())
+                    .sum(),
+            }),
+            permutation: self
+                .permutation
+                .as_ref()
+                .map(|permutation| TraceDimensions::of(&permutation.trace.value)),
+            quotient_chunk: self
+                .quotient_chunks
+                .as_ref()
+                .and_then(|quotient| quotient.traces.first())
+                .map(|chunk| TraceDimensions::of(&chunk.value)),
+            quotient_degree: self.quotient_degree,
+            has_cumulative_sum: self.cumulative_sum.is_some(),
         }
     }
 }
@@ -144,6 +331,8 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
+    /// Builds a trace of each chip in the order of `chips`. Opening indices follow this order, so
+    /// it must be deterministic for proofs to be reproducible, see [`crate::chip::canonicalize`].
     fn new(chips: &[C]) -> Self;
 }
 
@@ -160,36 +349,171 @@ where
     }
 }
 
+pub trait MachineTraceLookup<SC, C>
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    /// Returns the trace of the chip whose `Display` output is `name`, or `None` if there's no
+    /// such chip. If several chips share the name, the first one is returned.
+    fn chip_trace(&self, name: &str) -> Option<&ChipTrace<SC, C>>;
+
+    /// Mutable version of [`Self::chip_trace`].
+    fn chip_trace_mut(&mut self, name: &str) -> Option<&mut ChipTrace<SC, C>>;
+}
+
+impl<SC, C> MachineTraceLookup<SC, C> for MachineTrace<SC, C>
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    fn chip_trace(&self, name: &str) -> Option<&ChipTrace<SC, C>> {
+        self.iter()
+            .find(|chip_trace| chip_trace.chip.to_string() == name)
+    }
+
+    fn chip_trace_mut(&mut self, name: &str) -> Option<&mut ChipTrace<SC, C>> {
+        self.iter_mut()
+            .find(|chip_trace| chip_trace.chip.to_string() == name)
+    }
+}
+
+pub trait MachineTraceReporter {
+    /// Summarizes the dimensions of the traces loaded so far, e.g. after
+    /// [`MachineTraceLoader::generate_quotient`] to see what will be committed to.
+    fn report(&self) -> MachineTraceReport;
+}
+
+impl<SC, C> MachineTraceReporter for MachineTrace<SC, C>
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    fn report(&self) -> MachineTraceReport {
+        MachineTraceReport {
+            chips: self.iter().map(ChipTrace::report).collect(),
+        }
+    }
+}
+
+pub trait MachineTraceCumulativeSums<SC>
+where
+    SC: StarkGenericConfig,
+{
+    /// The cumulative sums of the chips in trace order, once their permutation traces are loaded.
+    /// Chips without interactions have none.
+    fn cumulative_sums(&self) -> Vec<Option<SC::Challenge>>;
+
+    /// The sum of the cumulative sums of all chips, which is zero if every bus balances. This
+    /// checks the machine before committing, without running [`MachineTraceChecker`].
+    fn total_cumulative_sum(&self) -> SC::Challenge {
+        self.cumulative_sums().into_iter().flatten().sum()
+    }
+}
+
+impl<SC, C> MachineTraceCumulativeSums<SC> for MachineTrace<SC, C>
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    fn cumulative_sums(&self) -> Vec<Option<SC::Challenge>> {
+        self.iter()
+            .map(|chip_trace| chip_trace.cumulative_sum)
+            .collect()
+    }
+}
+
 pub trait MachineTraceLoader<'a, SC>
 where
     SC: StarkGenericConfig,
 {
-    fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs);
+    /// Generates the preprocessed traces of the chips with [`chip_preprocessed_trace`] and loads
+    /// them.
+    fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs) -> Result<(), MachineError>;
 
     fn load_preprocessed(
         &mut self,
         pcs: &'a SC::Pcs,
         traces: &'a [Option<RowMajorMatrix<Val<SC>>>],
-    );
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError>;
+
+    /// Loads the main traces of the chips, each given as the segments of
+    /// [`Chip::main_segment_widths`]. A chip without a main trace has no segments, or only `None`
+    /// segments. The segments are committed to as separate matrices.
+    fn load_main(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Vec<Option<RowMajorMatrix<Val<SC>>>>>,
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError>;
 
-    fn load_main(&mut self, pcs: &'a SC::Pcs, traces: Vec<Option<RowMajorMatrix<Val<SC>>>>);
+    /// Like [`Self::load_main`], for chips whose main trace is a single segment.
+    fn load_main_single_segment(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<Val<SC>>>>,
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError> {
+        let traces = traces.into_iter().map(|trace| vec![trace]).collect();
+        self.load_main(pcs, traces, padding)
+    }
+
+    /// Loads the extension field columns of the main traces, see
+    /// [`InteractionAir::main_ext_width`]. Each trace must have the height of the chip's main
+    /// trace. They're committed to in the main commitment, each after the segments of its chip.
+    fn load_main_ext(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError>;
+
+    fn load_permutation(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+    ) -> Result<(), MachineError>;
 
     fn generate_permutation(
         &mut self,
         pcs: &'a SC::Pcs,
+        num_buses: &[usize],
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
-    );
+        public_values: &[Val<SC>],
+    ) -> Result<(), MachineError>;
 
+    /// Evaluates and loads the quotients of all chips. Equivalent to [`Self::load_quotients`] on
+    /// the batch returned by [`Self::evaluate_quotients`].
     fn generate_quotient(
         &mut self,
         pcs: &'a SC::Pcs,
         preprocessed_data: &'a Option<PcsProverData<SC>>,
         main_data: &'a Option<PcsProverData<SC>>,
         permutation_data: &'a Option<PcsProverData<SC>>,
+        quotient_domains: &'a [Option<QuotientDomains<SC>>],
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         alpha: SC::Challenge,
         public_values: &[Val<SC>],
     );
+
+    /// Evaluates the quotient of every chip on its quotient domain, without splitting it into
+    /// chunks. A backend can evaluate the batch itself, e.g. in a single accelerator kernel, and
+    /// then load it with [`Self::load_quotients`].
+    fn evaluate_quotients(
+        &self,
+        pcs: &'a SC::Pcs,
+        preprocessed_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a Option<PcsProverData<SC>>,
+        permutation_data: &'a Option<PcsProverData<SC>>,
+        quotient_domains: &'a [Option<QuotientDomains<SC>>],
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        alpha: SC::Challenge,
+        public_values: &[Val<SC>],
+    ) -> QuotientBatch<SC>;
+
+    /// Splits the evaluated quotients into the chunks that are committed to.
+    fn load_quotients(&mut self, batch: QuotientBatch<SC>);
 }
 
 impl<'a, SC, C> MachineTraceLoader<'a, SC> for MachineTrace<SC, C>
@@ -197,55 +521,272 @@ where
     SC: StarkGenericConfig,
     C: Chip
         + for<'b> Rap<ProverConstraintFolder<'b, SC>>
+        + for<'b> Rap<VerifierConstraintFolder<'b, SC>>
         + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    Val<SC>: TwoAdicField,
 {
-    fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs) {
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn generate_preprocessed(&mut self, pcs: &'a SC::Pcs) -> Result<(), MachineError> {
+        let parent = tracing::Span::current();
         let traces = self
-            .iter()
-            .map(|trace| trace.chip.preprocessed_trace())
-            .collect_vec();
-        let traces = load_traces::<SC, _>(pcs, traces);
+            .par_iter()
+            .map(|trace| {
+                let _span = chip_span!(
+                    parent: &parent,
+                    "generate preprocessed trace",
+                    chip = %trace.chip
+                )
+                .entered();
+                chip_preprocessed_trace(&trace.chip)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .enumerate()
+            .map(|(chip, trace)| {
+                trace.map_err(|error| MachineError::TraceGeneration { chip, error })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let traces = load_traces::<SC, _>(pcs, traces, PaddingStrategy::None)?;
         for (chip_trace, preprocessed) in self.iter_mut().zip_eq(traces) {
             chip_trace.preprocessed = preprocessed;
         }
+        Ok(())
     }
 
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn load_preprocessed(
         &mut self,
         pcs: &'a SC::Pcs,
         traces: &'a [Option<RowMajorMatrix<Val<SC>>>],
-    ) {
-        let traces = load_traces::<SC, _>(pcs, traces.to_vec());
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError> {
+        let traces = load_traces::<SC, _>(pcs, traces.to_vec(), padding)?;
         for (chip_trace, preprocessed) in self.iter_mut().zip_eq(traces) {
             chip_trace.preprocessed = preprocessed;
         }
+        Ok(())
     }
 
-    fn load_main(&mut self, pcs: &'a SC::Pcs, traces: Vec<Option<RowMajorMatrix<Val<SC>>>>) {
-        let traces = load_traces::<SC, _>(pcs, traces);
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn load_main(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Vec<Option<RowMajorMatrix<Val<SC>>>>>,
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError> {
+        // Drop the scratch columns that aren't committed to, which follow the last segment
+        let parent = tracing::Span::current();
+        let traces = self
+            .iter()
+            .zip_eq(traces)
+            .enumerate()
+            .collect_vec()
+            .into_par_iter()
+            .map(|(chip, (chip_trace, segments))| {
+                let _span = chip_span!(
+                    parent: &parent,
+                    "load main trace",
+                    chip = %chip_trace.chip,
+                    segments = segments.len(),
+                    height = segments.iter().flatten().next().map_or(0, |segment| segment.height()),
+                    width = segments.iter().flatten().map(|segment| segment.width()).sum::<usize>()
+                )
+                .entered();
+                if segments.iter().all(Option::is_none) {
+                    return Ok(vec![]);
+                }
+                let widths = chip_main_segment_widths::<Val<SC>, _>(&chip_trace.chip);
+                if segments.len() != widths.len() {
+                    return Err(MachineError::MainSegmentCount {
+                        chip,
+                        expected: widths.len(),
+                        actual: segments.len(),
+                    });
+                }
+                let last = widths.len() - 1;
+                let segments = segments
+                    .into_iter()
+                    .zip_eq(widths)
+                    .enumerate()
+                    .map(|(i, (segment, width))| {
+                        let segment =
+                            segment.ok_or(MachineError::MissingMainSegment { chip, segment: i })?;
+                        if i == last {
+                            if width > segment.width() {
+                                return Err(MachineError::CommittedWidthExceeded {
+                                    chip,
+                                    committed_width: width,
+                                    width: segment.width(),
+                                });
+                            }
+                        } else if width != segment.width() {
+                            return Err(MachineError::MainSegmentWidth {
+                                chip,
+                                segment: i,
+                                expected: width,
+                                actual: segment.width(),
+                            });
+                        }
+                        if width == segment.width() {
+                            Ok(segment)
+                        } else {
+                            let values = segment.rows().flat_map(|row| row.take(width)).collect();
+                            Ok(RowMajorMatrix::new(values, width))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !segments.iter().map(|segment| segment.height()).all_equal() {
+                    return Err(MachineError::MainSegmentHeightMismatch { chip });
+                }
+                Ok(segments.into_iter().map(Some).collect_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let traces = load_trace_segments::<SC, _>(pcs, traces, padding)?;
         for (chip_trace, main) in self.iter_mut().zip_eq(traces) {
+            if let (Some(preprocessed), Some(main)) = (&chip_trace.preprocessed, main.first()) {
+                assert_eq!(
+                    preprocessed.trace.value.height(),
+                    main.trace.value.height(),
+                    "Preprocessed and main traces of chip {} must have the same height",
+                    chip_trace.chip
+                );
+            }
             chip_trace.main = main;
         }
+        assign_main_opening_indices(self);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn load_main_ext(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+        padding: PaddingStrategy,
+    ) -> Result<(), MachineError> {
+        for (i, (chip_trace, trace)) in self.iter().zip_eq(traces.iter()).enumerate() {
+            let expected = InteractionAir::<Val<SC>>::main_ext_width(&chip_trace.chip);
+            let actual = trace.as_ref().map_or(0, |trace| trace.width());
+            if actual != expected {
+                return Err(MachineError::MainExtWidth {
+                    chip: i,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        let traces = load_traces::<SC, _>(pcs, traces, padding)?;
+        for (chip_trace, main_ext) in self.iter_mut().zip_eq(traces) {
+            if let Some(main_ext) = &main_ext {
+                assert_eq!(
+                    chip_trace
+                        .main
+                        .first()
+                        .map(|main| main.trace.value.height()),
+                    Some(main_ext.trace.value.height()),
+                    "Main and main extension traces of chip {} must have the same height",
+                    chip_trace.chip
+                );
+            }
+            chip_trace.main_ext = main_ext;
+        }
+        assign_main_opening_indices(self);
+        Ok(())
     }
 
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn generate_permutation(
         &mut self,
         pcs: &'a SC::Pcs,
+        num_buses: &[usize],
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
-    ) {
-        let traces = self
-            .iter()
-            .map(|trace| {
+        public_values: &[Val<SC>],
+    ) -> Result<(), MachineError> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)
+                .expect("Public value ranges should be in bounds");
+        let parent = tracing::Span::current();
+        let traces = (0..self.len())
+            .into_par_iter()
+            .map(|i| {
+                let trace = &self[i];
+                let _span = chip_span!(
+                    parent: &parent,
+                    "generate permutation trace",
+                    chip = %trace.chip,
+                    height = trace.main.first().map_or(0, |main| main.trace.value.height()),
+                    interactions = trace.chip.all_interactions().len()
+                )
+                .entered();
                 let preprocessed = trace
                     .preprocessed
                     .as_ref()
                     .map(|mt| mt.trace.value.as_view());
-                let main = trace.main.as_ref().map(|mt| mt.trace.value.as_view());
+                let main = trace.main_trace();
+                let main = main.as_deref().map(|main| main.as_view());
+                let public_values = layout.public_values_for(i);
                 let interactions = trace.chip.all_interactions();
+                let max_constraint_degree =
+                    InteractionAir::<Val<SC>>::max_permutation_constraint_degree(&trace.chip);
 
-                generate_permutation_trace(&preprocessed, &main, &interactions, perm_challenges)
+                let zero_policy = if cfg!(debug_assertions) {
+                    ZeroReciprocalPolicy::PanicOnZero
+                } else {
+                    ZeroReciprocalPolicy::AllowZero
+                };
+
+                // Chips without a count cached at setup have theirs computed from the interactions
+                let options = PermutationTraceOptions {
+                    zero_policy,
+                    max_constraint_degree,
+                    num_buses: num_buses.get(i).copied(),
+                    ..Default::default()
+                };
+                match InteractionAir::<Val<SC>>::permutation_backend(&trace.chip) {
+                    PermutationBackendKind::Reciprocal => generate_permutation_trace_with_options(
+                        &preprocessed,
+                        &main,
+                        public_values,
+                        &interactions,
+                        perm_challenges,
+                        options,
+                    ),
+                    PermutationBackendKind::LogUp => generate_permutation_trace_with_backend(
+                        &preprocessed,
+                        &main,
+                        public_values,
+                        &interactions,
+                        perm_challenges,
+                        options,
+                        &LogUpBackend,
+                        &DefaultBetaSource,
+                    ),
+                }
             })
-            .collect_vec();
+            .collect::<Vec<_>>();
+        self.load_permutation(pcs, traces)
+    }
+
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn load_permutation(
+        &mut self,
+        pcs: &'a SC::Pcs,
+        traces: Vec<Option<RowMajorMatrix<SC::Challenge>>>,
+    ) -> Result<(), MachineError> {
+        for (i, (chip_trace, trace)) in self.iter().zip_eq(traces.iter()).enumerate() {
+            let expected =
+                InteractionAir::<Val<SC>>::permutation_width(&chip_trace.chip).unwrap_or(0);
+            let actual = trace.as_ref().map_or(0, |trace| trace.width());
+            if actual != expected {
+                return Err(MachineError::PermutationWidth {
+                    chip: i,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
         let cumulative_sums = traces
             .iter()
             .map(|mt| {
@@ -256,7 +797,7 @@ where
                 })
             })
             .collect_vec();
-        let traces = load_traces::<SC, _>(pcs, traces);
+        let traces = load_traces::<SC, _>(pcs, traces, PaddingStrategy::None)?;
         for ((chip_trace, permutation), cumulative_sum) in self
             .iter_mut()
             .zip_eq(traces.into_iter())
@@ -265,6 +806,7 @@ where
             chip_trace.permutation = permutation;
             chip_trace.cumulative_sum = cumulative_sum;
         }
+        Ok(())
     }
 
     fn generate_quotient(
@@ -273,93 +815,220 @@ where
         preprocessed_data: &'a Option<PcsProverData<SC>>,
         main_data: &'a Option<PcsProverData<SC>>,
         permutation_data: &'a Option<PcsProverData<SC>>,
+        quotient_domains: &'a [Option<QuotientDomains<SC>>],
         perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         alpha: SC::Challenge,
         public_values: &[Val<SC>],
     ) {
+        let batch = self.evaluate_quotients(
+            pcs,
+            preprocessed_data,
+            main_data,
+            permutation_data,
+            quotient_domains,
+            perm_challenges,
+            alpha,
+            public_values,
+        );
+        self.load_quotients(batch);
+    }
+
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn evaluate_quotients(
+        &self,
+        pcs: &'a SC::Pcs,
+        preprocessed_data: &'a Option<PcsProverData<SC>>,
+        main_data: &'a Option<PcsProverData<SC>>,
+        permutation_data: &'a Option<PcsProverData<SC>>,
+        quotient_domains: &'a [Option<QuotientDomains<SC>>],
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        alpha: SC::Challenge,
+        public_values: &[Val<SC>],
+    ) -> QuotientBatch<SC> {
         let perm_challenges = perm_challenges.map(PackedChallenge::<SC>::from_f);
         let alpha = PackedChallenge::<SC>::from_f(alpha);
 
-        let mut count = 0;
-        for chip_trace in self.iter_mut() {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)
+                .expect("Public value ranges should be in bounds");
+        let mut batch = Vec::with_capacity(self.len());
+        for (i, chip_trace) in self.iter().enumerate() {
+            let public_values = layout.public_values_for(i);
             let quotient_degree =
                 get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, public_values.len());
             let trace_domain = chip_trace.domain();
+            let _span = chip_span!(
+                "evaluate quotient",
+                chip = %chip_trace.chip,
+                height = trace_domain.map_or(0, |domain| domain.size()),
+                quotient_degree
+            )
+            .entered();
+
+            let evaluation = if let Some(trace_domain) = trace_domain {
+                // Use the domains cached at setup if they match this trace
+                let cached_domains =
+                    quotient_domains
+                        .get(i)
+                        .and_then(Option::as_ref)
+                        .filter(|domains| {
+                            domains.quotient_degree == quotient_degree
+                                && domains.trace_domain.size() == trace_domain.size()
+                        });
+                let quotient_domain = cached_domains.map_or_else(
+                    || trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree),
+                    |domains| domains.quotient_domain,
+                );
 
-            if let Some(trace_domain) = trace_domain {
-                let quotient_domain =
-                    trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree);
-
+                // Lazy views of the committed low-degree extensions, so that no chip's traces are
+                // copied over the whole quotient domain
                 let preprocessed_trace_on_quotient_domains =
-                    if let Some(preprocessed) = &chip_trace.preprocessed {
+                    chip_trace.preprocessed.as_ref().map(|preprocessed| {
                         pcs.get_evaluations_on_domain(
                             preprocessed_data.as_ref().unwrap(),
                             preprocessed.opening_index,
                             quotient_domain,
                         )
-                        .to_row_major_matrix()
-                    } else {
-                        RowMajorMatrix::new(vec![], 0)
-                    };
-                let main_trace_on_quotient_domains = if let Some(main) = &chip_trace.main {
-                    pcs.get_evaluations_on_domain(
-                        main_data.as_ref().unwrap(),
-                        main.opening_index,
-                        quotient_domain,
-                    )
-                    .to_row_major_matrix()
-                } else {
-                    RowMajorMatrix::new(vec![], 0)
-                };
+                    });
+                let main_trace_on_quotient_domains = chip_trace
+                    .main
+                    .iter()
+                    .map(|main| {
+                        pcs.get_evaluations_on_domain(
+                            main_data.as_ref().unwrap(),
+                            main.opening_index,
+                            quotient_domain,
+                        )
+                    })
+                    .collect_vec();
+                let main_ext_trace_on_quotient_domains =
+                    chip_trace.main_ext.as_ref().map(|main_ext| {
+                        pcs.get_evaluations_on_domain(
+                            main_data.as_ref().unwrap(),
+                            main_ext.opening_index,
+                            quotient_domain,
+                        )
+                    });
                 let perm_trace_on_quotient_domains =
-                    if let Some(permutation) = &chip_trace.permutation {
+                    chip_trace.permutation.as_ref().map(|permutation| {
                         pcs.get_evaluations_on_domain(
                             permutation_data.as_ref().unwrap(),
                             permutation.opening_index,
                             quotient_domain,
                         )
-                        .to_row_major_matrix()
-                    } else {
-                        RowMajorMatrix::new(vec![], 0)
-                    };
+                    });
 
                 let cumulative_sum = chip_trace
                     .cumulative_sum
                     .map(PackedChallenge::<SC>::from_f)
                     .unwrap_or_default();
 
-                let quotient_values = quotient_values::<SC, _, _>(
+                // The verifier's accumulator silently differs if it folds a different number of
+                // constraints
+                if cfg!(debug_assertions) {
+                    let (preprocessed_width, main_width, perm_width) = (
+                        preprocessed_trace_on_quotient_domains
+                            .as_ref()
+                            .map_or(0, |trace| trace.width()),
+                        main_trace_on_quotient_domains
+                            .iter()
+                            .map(|trace| trace.width())
+                            .sum(),
+                        perm_trace_on_quotient_domains
+                            .as_ref()
+                            .map_or(0, |trace| trace.width())
+                            / <SC::Challenge as AbstractExtensionField<Val<SC>>>::D,
+                    );
+                    let prover_constraints = num_prover_constraints::<SC, _>(
+                        &chip_trace.chip,
+                        preprocessed_width,
+                        main_width,
+                        perm_width,
+                        public_values,
+                    );
+                    let verifier_constraints = num_verifier_constraints::<SC, _>(
+                        &chip_trace.chip,
+                        preprocessed_width,
+                        main_width,
+                        perm_width,
+                        public_values,
+                    );
+                    assert_eq!(
+                        prover_constraints, verifier_constraints,
+                        "Chip {} folds {} constraints on the prover but {} on the verifier",
+                        chip_trace.chip, prover_constraints, verifier_constraints
+                    );
+                }
+
+                let quotient_values = quotient_values::<SC, _, _, _, _, _>(
                     &chip_trace.chip,
                     trace_domain,
                     quotient_domain,
                     preprocessed_trace_on_quotient_domains,
                     main_trace_on_quotient_domains,
+                    main_ext_trace_on_quotient_domains,
                     perm_trace_on_quotient_domains,
                     perm_challenges,
                     alpha,
                     cumulative_sum,
                     public_values,
                 );
-                let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
-
-                let chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
-                let chunk_domains = quotient_domain.split_domains(quotient_degree);
-                let traces = chunk_domains
-                    .into_iter()
-                    .zip_eq(chunks.into_iter())
-                    .map(|(domain, chunk)| Trace {
-                        value: chunk,
-                        domain,
-                    })
-                    .collect();
+                let chunk_domains = cached_domains.map_or_else(
+                    || quotient_domain.split_domains(quotient_degree),
+                    |domains| domains.chunk_domains.clone(),
+                );
+                Some(QuotientEvaluation {
+                    quotient_degree,
+                    quotient_domain,
+                    chunk_domains,
+                    values: RowMajorMatrix::new_col(quotient_values),
+                })
+            } else {
+                None
+            };
+            batch.push(evaluation);
+        }
+        batch
+    }
 
-                chip_trace.quotient_degree = Some(quotient_degree);
-                chip_trace.quotient_chunks = Some(QuotientTrace {
-                    traces,
-                    opening_index: count,
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
+    fn load_quotients(&mut self, batch: QuotientBatch<SC>) {
+        let mut count = 0;
+        for (chip_trace, evaluation) in self.iter_mut().zip_eq(batch) {
+            let Some(QuotientEvaluation {
+                quotient_degree,
+                quotient_domain,
+                chunk_domains,
+                values,
+            }) = evaluation
+            else {
+                continue;
+            };
+
+            let quotient_flat = values.flatten_to_base();
+            if cfg!(debug_assertions) {
+                chip_trace.quotient_values = Some(Trace {
+                    value: values,
+                    domain: quotient_domain,
                 });
-                count += 1;
             }
+
+            let chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
+            let traces = chunk_domains
+                .into_iter()
+                .zip_eq(chunks.into_iter())
+                .map(|(domain, chunk)| Trace {
+                    value: chunk,
+                    domain,
+                })
+                .collect();
+
+            chip_trace.quotient_degree = Some(quotient_degree);
+            chip_trace.quotient_chunks = Some(QuotientTrace {
+                traces,
+                opening_index: count,
+            });
+            count += 1;
         }
     }
 }
@@ -383,6 +1052,7 @@ where
     SC: StarkGenericConfig,
     C: Chip,
 {
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn commit_preprocessed(
         &self,
         pcs: &'a SC::Pcs,
@@ -399,14 +1069,23 @@ where
         commit_traces::<SC>(pcs, traces)
     }
 
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn commit_main(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
             .iter()
-            .flat_map(|trace| trace.main.as_ref().map(|main| main.trace.clone()))
+            .flat_map(|trace| {
+                trace.main.iter().map(|main| main.trace.clone()).chain(
+                    trace
+                        .main_ext
+                        .as_ref()
+                        .map(|main_ext| main_ext.trace.flatten_to_base()),
+                )
+            })
             .collect_vec();
         commit_traces::<SC>(pcs, traces)
     }
 
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn commit_permutation(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
             .iter()
@@ -420,6 +1099,7 @@ where
         commit_traces::<SC>(pcs, traces)
     }
 
+    #[cfg_attr(feature = "trace-spans", tracing::instrument(skip_all, fields(chips = self.len())))]
     fn commit_quotient(&self, pcs: &'a SC::Pcs) -> (Option<Com<SC>>, Option<PcsProverData<SC>>) {
         let traces = self
             .iter()
@@ -439,9 +1119,20 @@ pub trait MachineTraceChecker<SC>
 where
     SC: StarkGenericConfig,
 {
-    fn check_constraints<B>(&self, perm_challenges: [SC::Challenge; 2], public_values: &[Val<SC>])
-    where
+    fn check_constraints<B>(
+        &self,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Val<SC>],
+    ) where
         B: Bus;
+
+    /// Checks the constraints of every chip like [`Self::check_constraints`], but returns the
+    /// first failing constraint, in chip order, instead of panicking. The buses aren't checked.
+    fn try_check_constraints(
+        &self,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Val<SC>],
+    ) -> Result<(), ChipConstraintError>;
 }
 
 impl<SC, C> MachineTraceChecker<SC> for MachineTrace<SC, C>
@@ -449,32 +1140,71 @@ where
     SC: StarkGenericConfig,
     C: Chip + for<'b> Rap<DebugConstraintBuilder<'b, Val<SC>, SC::Challenge>>,
 {
-    fn check_constraints<B>(&self, perm_challenges: [SC::Challenge; 2], public_values: &[Val<SC>])
-    where
+    fn check_constraints<B>(
+        &self,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Val<SC>],
+    ) where
         B: Bus,
     {
-        for chip_trace in self.iter() {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)
+                .expect("Public value ranges should be in bounds");
+        for (i, chip_trace) in self.iter().enumerate() {
             let preprocessed = chip_trace
                 .preprocessed
                 .as_ref()
                 .map(|preprocessed| preprocessed.trace.value.as_view());
-            let main = chip_trace
-                .main
+            let main = chip_trace.main_trace();
+            let main = main.as_deref().map(|main| main.as_view());
+            let main_ext = chip_trace
+                .main_ext
                 .as_ref()
-                .map(|main| main.trace.value.as_view());
+                .map(|main_ext| main_ext.trace.value.as_view());
             let permutation = chip_trace
                 .permutation
                 .as_ref()
                 .map(|permutation| permutation.trace.value.as_view());
-            check_constraints(
-                &chip_trace.chip,
-                &preprocessed,
-                &main,
-                &permutation,
-                perm_challenges,
-                chip_trace.cumulative_sum,
-                public_values,
-            );
+            let public_values = layout.public_values_for(i);
+            if chip_trace.chip.is_real_column().is_some() {
+                // The failing row is needed to tell padding rows apart, so the constraint values
+                // are collected once and checked as a whole
+                let values = collect_constraint_values(
+                    &chip_trace.chip,
+                    &preprocessed,
+                    &main,
+                    &main_ext,
+                    &permutation,
+                    perm_challenges,
+                    chip_trace.cumulative_sum,
+                    public_values,
+                );
+                let result = check_constraint_values(
+                    &chip_trace.chip,
+                    &preprocessed,
+                    &main,
+                    &main_ext,
+                    &permutation,
+                    perm_challenges,
+                    chip_trace.cumulative_sum,
+                    public_values,
+                    &values,
+                );
+                if let Err(error) = result {
+                    panic!("{}", chip_constraint_error(&chip_trace.chip, &main, error));
+                }
+            } else {
+                check_constraints(
+                    &chip_trace.chip,
+                    &preprocessed,
+                    &main,
+                    &main_ext,
+                    &permutation,
+                    perm_challenges,
+                    chip_trace.cumulative_sum,
+                    public_values,
+                );
+            }
         }
         let preprocessed_traces = self
             .iter()
@@ -487,12 +1217,11 @@ where
             .collect_vec();
         let main_traces = self
             .iter()
-            .map(|chip_trace| {
-                chip_trace
-                    .main
-                    .as_ref()
-                    .map(|main| main.trace.value.as_view())
-            })
+            .map(|chip_trace| chip_trace.main_trace())
+            .collect_vec();
+        let main_traces = main_traces
+            .iter()
+            .map(|main| main.as_deref().map(|main| main.as_view()))
             .collect_vec();
         let permutation_traces = self
             .iter()
@@ -509,6 +1238,11 @@ where
             .map(|chip_trace| chip_trace.chip.clone())
             .collect_vec();
 
+        check_bus_counts::<_, _, B>(
+            &airs,
+            preprocessed_traces.as_slice(),
+            main_traces.as_slice(),
+        );
         check_cumulative_sums::<_, _, _, B>(
             &airs,
             preprocessed_traces.as_slice(),
@@ -516,6 +1250,63 @@ where
             permutation_traces.as_slice(),
         );
     }
+
+    fn try_check_constraints(
+        &self,
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
+        public_values: &[Val<SC>],
+    ) -> Result<(), ChipConstraintError> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)
+                .expect("Public value ranges should be in bounds");
+        for (i, chip_trace) in self.iter().enumerate() {
+            let preprocessed = chip_trace
+                .preprocessed
+                .as_ref()
+                .map(|preprocessed| preprocessed.trace.value.as_view());
+            let main = chip_trace.main_trace();
+            let main = main.as_deref().map(|main| main.as_view());
+            let main_ext = chip_trace
+                .main_ext
+                .as_ref()
+                .map(|main_ext| main_ext.trace.value.as_view());
+            let permutation = chip_trace
+                .permutation
+                .as_ref()
+                .map(|permutation| permutation.trace.value.as_view());
+            let public_values = layout.public_values_for(i);
+            try_check_constraints(
+                &chip_trace.chip,
+                &preprocessed,
+                &main,
+                &main_ext,
+                &permutation,
+                perm_challenges,
+                chip_trace.cumulative_sum,
+                public_values,
+            )
+            .map_err(|error| chip_constraint_error(&chip_trace.chip, &main, error))?;
+        }
+        Ok(())
+    }
+}
+
+/// Attributes `error` to `chip`, noting whether it's on a padding row of `main`, see
+/// [`Chip::is_real_column`].
+fn chip_constraint_error<F: Field, C: Chip>(
+    chip: &C,
+    main: &Option<RowMajorMatrixView<F>>,
+    error: ConstraintError,
+) -> ChipConstraintError {
+    let padding = match (chip.is_real_column(), main) {
+        (Some(is_real), Some(main)) => main.get(error.row, is_real).is_zero(),
+        _ => false,
+    };
+    ChipConstraintError {
+        chip: chip.to_string(),
+        error,
+        padding,
+    }
 }
 
 #[cfg(feature = "air-logger")]
@@ -527,9 +1318,9 @@ where
     // TODO: Move to separate trait
     fn track_constraints(
         &self,
-        perm_challenges: [SC::Challenge; 2],
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         public_values: &[Val<SC>],
-    ) -> Vec<EntriesLog<TraceEntry>>;
+    ) -> Result<Vec<EntriesLog<TraceEntry>>, PublicValueLayoutError>;
 
     fn track_interactions(&self) -> Vec<EntriesLog<TraceEntry>>;
 
@@ -551,19 +1342,23 @@ where
 {
     fn track_constraints(
         &self,
-        perm_challenges: [SC::Challenge; 2],
+        perm_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         public_values: &[Val<SC>],
-    ) -> Vec<EntriesLog<TraceEntry>> {
+    ) -> Result<Vec<EntriesLog<TraceEntry>>, PublicValueLayoutError> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)?;
         let mut chip_indices = Vec::new();
-        for chip_trace in self.iter() {
+        for (i, chip_trace) in self.iter().enumerate() {
             let preprocessed = chip_trace
                 .preprocessed
                 .as_ref()
                 .map(|preprocessed| preprocessed.trace.value.as_view());
-            let main = chip_trace
-                .main
+            let main = chip_trace.main_trace();
+            let main = main.as_deref().map(|main| main.as_view());
+            let main_ext = chip_trace
+                .main_ext
                 .as_ref()
-                .map(|main| main.trace.value.as_view());
+                .map(|main_ext| main_ext.trace.value.as_view());
             let permutation = chip_trace
                 .permutation
                 .as_ref()
@@ -572,14 +1367,15 @@ where
                 &chip_trace.chip,
                 &preprocessed,
                 &main,
+                &main_ext,
                 &permutation,
                 perm_challenges,
                 chip_trace.cumulative_sum,
-                public_values,
+                layout.public_values_for(i),
             );
             chip_indices.push(indices);
         }
-        chip_indices
+        Ok(chip_indices)
     }
 
     fn track_interactions(&self) -> Vec<EntriesLog<TraceEntry>> {
@@ -594,12 +1390,11 @@ where
             .collect_vec();
         let main_traces = self
             .iter()
-            .map(|chip_trace| {
-                chip_trace
-                    .main
-                    .as_ref()
-                    .map(|main| main.trace.value.as_view())
-            })
+            .map(|chip_trace| chip_trace.main_trace())
+            .collect_vec();
+        let main_traces = main_traces
+            .iter()
+            .map(|main| main.as_deref().map(|main| main.as_view()))
             .collect_vec();
 
         let airs = self
@@ -624,7 +1419,7 @@ where
 
         // TODO: Account for public values
         let mut entries = vec![EntriesLog::default(); self.len()];
-        self.track_constraints(perm_challenges, &[])
+        self.track_constraints(perm_challenges, &[])?
             .iter()
             .zip(&mut entries)
             .for_each(|(entry, set)| set.extend(entry));
@@ -643,10 +1438,8 @@ where
                 .preprocessed
                 .as_ref()
                 .map(|preprocessed| preprocessed.trace.value.as_view());
-            let main_trace = chip_trace
-                .main
-                .as_ref()
-                .map(|main| main.trace.value.as_view());
+            let main_trace = chip_trace.main_trace();
+            let main_trace = main_trace.as_deref().map(|main| main.as_view());
 
             chip.write_traces_to_worksheet::<_, SC::Challenge>(
                 worksheet,
@@ -689,12 +1482,16 @@ where
         &self,
         openings: Vec<OpenedValues<SC::Challenge>>,
     ) -> Vec<Option<InteractionAirProof<SC::Challenge>>>;
+
+    /// Returns the slice of `public_values` each chip is evaluated against, to be carried in the
+    /// proof.
+    fn generate_public_values(&self, public_values: &[Val<SC>]) -> Vec<Vec<Val<SC>>>;
 }
 
 impl<'a, SC, C> MachineTraceOpener<'a, SC> for MachineTrace<SC, C>
 where
     SC: StarkGenericConfig,
-    C: Chip,
+    C: Chip + InteractionAir<Val<SC>>,
 {
     fn generate_rounds(
         &self,
@@ -711,22 +1508,28 @@ where
                 .flat_map(|chip_trace| {
                     chip_trace.preprocessed.as_ref().map(|preprocessed| {
                         let domain = preprocessed.trace.domain;
-                        vec![zeta, domain.next_point(zeta).unwrap()]
+                        vec![zeta, rotate_point(domain, zeta, 1)]
                     })
                 })
                 .collect_vec();
             rounds.push((preprocessed_data, opening_points));
         }
         if let Some(main_data) = main_data {
-            let opening_points = self
-                .iter()
-                .flat_map(|chip_trace| {
-                    chip_trace.main.as_ref().map(|main| {
-                        let domain = main.trace.domain;
-                        vec![zeta, domain.next_point(zeta).unwrap()]
+            let opening_points =
+                self.iter()
+                    .flat_map(|chip_trace| {
+                        let rotations = InteractionAir::<Val<SC>>::main_rotations(&chip_trace.chip);
+                        // The main extension trace isn't opened at the rotations
+                        chip_trace
+                            .main
+                            .iter()
+                            .map(|main| main_opening_points(main.trace.domain, zeta, &rotations))
+                            .chain(chip_trace.main_ext.as_ref().map(|main_ext| {
+                                main_opening_points(main_ext.trace.domain, zeta, &[])
+                            }))
+                            .collect_vec()
                     })
-                })
-                .collect_vec();
+                    .collect_vec();
             rounds.push((main_data, opening_points));
         }
         if let Some(permutation_data) = permutation_data {
@@ -735,7 +1538,7 @@ where
                 .flat_map(|chip_trace| {
                     chip_trace.permutation.as_ref().map(|permutation| {
                         let domain = permutation.trace.domain;
-                        vec![zeta, domain.next_point(zeta).unwrap()]
+                        vec![zeta, rotate_point(domain, zeta, 1)]
                     })
                 })
                 .collect_vec();
@@ -804,6 +1607,7 @@ where
                         AdjacentOpenedValues {
                             local: openings[0].clone(),
                             next: openings[1].clone(),
+                            rotations: vec![],
                         }
                     })
                 })
@@ -813,23 +1617,58 @@ where
             self.iter().map(|_| None).collect_vec()
         };
 
-        let main_openings = if main_data.is_some() {
+        let (main_openings, main_ext_openings) = if main_data.is_some() {
             let openings = opening_values.pop().expect("Opening should be present");
-            self.iter()
+            let main_openings = self
+                .iter()
                 .map(|chip_trace| {
-                    chip_trace.main.as_ref().map(|main| {
+                    if chip_trace.main.is_empty() {
+                        return None;
+                    }
+                    // The openings of the segments are put side by side
+                    let rotations = InteractionAir::<Val<SC>>::main_rotations(&chip_trace.chip);
+                    let mut values = AdjacentOpenedValues {
+                        local: vec![],
+                        next: vec![],
+                        rotations: vec![vec![]; rotations.len()],
+                    };
+                    for main in &chip_trace.main {
                         let openings = &openings[main.opening_index];
+                        assert_eq!(
+                            openings.len(),
+                            2 + rotations.len(),
+                            "Should have 2 openings and one per rotation"
+                        );
+                        values.local.extend_from_slice(&openings[0]);
+                        values.next.extend_from_slice(&openings[1]);
+                        for (rotation, opening) in values.rotations.iter_mut().zip(&openings[2..]) {
+                            rotation.extend_from_slice(opening);
+                        }
+                    }
+                    Some(values)
+                })
+                .collect_vec();
+            let main_ext_openings = self
+                .iter()
+                .map(|chip_trace| {
+                    chip_trace.main_ext.as_ref().map(|main_ext| {
+                        let openings = &openings[main_ext.opening_index];
                         assert_eq!(openings.len(), 2, "Should have 2 openings");
                         AdjacentOpenedValues {
                             local: openings[0].clone(),
                             next: openings[1].clone(),
+                            rotations: vec![],
                         }
                     })
                 })
-                .collect_vec()
+                .collect_vec();
+            (main_openings, main_ext_openings)
         } else {
             // TODO: Better way
-            self.iter().map(|_| None).collect_vec()
+            (
+                self.iter().map(|_| None).collect_vec(),
+                self.iter().map(|_| None).collect_vec(),
+            )
         };
 
         let preprocessed_openings = if preprocessed_data.is_some() {
@@ -842,6 +1681,7 @@ where
                         AdjacentOpenedValues {
                             local: openings[0].clone(),
                             next: openings[1].clone(),
+                            rotations: vec![],
                         }
                     })
                 })
@@ -854,12 +1694,14 @@ where
         preprocessed_openings
             .into_iter()
             .zip_eq(main_openings)
+            .zip_eq(main_ext_openings)
             .zip_eq(permutation_openings)
             .zip_eq(quotient_openings)
             .map(
-                |(((preprocessed, main), permutation), quotient_chunks)| OpenedValues {
+                |((((preprocessed, main), main_ext), permutation), quotient_chunks)| OpenedValues {
                     preprocessed,
                     main,
+                    main_ext,
                     permutation,
                     quotient_chunks,
                 },
@@ -887,43 +1729,131 @@ where
             })
             .collect()
     }
+
+    fn generate_public_values(&self, public_values: &[Val<SC>]) -> Vec<Vec<Val<SC>>> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)
+                .expect("Public value ranges should be in bounds");
+        (0..layout.num_chips())
+            .map(|i| layout.public_values_for(i).to_vec())
+            .collect()
+    }
 }
 
+/// Wraps the traces for committing. The present, non-empty traces are assigned consecutive
+/// opening indices in chip order, which is the order in which the verifier lists their openings.
+/// Traces are first padded according to `padding`. Returns an error for chips whose trace height
+/// isn't a power of two within the two-adicity of the field.
 fn load_traces<SC, F>(
     pcs: &SC::Pcs,
     traces: Vec<Option<RowMajorMatrix<F>>>,
-) -> Vec<Option<IndexedTrace<F, Domain<SC>>>>
+    padding: PaddingStrategy,
+) -> Result<Vec<Option<IndexedTrace<F, Domain<SC>>>>, MachineError>
 where
     F: Field,
     SC: StarkGenericConfig,
+    Val<SC>: TwoAdicField,
 {
+    let traces = traces.into_iter().map(|trace| vec![trace]).collect();
+    Ok(load_trace_segments::<SC, F>(pcs, traces, padding)?
+        .into_iter()
+        .map(|segments| segments.into_iter().next())
+        .collect())
+}
+
+/// Like [`load_traces`], for chips whose traces are made of several segments. The present,
+/// non-empty segments are assigned consecutive opening indices in chip order, and then in segment
+/// order.
+fn load_trace_segments<SC, F>(
+    pcs: &SC::Pcs,
+    traces: Vec<Vec<Option<RowMajorMatrix<F>>>>,
+    padding: PaddingStrategy,
+) -> Result<Vec<Vec<IndexedTrace<F, Domain<SC>>>>, MachineError>
+where
+    F: Field,
+    SC: StarkGenericConfig,
+    Val<SC>: TwoAdicField,
+{
+    let num_chips = traces.len();
+    let traces = traces
+        .into_iter()
+        .enumerate()
+        .flat_map(|(chip, segments)| segments.into_iter().map(move |segment| (chip, segment)))
+        .collect_vec()
+        .into_par_iter()
+        .map(|(chip, trace)| (chip, trace.map(|trace| padding.pad(trace))))
+        .collect::<Vec<_>>();
+
+    // Opening indices depend on which traces are present, so they're assigned in order before
+    // the traces are loaded in parallel
     let mut count = 0;
-    traces
+    let mut opening_indices = Vec::with_capacity(traces.len());
+    for (chip, trace) in traces.iter() {
+        let degree = trace.as_ref().map_or(0, |trace| trace.height());
+        if degree > 0 {
+            check_trace_height::<Val<SC>>(*chip, degree)?;
+            opening_indices.push(Some(count));
+            count += 1;
+        } else {
+            opening_indices.push(None);
+        }
+    }
+
+    let loaded = traces
         .into_iter()
-        .map(|mt| {
-            if let Some(trace) = mt {
-                let degree = trace.height();
-                if degree > 0 {
-                    let domain = pcs.natural_domain_for_degree(degree);
-                    let trace = Trace {
+        .zip_eq(opening_indices)
+        .collect_vec()
+        .into_par_iter()
+        .map(|((chip, trace), opening_index)| {
+            let indexed_trace = trace.zip(opening_index).map(|(trace, opening_index)| {
+                let domain = pcs.natural_domain_for_degree(trace.height());
+                IndexedTrace {
+                    trace: Trace {
                         value: trace,
                         domain,
-                    };
-                    let index = count;
-                    count += 1;
-
-                    Some(IndexedTrace {
-                        trace,
-                        opening_index: index,
-                    })
-                } else {
-                    None
+                    },
+                    opening_index,
                 }
-            } else {
-                None
-            }
+            });
+            (chip, indexed_trace)
         })
-        .collect()
+        .collect::<Vec<_>>();
+
+    let mut segments = (0..num_chips).map(|_| vec![]).collect_vec();
+    for (chip, trace) in loaded {
+        segments[chip].extend(trace);
+    }
+    Ok(segments)
+}
+
+/// Checks that a domain of `height` points exists in the two-adic subgroups of `F`, before the
+/// PCS is asked to build it.
+fn check_trace_height<F: TwoAdicField>(chip: usize, height: usize) -> Result<(), MachineError> {
+    if height.is_power_of_two() && log2_strict_usize(height) <= F::TWO_ADICITY {
+        Ok(())
+    } else {
+        Err(MachineError::UnsupportedTraceHeight { chip, height })
+    }
+}
+
+/// Assigns the opening indices of the main commitment, which holds the segments of each chip's main
+/// trace followed by its flattened main extension trace, in chip order.
+fn assign_main_opening_indices<SC, C>(trace: &mut MachineTrace<SC, C>)
+where
+    SC: StarkGenericConfig,
+    C: Chip,
+{
+    let mut count = 0;
+    for chip_trace in trace.iter_mut() {
+        for main in chip_trace.main.iter_mut() {
+            main.opening_index = count;
+            count += 1;
+        }
+        if let Some(main_ext) = chip_trace.main_ext.as_mut() {
+            main_ext.opening_index = count;
+            count += 1;
+        }
+    }
 }
 
 fn commit_traces<SC>(
@@ -945,6 +1875,43 @@ where
     }
 }
 
+/// The points at which a main trace over `domain` is opened: `zeta`, the next point, and
+/// `zeta * g^k` for every rotation `k` of the chip.
+fn main_opening_points<D, EF>(domain: D, zeta: EF, rotations: &[usize]) -> Vec<EF>
+where
+    D: PolynomialSpace,
+    EF: ExtensionField<D::Val>,
+{
+    let mut points = vec![zeta, rotate_point(domain, zeta, 1)];
+    points.extend(
+        rotations
+            .iter()
+            .map(|&rotation| rotate_point(domain, zeta, rotation)),
+    );
+    points
+}
+
+/// Returns `point * g^shift`, where `g` is the generator of `domain`. The prover and the verifier
+/// both derive the points that traces are opened at from this, so that they agree on the rows the
+/// openings refer to.
+pub fn rotate_point<D, EF>(domain: D, point: EF, shift: usize) -> EF
+where
+    D: PolynomialSpace,
+    EF: ExtensionField<D::Val>,
+{
+    (0..shift).fold(point, |point, _| {
+        domain
+            .next_point(point)
+            .expect("Trace domains should have a next point")
+    })
+}
+
+/// Returns `g^{-1}`, where `g` is the generator of `domain`, which the constraint folders use for
+/// the selectors of transition windows larger than 2.
+pub fn trace_generator_inv<D: PolynomialSpace>(domain: D) -> D::Val {
+    rotate_point(domain, D::Val::one(), 1).inverse()
+}
+
 #[derive(Clone)]
 pub struct TraceOpening<EF, Domain>
 where
@@ -985,6 +1952,7 @@ where
 
     pub preprocessed: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
     pub main: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
+    pub main_ext: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
     pub permutation: Option<TraceOpening<SC::Challenge, Domain<SC>>>,
 
     pub cumulative_sum: Option<SC::Challenge>,
@@ -1003,6 +1971,7 @@ where
             chip,
             preprocessed: None,
             main: None,
+            main_ext: None,
             permutation: None,
             cumulative_sum: None,
             quotient_chunks: None,
@@ -1061,7 +2030,7 @@ where
         pcs: &'a SC::Pcs,
         chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
         preprocessed_degrees: Vec<usize>,
-    );
+    ) -> Result<(), VerificationError>;
 
     fn verify_shapes(&self) -> Result<(), VerificationError>;
 }
@@ -1070,19 +2039,26 @@ impl<'a, SC, C> MachineTraceOpeningLoader<'a, SC> for Vec<ChipTraceOpening<SC, C
 where
     SC: StarkGenericConfig,
     C: Chip + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    Val<SC>: TwoAdicField,
 {
     fn load_openings(
         &mut self,
         pcs: &'a SC::Pcs,
         chip_proofs: Vec<Option<InteractionAirProof<SC::Challenge>>>,
         preprocessed_degrees: Vec<usize>,
-    ) {
-        for ((chip_trace, chip_proof), preprocessed_degree) in self
+    ) -> Result<(), VerificationError> {
+        for (i, ((chip_trace, chip_proof), preprocessed_degree)) in self
             .iter_mut()
             .zip_eq(chip_proofs.into_iter())
             .zip_eq(preprocessed_degrees.into_iter())
+            .enumerate()
         {
             if let Some(proof) = chip_proof {
+                // The degrees come from the proof, so they're checked before building domains
+                check_trace_height::<Val<SC>>(i, proof.degree)?;
+                if proof.opened_values.preprocessed.is_some() {
+                    check_trace_height::<Val<SC>>(i, preprocessed_degree)?;
+                }
                 chip_trace.preprocessed = proof.opened_values.preprocessed.map(|values| {
                     let domain = pcs.natural_domain_for_degree(preprocessed_degree);
                     TraceOpening { values, domain }
@@ -1093,6 +2069,10 @@ where
                     .opened_values
                     .main
                     .map(|values| TraceOpening { values, domain });
+                chip_trace.main_ext = proof
+                    .opened_values
+                    .main_ext
+                    .map(|values| TraceOpening { values, domain });
                 chip_trace.permutation = proof
                     .opened_values
                     .permutation
@@ -1106,6 +2086,17 @@ where
                 let quotient_domain =
                     domain.create_disjoint_domain(domain.size() * quotient_degree);
                 let quotient_chunks_domains = quotient_domain.split_domains(quotient_degree);
+                if proof
+                    .opened_values
+                    .quotient_chunks
+                    .as_ref()
+                    .is_some_and(|chunks| chunks.len() != quotient_degree)
+                {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "quotient chunk count mismatch",
+                    });
+                }
                 chip_trace.quotient_chunks = proof.opened_values.quotient_chunks.map(|chunks| {
                     let values = chunks
                         .into_iter()
@@ -1119,32 +2110,150 @@ where
                 });
             }
         }
+        Ok(())
     }
 
     fn verify_shapes(&self) -> Result<(), VerificationError> {
         // TODO: Add preprocessed and permutation size check
-        for chip_trace in self.iter() {
-            // TODO: Try to do without the cast
-            let main_width = <C as BaseAir<Val<SC>>>::width(&chip_trace.chip);
+        for (i, chip_trace) in self.iter().enumerate() {
+            let main_width = chip_committed_width::<Val<SC>, _>(&chip_trace.chip);
+            let segment_widths = chip_main_segment_widths::<Val<SC>, _>(&chip_trace.chip);
+            if segment_widths.iter().sum::<usize>() != main_width {
+                return Err(VerificationError::ShapeMismatch {
+                    chip: Some(i),
+                    detail: "main segment widths don't add up to the committed width",
+                });
+            }
 
+            if let (Some(preprocessed), Some(main)) = (&chip_trace.preprocessed, &chip_trace.main) {
+                if preprocessed.domain.size() != main.domain.size() {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "preprocessed and main trace heights differ",
+                    });
+                }
+            }
             if let Some(main) = &chip_trace.main {
                 if main.values.local.len() != main_width {
-                    return Err(VerificationError::InvalidProofShape);
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "main opening width mismatch",
+                    });
                 }
                 if main.values.next.len() != main_width {
-                    return Err(VerificationError::InvalidProofShape);
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "main opening width mismatch",
+                    });
+                }
+                let rotations = InteractionAir::<Val<SC>>::main_rotations(&chip_trace.chip);
+                if main.values.rotations.len() != rotations.len() {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "main rotation opening count mismatch",
+                    });
+                }
+                if main
+                    .values
+                    .rotations
+                    .iter()
+                    .any(|rotation| rotation.len() != main_width)
+                {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "main opening width mismatch",
+                    });
+                }
+            }
+            let main_ext_width = InteractionAir::<Val<SC>>::main_ext_width(&chip_trace.chip);
+            match (&chip_trace.main, &chip_trace.main_ext) {
+                (Some(_), Some(main_ext)) if main_ext_width > 0 => {
+                    let width =
+                        main_ext_width * <SC::Challenge as AbstractExtensionField<Val<SC>>>::D;
+                    if main_ext.values.local.len() != width || main_ext.values.next.len() != width {
+                        return Err(VerificationError::ShapeMismatch {
+                            chip: Some(i),
+                            detail: "main extension opening width mismatch",
+                        });
+                    }
+                }
+                (Some(_), None) if main_ext_width > 0 => {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "main extension opening is missing",
+                    });
+                }
+                (_, Some(_)) => {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "unexpected main extension opening",
+                    });
+                }
+                _ => {}
+            }
+            // Only the main trace is opened at rotations
+            if chip_trace
+                .preprocessed
+                .iter()
+                .chain(&chip_trace.main_ext)
+                .chain(&chip_trace.permutation)
+                .any(|trace| !trace.values.rotations.is_empty())
+            {
+                return Err(VerificationError::ShapeMismatch {
+                    chip: Some(i),
+                    detail: "unexpected rotation openings",
+                });
+            }
+            // Chips without interactions have no permutation trace and take no place in the shared
+            // permutation commitment, so the openings of the other chips stay aligned with it
+            let permutation_width = InteractionAir::<Val<SC>>::permutation_width(&chip_trace.chip);
+            match (&chip_trace.permutation, permutation_width) {
+                (Some(permutation), Some(width)) => {
+                    let width = width * <SC::Challenge as AbstractExtensionField<Val<SC>>>::D;
+                    if permutation.values.local.len() != width
+                        || permutation.values.next.len() != width
+                    {
+                        return Err(VerificationError::ShapeMismatch {
+                            chip: Some(i),
+                            detail: "permutation opening width mismatch",
+                        });
+                    }
                 }
+                (None, None) => {}
+                // The chip isn't used
+                (None, Some(_)) if chip_trace.domain().is_none() => {}
+                _ => {
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "permutation opening doesn't match the chip's interactions",
+                    });
+                }
+            }
+            if chip_trace.permutation.is_some() != chip_trace.cumulative_sum.is_some() {
+                return Err(VerificationError::ShapeMismatch {
+                    chip: Some(i),
+                    detail: "cumulative sum doesn't match the permutation opening",
+                });
+            }
+            if chip_trace.domain().is_some() && chip_trace.quotient_chunks.is_none() {
+                return Err(VerificationError::MissingQuotient { chip: i });
             }
             if let Some(quotient_chunks) = &chip_trace.quotient_chunks {
                 // TODO: Pub values
                 let quotient_degree = get_quotient_degree::<Val<SC>, _>(&chip_trace.chip, 0);
                 if quotient_chunks.traces.len() != quotient_degree {
-                    return Err(VerificationError::InvalidProofShape);
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "quotient chunk count mismatch",
+                    });
                 }
                 if !quotient_chunks.traces.iter().all(|qc| {
                     qc.values.len() == <SC::Challenge as AbstractExtensionField<Val<SC>>>::D
                 }) {
-                    return Err(VerificationError::InvalidProofShape);
+                    return Err(VerificationError::ShapeMismatch {
+                        chip: Some(i),
+                        detail: "quotient chunk opening width mismatch",
+                    });
                 }
             }
         }
@@ -1173,7 +2282,7 @@ where
 impl<SC, C> MachineTraceOpeningVerifier<SC> for MachineTraceOpening<SC, C>
 where
     SC: StarkGenericConfig,
-    C: Chip,
+    C: Chip + BaseAir<Val<SC>> + InteractionAir<Val<SC>>,
 {
     fn generate_rounds(
         &self,
@@ -1197,7 +2306,7 @@ where
                             vec![
                                 (zeta, trace.values.local.clone()),
                                 (
-                                    trace.domain.next_point(zeta).unwrap(),
+                                    rotate_point(trace.domain, zeta, 1),
                                     trace.values.next.clone(),
                                 ),
                             ],
@@ -1215,18 +2324,44 @@ where
                 .iter()
                 .filter_map(|chip_trace| {
                     chip_trace.main.as_ref().map(|trace| {
-                        (
-                            trace.domain,
-                            vec![
-                                (zeta, trace.values.local.clone()),
+                        let rotations = InteractionAir::<Val<SC>>::main_rotations(&chip_trace.chip);
+                        let points = main_opening_points(trace.domain, zeta, &rotations);
+                        let values = [&trace.values.local, &trace.values.next]
+                            .into_iter()
+                            .chain(&trace.values.rotations)
+                            .collect_vec();
+                        // Every segment is a separate matrix of the commitment
+                        let mut start = 0;
+                        chip_main_segment_widths::<Val<SC>, _>(&chip_trace.chip)
+                            .into_iter()
+                            .map(|width| {
+                                let columns = start..start + width;
+                                start += width;
+                                let openings = points
+                                    .iter()
+                                    .zip_eq(&values)
+                                    .map(|(&point, values)| {
+                                        (point, values[columns.clone()].to_vec())
+                                    })
+                                    .collect_vec();
+                                (trace.domain, openings)
+                            })
+                            .chain(chip_trace.main_ext.as_ref().map(|main_ext| {
                                 (
-                                    trace.domain.next_point(zeta).unwrap(),
-                                    trace.values.next.clone(),
-                                ),
-                            ],
-                        )
+                                    main_ext.domain,
+                                    vec![
+                                        (zeta, main_ext.values.local.clone()),
+                                        (
+                                            rotate_point(main_ext.domain, zeta, 1),
+                                            main_ext.values.next.clone(),
+                                        ),
+                                    ],
+                                )
+                            }))
+                            .collect_vec()
                     })
                 })
+                .flatten()
                 .collect_vec();
             rounds.push((main_commitment.clone(), main_domains_and_openings));
         }
@@ -1240,7 +2375,7 @@ where
                             vec![
                                 (zeta, trace.values.local.clone()),
                                 (
-                                    trace.domain.next_point(zeta).unwrap(),
+                                    rotate_point(trace.domain, zeta, 1),
                                     trace.values.next.clone(),
                                 ),
                             ],
@@ -1280,14 +2415,27 @@ pub trait MachineTraceConstraintVerifier<SC>
 where
     SC: StarkGenericConfig,
 {
+    /// Verifies the constraints of every chip at `zeta`. Returns the evaluations each chip was
+    /// checked with, or `None` for chips without traces.
     fn verify_constraints(
         &self,
         zeta: SC::Challenge,
         alpha: SC::Challenge,
         permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         public_values: &[Val<SC>],
+    ) -> Result<Vec<Option<ConstraintReport<SC::Challenge>>>, VerificationError>;
+
+    /// Verifies that the public values the proof carries for each chip fit the chip's public value
+    /// range, that chips sharing public values were proven against the same values, and that
+    /// these are the values in `public_values`.
+    fn verify_public_values(
+        &self,
+        public_values: &[Val<SC>],
+        chip_public_values: &[Vec<Val<SC>>],
     ) -> Result<(), VerificationError>;
 
+    /// Verifies that the cumulative sums of all chips add up to zero. Only the total is
+    /// constrained, so chips which only send or only receive are balanced by the other chips.
     fn verify_cumulative_sums(&self) -> Result<(), VerificationError>;
 }
 
@@ -1302,13 +2450,17 @@ where
         alpha: SC::Challenge,
         permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
         public_values: &[Val<SC>],
-    ) -> Result<(), VerificationError> {
-        for chip_trace in self.iter() {
-            if let Some(domain) = chip_trace.domain() {
+    ) -> Result<Vec<Option<ConstraintReport<SC::Challenge>>>, VerificationError> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)?;
+        let mut reports = Vec::with_capacity(self.len());
+        for (i, chip_trace) in self.iter().enumerate() {
+            let report = if let Some(domain) = chip_trace.domain() {
+                let public_values = layout.public_values_for(i);
                 let qc_domains = chip_trace
                     .quotient_chunks
                     .as_ref()
-                    .expect("Quotient chunks should be present")
+                    .ok_or(VerificationError::MissingQuotient { chip: i })?
                     .traces
                     .iter()
                     .map(|trace| trace.domain)
@@ -1320,6 +2472,10 @@ where
                         .as_ref()
                         .map(|trace| trace.values.clone()),
                     main: chip_trace.main.as_ref().map(|trace| trace.values.clone()),
+                    main_ext: chip_trace
+                        .main_ext
+                        .as_ref()
+                        .map(|trace| trace.values.clone()),
                     permutation: chip_trace
                         .permutation
                         .as_ref()
@@ -1332,7 +2488,7 @@ where
                             .collect_vec()
                     }),
                 };
-                verify_constraints::<SC, _>(
+                let report = evaluate_constraints::<SC, _>(
                     &chip_trace.chip,
                     &opened_values,
                     domain,
@@ -1342,7 +2498,62 @@ where
                     permutation_challenges,
                     chip_trace.cumulative_sum,
                     public_values,
-                )?;
+                )
+                .ok_or(VerificationError::MissingQuotient { chip: i })?;
+                // Check that
+                //     folded_constraints(zeta) / Z_H(zeta) = quotient(zeta)
+                if !report.is_consistent() {
+                    return Err(VerificationError::ConstraintMismatch { chip: i });
+                }
+                Some(report)
+            } else {
+                None
+            };
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    fn verify_public_values(
+        &self,
+        public_values: &[Val<SC>],
+        chip_public_values: &[Vec<Val<SC>>],
+    ) -> Result<(), VerificationError> {
+        let layout =
+            PublicValueLayout::from_chips(self.iter().map(|trace| &trace.chip), public_values)?;
+        if chip_public_values.len() != layout.num_chips() {
+            return Err(VerificationError::ShapeMismatch {
+                chip: None,
+                detail: "public values don't match the chips",
+            });
+        }
+
+        // Public value index -> (chip index, value) of the first chip that reads it
+        let mut seen: BTreeMap<usize, (usize, Val<SC>)> = BTreeMap::new();
+        for (i, values) in chip_public_values.iter().enumerate() {
+            let range = layout.range(i);
+            if values.len() != range.len() {
+                return Err(VerificationError::ShapeMismatch {
+                    chip: Some(i),
+                    detail: "public values don't match the chip's public value range",
+                });
+            }
+            for (index, value) in range.zip(values) {
+                match seen.get(&index) {
+                    Some((other, other_value)) if other_value != value => {
+                        return Err(VerificationError::InconsistentPublicValues {
+                            chips: (*other, i),
+                            index,
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(index, (i, *value));
+                    }
+                }
+                if *value != public_values[index] {
+                    return Err(VerificationError::PublicValuesMismatch { chip: i, index });
+                }
             }
         }
         Ok(())
@@ -1355,7 +2566,9 @@ where
             .sum();
 
         if sum != SC::Challenge::zero() {
-            return Err(VerificationError::NonZeroCumulativeSum);
+            return Err(VerificationError::CumulativeSumMismatch {
+                total: sum.to_string(),
+            });
         }
         Ok(())
     }
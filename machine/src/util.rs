@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::Matrix;
+
+/// Splits the columns of `trace` into groups at `boundaries`, e.g. boundaries `[2, 5]` split a
+/// trace of width 8 into the columns `0..2`, `2..5` and `5..8`. The columns of a group aren't
+/// contiguous in a row-major trace, so every group is copied.
+///
+/// # Panics
+///
+/// Panics if the boundaries aren't strictly increasing or don't lie strictly within the width of
+/// the trace, i.e. if a group would be empty.
+pub fn split_columns<T: Clone + Send + Sync>(
+    trace: &RowMajorMatrixView<'_, T>,
+    boundaries: &[usize],
+) -> Vec<RowMajorMatrix<T>> {
+    let width = trace.width();
+    assert!(
+        boundaries.iter().tuple_windows().all(|(a, b)| a < b),
+        "Column boundaries {:?} must be strictly increasing",
+        boundaries
+    );
+    assert!(
+        boundaries
+            .iter()
+            .all(|&boundary| 0 < boundary && boundary < width),
+        "Column boundaries {:?} must lie within the width {}",
+        boundaries,
+        width
+    );
+
+    core::iter::once(0)
+        .chain(boundaries.iter().copied())
+        .chain(core::iter::once(width))
+        .tuple_windows()
+        .map(|(start, end)| {
+            let values = trace
+                .rows()
+                .flat_map(|row| row.skip(start).take(end - start))
+                .collect();
+            RowMajorMatrix::new(values, end - start)
+        })
+        .collect()
+}
+
+/// Concatenates the columns of `traces`, the inverse of [`split_columns`].
+///
+/// # Panics
+///
+/// Panics if there are no traces or if their heights differ.
+pub fn concat_columns<T: Clone + Send + Sync>(
+    traces: &[RowMajorMatrixView<'_, T>],
+) -> RowMajorMatrix<T> {
+    let height = traces.first().expect("No traces to concatenate").height();
+    assert!(
+        traces.iter().all(|trace| trace.height() == height),
+        "Traces of different heights can't be concatenated"
+    );
+
+    let width = traces.iter().map(|trace| trace.width()).sum();
+    let values = (0..height)
+        .flat_map(|r| traces.iter().flat_map(move |trace| trace.row(r)))
+        .collect();
+    RowMajorMatrix::new(values, width)
+}
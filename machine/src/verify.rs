@@ -2,21 +2,73 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
-use p3_air_util::folders::rap::VerifierConstraintFolder;
+use p3_air::Air;
+use p3_air_util::folders::{
+    rap::{SymbolicAirBuilder, VerifierConstraintFolder},
+    window,
+};
 use p3_air_util::proof::OpenedValues;
 use p3_commit::PolynomialSpace;
-use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_field::{
+    AbstractExtensionField, AbstractField, ExtensionField, Field, PrimeField32, TwoAdicField,
+};
 use p3_interaction::Rap;
 use p3_interaction::NUM_PERM_CHALLENGES;
 use p3_matrix::dense::RowMajorMatrixView;
 use p3_matrix::stack::VerticalPair;
+use p3_maybe_rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use p3_uni_stark::Domain;
 use p3_uni_stark::StarkGenericConfig;
 use p3_uni_stark::Val;
+use tracing::instrument;
 
-use crate::error::VerificationError;
+use crate::{
+    error::BatchVerificationError,
+    joint::{verify_joint, VerifyingInstance},
+    machine::Machine,
+    proof::{MachineProof, VerifyingKey},
+    trace::trace_generator_inv,
+};
 
-pub fn verify_constraints<SC, A>(
+/// The out-of-domain evaluations a chip's constraints are checked with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintReport<EF> {
+    /// The folded constraints at `zeta`, divided by the vanishing polynomial.
+    pub folded_constraints: EF,
+    /// The quotient recombined from its opened chunks at `zeta`.
+    pub quotient: EF,
+}
+
+impl<EF: PartialEq> ConstraintReport<EF> {
+    pub fn is_consistent(&self) -> bool {
+        self.folded_constraints == self.quotient
+    }
+}
+
+/// Recombines the openings of an extension trace that was committed with
+/// `RowMajorMatrix::flatten_to_base`, where each extension column became `EF::D` consecutive base
+/// columns holding its coordinates.
+pub fn unflatten<F, EF>(values: &[EF]) -> Vec<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    values
+        .chunks_exact(EF::D)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .map(|(e_i, &c)| EF::monomial(e_i) * c)
+                .sum()
+        })
+        .collect()
+}
+
+/// Evaluates the constraints of `air` and its quotient at `zeta` from the opened values. Returns
+/// `None` if the quotient isn't opened, or if it isn't opened in one chunk per domain of
+/// `qc_domains`.
+pub fn evaluate_constraints<SC, A>(
     air: &A,
     opened_values: &OpenedValues<SC::Challenge>,
     main_domain: Domain<SC>,
@@ -26,11 +78,16 @@ pub fn verify_constraints<SC, A>(
     permutation_challenges: [SC::Challenge; NUM_PERM_CHALLENGES],
     cumulative_sum: Option<SC::Challenge>,
     public_values: &[Val<SC>],
-) -> Result<(), VerificationError>
+) -> Option<ConstraintReport<SC::Challenge>>
 where
     SC: StarkGenericConfig,
     A: for<'a> Rap<VerifierConstraintFolder<'a, SC>>,
 {
+    let quotient_chunks = opened_values.quotient_chunks.as_ref()?;
+    if quotient_chunks.len() != qc_domains.len() {
+        return None;
+    }
+
     let zps = qc_domains
         .iter()
         .enumerate()
@@ -47,11 +104,7 @@ where
         })
         .collect_vec();
 
-    let quotient = opened_values
-        .quotient_chunks
-        .as_ref()
-        // TODO: Remove
-        .expect("Quotient should be present")
+    let quotient = quotient_chunks
         .iter()
         .enumerate()
         .map(|(ch_i, ch)| {
@@ -64,18 +117,6 @@ where
 
     let sels = main_domain.selectors_at_point(zeta);
 
-    let unflatten = |v: &[SC::Challenge]| {
-        v.chunks_exact(SC::Challenge::D)
-            .map(|chunk| {
-                chunk
-                    .iter()
-                    .enumerate()
-                    .map(|(e_i, &c)| SC::Challenge::monomial(e_i) * c)
-                    .sum()
-            })
-            .collect::<Vec<SC::Challenge>>()
-    };
-
     let (preprocessed_local, preprocessed_next) =
         if let Some(opened_values) = &opened_values.preprocessed {
             (opened_values.local.clone(), opened_values.next.clone())
@@ -83,16 +124,32 @@ where
             (vec![], vec![])
         };
 
+    // The next row is followed by the rows at the air's rotations
     let (main_local, main_next) = if let Some(opened_values) = &opened_values.main {
-        (opened_values.local.clone(), opened_values.next.clone())
+        let main_next = [&opened_values.next]
+            .into_iter()
+            .chain(&opened_values.rotations)
+            .flatten()
+            .copied()
+            .collect_vec();
+        (opened_values.local.clone(), main_next)
+    } else {
+        (vec![], vec![])
+    };
+
+    let (main_ext_local, main_ext_next) = if let Some(opened_values) = &opened_values.main_ext {
+        (
+            unflatten::<Val<SC>, _>(&opened_values.local),
+            unflatten::<Val<SC>, _>(&opened_values.next),
+        )
     } else {
         (vec![], vec![])
     };
 
     let (perm_local, perm_next) = if let Some(opened_values) = &opened_values.permutation {
         (
-            unflatten(&opened_values.local),
-            unflatten(&opened_values.next),
+            unflatten::<Val<SC>, _>(&opened_values.local),
+            unflatten::<Val<SC>, _>(&opened_values.next),
         )
     } else {
         (vec![], vec![])
@@ -103,10 +160,8 @@ where
             RowMajorMatrixView::new_row(&preprocessed_local),
             RowMajorMatrixView::new_row(&preprocessed_next),
         ),
-        main: VerticalPair::new(
-            RowMajorMatrixView::new_row(&main_local),
-            RowMajorMatrixView::new_row(&main_next),
-        ),
+        main: window(&main_local, &main_next),
+        main_ext: window(&main_ext_local, &main_ext_next),
         perm: VerticalPair::new(
             RowMajorMatrixView::new_row(&perm_local),
             RowMajorMatrixView::new_row(&perm_next),
@@ -117,17 +172,116 @@ where
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
         is_transition: sels.is_transition,
+        trace_generator_inv: trace_generator_inv(main_domain),
         alpha,
         accumulator: SC::Challenge::zero(),
+        num_constraints: 0,
+    };
+    // Chips without interactions have no permutation openings, nor constraints on them
+    if opened_values.permutation.is_some() {
+        air.eval_all(&mut folder);
+    } else {
+        air.eval(&mut folder);
+    }
+
+    Some(ConstraintReport {
+        folded_constraints: folder.accumulator * sels.inv_zeroifier,
+        quotient,
+    })
+}
+
+/// Returns the number of constraints the verifier folds for `air`, by evaluating them once on rows
+/// of zeros of the given widths. `perm_width` is in extension field elements.
+pub fn num_verifier_constraints<SC, A>(
+    air: &A,
+    preprocessed_width: usize,
+    main_width: usize,
+    perm_width: usize,
+    public_values: &[Val<SC>],
+) -> usize
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Rap<VerifierConstraintFolder<'a, SC>>,
+{
+    let preprocessed = vec![SC::Challenge::zero(); preprocessed_width];
+    let main_local = vec![SC::Challenge::zero(); main_width];
+    let main_next = vec![SC::Challenge::zero(); main_width * (1 + air.main_rotations().len())];
+    let main_ext = vec![SC::Challenge::zero(); air.main_ext_width()];
+    let perm = vec![SC::Challenge::zero(); perm_width];
+    let mut folder: VerifierConstraintFolder<'_, SC> = VerifierConstraintFolder {
+        preprocessed: VerticalPair::new(
+            RowMajorMatrixView::new_row(&preprocessed),
+            RowMajorMatrixView::new_row(&preprocessed),
+        ),
+        main: window(&main_local, &main_next),
+        main_ext: window(&main_ext, &main_ext),
+        perm: VerticalPair::new(
+            RowMajorMatrixView::new_row(&perm),
+            RowMajorMatrixView::new_row(&perm),
+        ),
+        perm_challenges: [SC::Challenge::zero(); NUM_PERM_CHALLENGES],
+        public_values,
+        cumulative_sum: SC::Challenge::zero(),
+        is_first_row: SC::Challenge::zero(),
+        is_last_row: SC::Challenge::zero(),
+        is_transition: SC::Challenge::zero(),
+        trace_generator_inv: Val::<SC>::one(),
+        alpha: SC::Challenge::zero(),
+        accumulator: SC::Challenge::zero(),
+        num_constraints: 0,
     };
     air.eval_all(&mut folder);
+    folder.num_constraints
+}
 
-    let folded_constraints = folder.accumulator;
-    // Finally, check that
-    //     folded_constraints(zeta) / Z_H(zeta) = quotient(zeta)
-    if folded_constraints * sels.inv_zeroifier != quotient {
-        return Err(VerificationError::OodEvaluationMismatch);
-    }
+/// Verifies independent proofs of `machine` in parallel, each against its own public values. Every
+/// proof is verified with a copy of `challenger`, which should be in the state the proofs were
+/// generated from.
+///
+/// The PCS verifies an opening proof against the transcript of the proof it belongs to, so the
+/// openings are still checked proof by proof. Verification stops at the first invalid proof
+/// found; if several proofs are invalid, any one of them may be reported.
+#[instrument(skip_all)]
+pub fn verify_batch<SC, M>(
+    config: &SC,
+    challenger: &SC::Challenger,
+    machine: &M,
+    vk: &VerifyingKey<SC>,
+    proofs: &[MachineProof<SC>],
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), BatchVerificationError>
+where
+    SC: StarkGenericConfig + Sync,
+    SC::Challenger: Clone,
+    M: Machine + Sync,
+    Val<SC>: PrimeField32 + TwoAdicField,
+    M::Chip:
+        for<'b> Rap<VerifierConstraintFolder<'b, SC>> + for<'b> Rap<SymbolicAirBuilder<Val<SC>>>,
+    VerifyingKey<SC>: Sync,
+    MachineProof<SC>: Sync,
+{
+    assert_eq!(
+        proofs.len(),
+        public_values.len(),
+        "Got public values for {} proofs, expected {}",
+        public_values.len(),
+        proofs.len()
+    );
 
-    Ok(())
+    (0..proofs.len())
+        .into_par_iter()
+        .map(|i| {
+            verify_joint(
+                config,
+                &mut challenger.clone(),
+                vec![VerifyingInstance {
+                    machine,
+                    vk,
+                    proof: &proofs[i],
+                    public_values: &public_values[i],
+                }],
+            )
+            .map_err(|error| BatchVerificationError { proof: i, error })
+        })
+        .collect()
 }
@@ -0,0 +1,81 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{machine::Machine, proof::MachineProof, verify::verify_batch};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockConstraint, MockMachine};
+use common::{challenger, default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+const NUM_PROOFS: usize = 3;
+
+/// A counter sending `0, 1, ..., HEIGHT - 1` to a chip receiving them in reverse.
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Counter", RowMajorMatrix::new_col(values))
+                .with_constraint(MockConstraint::FirstRow(VirtualPairCol::single_main(0)))
+                .with_constraint(MockConstraint::Transition {
+                    local: VirtualPairCol::new_main(vec![(0, Val::one())], Val::one()),
+                    next: VirtualPairCol::single_main(0),
+                })
+                .with_send(&[0], VirtualPairCol::one(), 0),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_verify_batch() {
+    let (config, perm) = default_config();
+    let machine = machine();
+
+    let (pk, vk) = machine.setup(&config);
+    let proofs: Vec<MachineProof<MyConfig>> = (0..NUM_PROOFS)
+        .map(|_| {
+            machine.prove(
+                &config,
+                &mut challenger(&perm),
+                &pk,
+                machine.main_traces(),
+                &[],
+            )
+        })
+        .collect();
+    let public_values = vec![vec![]; NUM_PROOFS];
+
+    verify_batch(
+        &config,
+        &challenger(&perm),
+        &machine,
+        &vk,
+        &proofs,
+        &public_values,
+    )
+    .expect("Proofs should verify");
+    verify_batch(&config, &challenger(&perm), &machine, &vk, &[], &[])
+        .expect("An empty batch should verify");
+
+    // Public values are observed by the challenger, so different ones invalidate the proof
+    let mut public_values = public_values;
+    public_values[1] = vec![Val::one()];
+    let err = verify_batch(
+        &config,
+        &challenger(&perm),
+        &machine,
+        &vk,
+        &proofs,
+        &public_values,
+    )
+    .unwrap_err();
+    assert_eq!(err.proof, 1);
+}
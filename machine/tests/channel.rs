@@ -0,0 +1,156 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::NUM_PERM_CHALLENGES;
+use p3_machine::{
+    channel::{FiatShamirChannel, ProverChannel, ProverRound},
+    error::VerificationError,
+    machine::Machine,
+    proof::Com,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, Challenge, Challenger, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// A [`FiatShamirChannel`] which records the commitments sent through it.
+struct RecordingChannel<'a> {
+    inner: FiatShamirChannel<'a, MyConfig>,
+    commitments: Vec<(ProverRound, Com<MyConfig>)>,
+}
+
+impl<'a> ProverChannel<MyConfig> for RecordingChannel<'a> {
+    fn send_public_values(&mut self, public_values: &[Val]) {
+        self.inner.send_public_values(public_values);
+    }
+
+    fn send_commitment(&mut self, round: ProverRound, commitment: &Com<MyConfig>) {
+        self.commitments.push((round, commitment.clone()));
+        self.inner.send_commitment(round, commitment);
+    }
+
+    fn receive_challenge(&mut self, round: ProverRound) -> Challenge {
+        self.inner.receive_challenge(round)
+    }
+
+    fn opening_challenger(&mut self) -> &mut Challenger {
+        self.inner.opening_challenger()
+    }
+}
+
+/// A [`FiatShamirChannel`] which hands out the permutation challenge at index `perturbed` plus
+/// one, without changing the transcript.
+struct PerturbingChannel<'a> {
+    inner: FiatShamirChannel<'a, MyConfig>,
+    perturbed: usize,
+    received: usize,
+}
+
+impl<'a> ProverChannel<MyConfig> for PerturbingChannel<'a> {
+    fn send_public_values(&mut self, public_values: &[Val]) {
+        self.inner.send_public_values(public_values);
+    }
+
+    fn send_commitment(&mut self, round: ProverRound, commitment: &Com<MyConfig>) {
+        self.inner.send_commitment(round, commitment);
+    }
+
+    fn receive_challenge(&mut self, round: ProverRound) -> Challenge {
+        let challenge = self.inner.receive_challenge(round);
+        if round != ProverRound::Main {
+            return challenge;
+        }
+        let index = self.received;
+        self.received += 1;
+        if index == self.perturbed {
+            challenge + Challenge::one()
+        } else {
+            challenge
+        }
+    }
+
+    fn opening_challenger(&mut self) -> &mut Challenger {
+        self.inner.opening_challenger()
+    }
+}
+
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(values)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_channel_commitments_match_proof() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+
+    let mut prover_challenger = challenger(&perm);
+    let mut channel = RecordingChannel {
+        inner: FiatShamirChannel::new(&mut prover_challenger),
+        commitments: vec![],
+    };
+    let proof = machine.prove_with_channel(&config, &mut channel, &pk, machine.main_traces(), &[]);
+
+    // Every round yields exactly the commitment that ends up in the proof
+    let commitments = &proof.commitments;
+    let expected = [
+        (ProverRound::Main, commitments.main.clone()),
+        (ProverRound::Permutation, commitments.permutation.clone()),
+        (ProverRound::Quotient, commitments.quotient_chunks.clone()),
+    ]
+    .into_iter()
+    .map(|(round, commitment)| (round, commitment.expect("Round should be committed to")))
+    .collect::<Vec<_>>();
+    assert_eq!(channel.commitments, expected);
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_perturbed_permutation_challenge() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+
+    // The openings still verify, but the permutation constraints of `Receiver`, which comes first
+    // in canonical order, no longer match its quotient
+    for perturbed in 0..NUM_PERM_CHALLENGES {
+        let mut verifier_challenger = challenger(&perm);
+        let mut channel = PerturbingChannel {
+            inner: FiatShamirChannel::new(&mut verifier_challenger),
+            perturbed,
+            received: 0,
+        };
+        let result = machine.verify_with_channel(&config, &mut channel, &vk, &proof, &[]);
+        assert!(matches!(
+            result,
+            Err(VerificationError::ConstraintMismatch { chip: 0 })
+        ));
+    }
+}
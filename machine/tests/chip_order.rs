@@ -0,0 +1,75 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::chip::canonicalize;
+use p3_machine::machine::Machine;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, Val};
+
+const HEIGHT: usize = 8;
+
+fn chips() -> Vec<MockChip> {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    vec![
+        MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Idle", RowMajorMatrix::new(values.repeat(2), 2)),
+    ]
+}
+
+#[test]
+fn test_canonicalized_machines_commit_identically() {
+    let (config, perm) = default_config();
+    let chips = chips();
+
+    // The same set of chips, listed in different orders
+    let mut forward = chips.iter().collect::<Vec<_>>();
+    let mut backward = chips.iter().rev().collect::<Vec<_>>();
+    canonicalize(&mut forward);
+    canonicalize(&mut backward);
+    let names = |chips: &[&MockChip]| {
+        chips
+            .iter()
+            .map(|chip| chip.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&forward), ["Idle", "Receiver", "Sender"]);
+    assert_eq!(names(&forward), names(&backward));
+
+    let commitments = [forward, backward].map(|chips| {
+        let machine = MockMachine {
+            chips: chips.into_iter().cloned().collect(),
+        };
+        let (pk, vk) = machine.setup(&config);
+        let proof = machine.prove(
+            &config,
+            &mut challenger(&perm),
+            &pk,
+            machine.main_traces(),
+            &[],
+        );
+        machine
+            .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+            .expect("Proof should verify");
+        [
+            proof.commitments.main,
+            proof.commitments.permutation,
+            proof.commitments.quotient_chunks,
+        ]
+        .map(|commitment| -> [Val; 8] { commitment.unwrap().into() })
+    });
+    assert_eq!(commitments[0], commitments[1]);
+}
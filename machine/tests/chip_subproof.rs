@@ -0,0 +1,62 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{error::VerificationError, machine::Machine};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, Challenge, Val};
+
+const HEIGHT: usize = 8;
+
+#[test]
+fn test_chip_subproof() {
+    let (config, perm) = default_config();
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    let machine = MockMachine {
+        chips: vec![
+            MockChip::new("Sender", RowMajorMatrix::new_col(values)).with_send(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    };
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    for chip in 0..2 {
+        let subproof = proof.chip_subproof(chip).unwrap();
+        let report = machine
+            .verify_chip_subproof(&config, &mut challenger(&perm), &vk, &subproof, &[])
+            .expect("Subproof should verify");
+        assert!(report.is_consistent());
+    }
+    assert!(proof.chip_subproof(2).is_none());
+
+    // A tampered opening of one chip is attributed to that chip
+    let mut subproof = proof.chip_subproof(1).unwrap();
+    subproof.chip_proof.cumulative_sum = subproof
+        .chip_proof
+        .cumulative_sum
+        .map(|cumulative_sum| cumulative_sum + Challenge::one());
+    let result = machine.verify_chip_subproof(&config, &mut challenger(&perm), &vk, &subproof, &[]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::ConstraintMismatch { chip: 1 })
+    ));
+}
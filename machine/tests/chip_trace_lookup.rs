@@ -0,0 +1,41 @@
+mod common;
+
+use p3_field::AbstractField;
+use p3_machine::trace::{
+    MachineTrace, MachineTraceBuilder, MachineTraceLoader, MachineTraceLookup, PaddingStrategy,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+
+use common::mock::MockChip;
+use common::{default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+#[test]
+fn test_chip_trace_lookup() {
+    let (config, _) = default_config();
+    let column = |value: Val| RowMajorMatrix::new_col(vec![value; HEIGHT]);
+    let chips = vec![
+        MockChip::new("Counter", column(Val::zero())),
+        MockChip::new("Register", column(Val::one())),
+        MockChip::new("Register", column(Val::two())),
+    ];
+    let main_traces = chips
+        .iter()
+        .map(|chip| Some(chip.trace.clone()))
+        .collect::<Vec<_>>();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    trace
+        .load_main_single_segment(config.pcs(), main_traces, PaddingStrategy::None)
+        .unwrap();
+
+    assert!(trace.chip_trace("Missing").is_none());
+    // The first of the chips sharing a name is returned
+    let register = trace.chip_trace("Register").unwrap();
+    assert_eq!(register.main[0].opening_index, 1);
+
+    trace.chip_trace_mut("Counter").unwrap().main.clear();
+    assert!(trace[0].main.is_empty());
+}
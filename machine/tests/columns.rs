@@ -0,0 +1,58 @@
+mod common;
+
+use p3_machine::util::{concat_columns, split_columns};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use rand::thread_rng;
+
+use common::Val;
+
+const HEIGHT: usize = 8;
+const WIDTH: usize = 8;
+
+#[test]
+fn test_split_and_concat_columns() {
+    let trace = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, WIDTH);
+
+    let groups = split_columns(&trace.as_view(), &[2, 5]);
+    assert_eq!(
+        groups.iter().map(|group| group.width()).collect::<Vec<_>>(),
+        vec![2, 3, 3]
+    );
+    for r in 0..HEIGHT {
+        assert_eq!(groups[1].row_slice(r)[..], trace.row_slice(r)[2..5]);
+    }
+
+    let views: Vec<_> = groups.iter().map(|group| group.as_view()).collect();
+    assert_eq!(concat_columns(&views).values, trace.values);
+}
+
+#[test]
+fn test_split_columns_without_boundaries() {
+    let trace = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, WIDTH);
+    let groups = split_columns(&trace.as_view(), &[]);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].values, trace.values);
+}
+
+#[test]
+#[should_panic(expected = "must be strictly increasing")]
+fn test_split_columns_unsorted_boundaries() {
+    let trace = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, WIDTH);
+    split_columns(&trace.as_view(), &[5, 2]);
+}
+
+#[test]
+#[should_panic(expected = "must lie within the width")]
+fn test_split_columns_boundary_out_of_bounds() {
+    let trace = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, WIDTH);
+    split_columns(&trace.as_view(), &[2, WIDTH]);
+}
+
+#[test]
+#[should_panic(expected = "different heights")]
+fn test_concat_columns_height_mismatch() {
+    let a = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, 1);
+    let b = RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT / 2, 1);
+    concat_columns(&[a.as_view(), b.as_view()]);
+}
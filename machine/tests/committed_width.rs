@@ -0,0 +1,82 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{
+    error::MachineError,
+    machine::Machine,
+    trace::{MachineTrace, MachineTraceBuilder, MachineTraceLoader, PaddingStrategy},
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+
+use common::mock::{MockChip, MockConstraint, MockMachine};
+use common::{challenger, default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+fn values() -> Vec<Val> {
+    (0..HEIGHT).map(Val::from_canonical_usize).collect()
+}
+
+/// Counts up in its first column, which is the only committed one.
+fn counter(trace: RowMajorMatrix<Val>) -> MockChip {
+    MockChip::new("Counter", trace)
+        .with_constraint(MockConstraint::Transition {
+            local: VirtualPairCol::new_main(vec![(0, Val::one())], Val::one()),
+            next: VirtualPairCol::single_main(0),
+        })
+        .with_committed_width(1)
+}
+
+#[test]
+fn test_scratch_columns_are_not_committed() {
+    let (config, perm) = default_config();
+    // The second column is scratch space that is dropped before committing
+    let values = values()
+        .into_iter()
+        .flat_map(|value| [value, value.square()])
+        .collect();
+    let machine = MockMachine {
+        chips: vec![counter(RowMajorMatrix::new(values, 2))],
+    };
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    let main = proof.chip_proofs[0]
+        .as_ref()
+        .unwrap()
+        .opened_values
+        .main
+        .as_ref()
+        .unwrap();
+    assert_eq!(main.local.len(), 1);
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_committed_width_exceeds_trace_width() {
+    let (config, _) = default_config();
+    let main = RowMajorMatrix::new_col(values());
+    let chips = vec![counter(main.clone()).with_committed_width(2)];
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    let result =
+        trace.load_main_single_segment(config.pcs(), vec![Some(main)], PaddingStrategy::None);
+    assert_eq!(
+        result,
+        Err(MachineError::CommittedWidthExceeded {
+            chip: 0,
+            committed_width: 2,
+            width: 1
+        })
+    );
+}
@@ -0,0 +1,294 @@
+use core::borrow::Borrow;
+use core::fmt::{self, Display, Formatter};
+use core::ops::Range;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_interaction::{
+    BaseInteractionAir, Bus, Interaction, InteractionAir, InteractionAirBuilder, Rap,
+};
+use p3_machine::{chip::Chip, error::TraceGenError, machine::Machine};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use super::Val;
+
+/// A bus identified only by its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockBus(pub usize);
+
+impl From<usize> for MockBus {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for MockBus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Bus{}", self.0)
+    }
+}
+
+impl Bus for MockBus {
+    fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// A constraint on the main columns of a [`MockChip`].
+#[derive(Clone, Debug)]
+pub enum MockConstraint {
+    /// The combination vanishes on the first row.
+    FirstRow(VirtualPairCol<Val>),
+    /// The combination vanishes on the last row.
+    LastRow(VirtualPairCol<Val>),
+    /// The combination vanishes on every row.
+    EveryRow(VirtualPairCol<Val>),
+    /// `next` on the next row equals `local` on the current row, on all but the last row.
+    Transition {
+        local: VirtualPairCol<Val>,
+        next: VirtualPairCol<Val>,
+    },
+}
+
+/// A chip whose main trace, constraints and interactions are configured inline.
+#[derive(Clone, Debug)]
+pub struct MockChip {
+    pub name: String,
+    pub trace: RowMajorMatrix<Val>,
+    pub constraints: Vec<MockConstraint>,
+    pub sends: Vec<Interaction<Val>>,
+    pub receives: Vec<Interaction<Val>>,
+    pub committed_width: Option<usize>,
+    pub main_segment_widths: Option<Vec<usize>>,
+    pub trace_gen_error: Option<TraceGenError>,
+    pub public_value_range: Option<Range<usize>>,
+    pub is_real_column: Option<usize>,
+}
+
+impl MockChip {
+    /// A chip without constraints or interactions. Chip names must be unique within a machine.
+    pub fn new(name: &str, trace: RowMajorMatrix<Val>) -> Self {
+        Self {
+            name: name.to_string(),
+            trace,
+            constraints: vec![],
+            sends: vec![],
+            receives: vec![],
+            committed_width: None,
+            main_segment_widths: None,
+            trace_gen_error: None,
+            public_value_range: None,
+            is_real_column: None,
+        }
+    }
+
+    pub fn with_constraint(mut self, constraint: MockConstraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// Commits only to the first `width` main columns.
+    pub fn with_committed_width(mut self, width: usize) -> Self {
+        self.committed_width = Some(width);
+        self
+    }
+
+    /// Commits to the main trace in segments of the given widths.
+    pub fn with_main_segments(mut self, widths: &[usize]) -> Self {
+        self.main_segment_widths = Some(widths.to_vec());
+        self
+    }
+
+    /// Fails to generate its preprocessed trace with `error`.
+    pub fn with_trace_gen_error(mut self, error: TraceGenError) -> Self {
+        self.trace_gen_error = Some(error);
+        self
+    }
+
+    /// Evaluates the chip against the public values in `range` only.
+    pub fn with_public_value_range(mut self, range: Range<usize>) -> Self {
+        self.public_value_range = Some(range);
+        self
+    }
+
+    /// Marks the main column `col` as one on real rows and zero on padding rows.
+    pub fn with_is_real_column(mut self, col: usize) -> Self {
+        self.is_real_column = Some(col);
+        self
+    }
+
+    /// Sends the main columns `cols` on `bus` with multiplicity `count`.
+    pub fn with_send(mut self, cols: &[usize], count: VirtualPairCol<Val>, bus: usize) -> Self {
+        self.sends.push(Self::interaction(cols, count, bus));
+        self
+    }
+
+    /// Receives the main columns `cols` on `bus` with multiplicity `count`.
+    pub fn with_receive(mut self, cols: &[usize], count: VirtualPairCol<Val>, bus: usize) -> Self {
+        self.receives.push(Self::interaction(cols, count, bus));
+        self
+    }
+
+    fn interaction(cols: &[usize], count: VirtualPairCol<Val>, bus: usize) -> Interaction<Val> {
+        let fields = cols
+            .iter()
+            .map(|&col| VirtualPairCol::single_main(col))
+            .collect();
+        Interaction::new(fields, count, MockBus(bus))
+    }
+}
+
+impl Display for MockChip {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl BaseAir<Val> for MockChip {
+    fn width(&self) -> usize {
+        self.trace.width()
+    }
+}
+
+impl<AB: AirBuilder<F = Val>> Air<AB> for MockChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let local: &[AB::Var] = (*local).borrow();
+        let next: &[AB::Var] = (*next).borrow();
+
+        for constraint in self.constraints.iter() {
+            match constraint {
+                MockConstraint::FirstRow(expr) => builder
+                    .when_first_row()
+                    .assert_zero(expr.apply::<AB::Expr, AB::Var>(&[], local)),
+                MockConstraint::LastRow(expr) => builder
+                    .when_last_row()
+                    .assert_zero(expr.apply::<AB::Expr, AB::Var>(&[], local)),
+                MockConstraint::EveryRow(expr) => {
+                    builder.assert_zero(expr.apply::<AB::Expr, AB::Var>(&[], local))
+                }
+                MockConstraint::Transition {
+                    local: local_expr,
+                    next: next_expr,
+                } => builder.when_transition().assert_eq(
+                    next_expr.apply::<AB::Expr, AB::Var>(&[], next),
+                    local_expr.apply::<AB::Expr, AB::Var>(&[], local),
+                ),
+            }
+        }
+    }
+}
+
+impl BaseInteractionAir<Val> for MockChip {}
+
+impl InteractionAir<Val> for MockChip {
+    fn receives(&self) -> Vec<Interaction<Val>> {
+        self.receives.clone()
+    }
+
+    fn sends(&self) -> Vec<Interaction<Val>> {
+        self.sends.clone()
+    }
+}
+
+impl<AB: InteractionAirBuilder<F = Val>> Rap<AB> for MockChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for MockChip {
+    fn main_headers(&self) -> Vec<String> {
+        (0..self.committed_width.unwrap_or(self.trace.width()))
+            .map(|i| format!("col_{}", i))
+            .collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        (0..self.committed_width.unwrap_or(self.trace.width()))
+            .map(|i| (format!("col_{}", i), "Field".to_string(), i..i + 1))
+            .collect()
+    }
+}
+
+impl Chip for MockChip {
+    fn public_value_range(&self) -> Option<Range<usize>> {
+        self.public_value_range.clone()
+    }
+
+    fn is_real_column(&self) -> Option<usize> {
+        self.is_real_column
+    }
+
+    fn committed_width(&self) -> Option<usize> {
+        self.committed_width
+    }
+
+    fn main_segment_widths(&self) -> Option<Vec<usize>> {
+        self.main_segment_widths.clone()
+    }
+
+    fn check_preprocessed_inputs(&self) -> Result<(), TraceGenError> {
+        self.trace_gen_error.map_or(Ok(()), Err)
+    }
+}
+
+/// A machine made of [`MockChip`]s, whose main traces are the traces of its chips.
+pub struct MockMachine {
+    pub chips: Vec<MockChip>,
+}
+
+impl MockMachine {
+    /// Main traces in the order of the machine's chips.
+    pub fn main_traces(&self) -> Vec<Option<RowMajorMatrix<Val>>> {
+        self.chips
+            .iter()
+            .map(|chip| Some(chip.trace.clone()))
+            .collect()
+    }
+
+    /// Main traces in the order of the machine's chips, split into the chips' segments. Scratch
+    /// columns are part of the last segment.
+    pub fn main_trace_segments(&self) -> Vec<Vec<Option<RowMajorMatrix<Val>>>> {
+        self.chips
+            .iter()
+            .map(|chip| {
+                let widths = chip
+                    .main_segment_widths
+                    .clone()
+                    .unwrap_or_else(|| vec![chip.trace.width()]);
+                let mut start = 0;
+                widths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &width)| {
+                        let end = if i == widths.len() - 1 {
+                            chip.trace.width()
+                        } else {
+                            start + width
+                        };
+                        let values = chip
+                            .trace
+                            .rows()
+                            .flat_map(|row| row.skip(start).take(end - start))
+                            .collect();
+                        let segment = RowMajorMatrix::new(values, end - start);
+                        start = end;
+                        Some(segment)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Machine for MockMachine {
+    type Chip = MockChip;
+
+    type Bus = MockBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        self.chips.clone()
+    }
+}
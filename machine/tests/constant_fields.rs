@@ -0,0 +1,65 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::Interaction;
+use p3_machine::machine::Machine;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockBus, MockChip, MockMachine};
+use common::{challenger, default_config, Val};
+
+const HEIGHT: usize = 8;
+
+/// Two logical messages share a bus and are told apart by a constant tag, which doesn't reference
+/// any column.
+#[test]
+fn test_constant_interaction_fields() {
+    let (config, perm) = default_config();
+    let tagged = |tag: u32, col: usize| {
+        Interaction::new(
+            vec![
+                VirtualPairCol::constant(Val::from_canonical_u32(tag)),
+                VirtualPairCol::single_main(col),
+            ],
+            VirtualPairCol::one(),
+            MockBus(0),
+        )
+    };
+
+    let mut sender = MockChip::new(
+        "Sender",
+        RowMajorMatrix::new((0..2 * HEIGHT).map(Val::from_canonical_usize).collect(), 2),
+    );
+    sender.sends = vec![tagged(1, 0), tagged(2, 1)];
+    // The receiver sees the tags as columns, with its rows in reverse order
+    let received = (0..HEIGHT)
+        .rev()
+        .flat_map(|i| {
+            [
+                Val::one(),
+                Val::from_canonical_usize(2 * i),
+                Val::two(),
+                Val::from_canonical_usize(2 * i + 1),
+            ]
+        })
+        .collect();
+    let receiver = MockChip::new("Receiver", RowMajorMatrix::new(received, 4))
+        .with_receive(&[0, 1], VirtualPairCol::one(), 0)
+        .with_receive(&[2, 3], VirtualPairCol::one(), 0);
+    let machine = MockMachine {
+        chips: vec![sender, receiver],
+    };
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
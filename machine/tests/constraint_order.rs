@@ -0,0 +1,193 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::folders::rap::{ProverConstraintFolder, VerifierConstraintFolder};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{
+    BaseInteractionAir, Interaction, InteractionAir, InteractionAirBuilder, Rap,
+    NUM_PERM_CHALLENGES,
+};
+use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair, Matrix};
+use p3_uni_stark::{PackedChallenge, PackedVal};
+use rand::{thread_rng, Rng};
+
+use common::{Challenge, MyConfig, Val};
+
+/// A Fibonacci chip which sends its first column and receives its second column, so that it has
+/// both main and permutation constraints.
+struct FibonacciChip;
+
+impl<F: Field> BaseAir<F> for FibonacciChip {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for FibonacciChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        builder.when_first_row().assert_zero(local[0]);
+        builder.when_transition().assert_eq(next[0], local[1]);
+        builder
+            .when_transition()
+            .assert_eq(next[1], local[0] + local[1]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for FibonacciChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![VirtualPairCol::single_main(main_indices[1])],
+            next_fields: vec![],
+            public_fields: vec![],
+            count: VirtualPairCol::single_main(main_indices[0]),
+            filter: None,
+            argument_index: 0,
+        }]
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        vec![Interaction {
+            fields: vec![
+                VirtualPairCol::single_main(main_indices[0]),
+                VirtualPairCol::single_main(main_indices[1]),
+            ],
+            next_fields: vec![],
+            public_fields: vec![],
+            count: VirtualPairCol::constant(F::one()),
+            filter: None,
+            argument_index: 1,
+        }]
+    }
+}
+
+impl<F: Field> InteractionAir<F> for FibonacciChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives_from_main_indices(&[0, 1])
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends_from_main_indices(&[0, 1])
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for FibonacciChip {}
+
+/// The prover and the verifier fold the constraints with powers of `alpha` in the order they are
+/// emitted. Evaluates both folders on the same random row, with every selector active, and checks
+/// that they fold to the same value, which for a random `alpha` only holds if the constraints
+/// were emitted in the same order.
+#[test]
+fn test_prover_and_verifier_constraint_order() {
+    let mut rng = thread_rng();
+    let chip = FibonacciChip;
+
+    let main_width = <FibonacciChip as BaseAir<Val>>::width(&chip);
+    let perm_width = InteractionAir::<Val>::permutation_width(&chip).unwrap();
+
+    let main_local: Vec<Val> = (0..main_width).map(|_| rng.gen()).collect();
+    let main_next: Vec<Val> = (0..main_width).map(|_| rng.gen()).collect();
+    let perm_local: Vec<Challenge> = (0..perm_width).map(|_| rng.gen()).collect();
+    let perm_next: Vec<Challenge> = (0..perm_width).map(|_| rng.gen()).collect();
+    let perm_challenges: [Challenge; NUM_PERM_CHALLENGES] = [rng.gen(), rng.gen()];
+    let cumulative_sum: Challenge = rng.gen();
+    let [is_first_row, is_last_row, is_transition]: [Val; 3] = [rng.gen(), rng.gen(), rng.gen()];
+    let trace_generator_inv: Val = rng.gen();
+    let alpha: Challenge = rng.gen();
+
+    // Prover
+    let pack = |row: &[Val]| {
+        row.iter()
+            .map(|&v| PackedVal::<MyConfig>::from(v))
+            .collect::<Vec<_>>()
+    };
+    let pack_ext = |row: &[Challenge]| {
+        row.iter()
+            .map(|&v| PackedChallenge::<MyConfig>::from_f(v))
+            .collect::<Vec<_>>()
+    };
+    let (packed_main_local, packed_main_next) = (pack(&main_local), pack(&main_next));
+    let (packed_perm_local, packed_perm_next) = (pack_ext(&perm_local), pack_ext(&perm_next));
+    let mut prover_folder: ProverConstraintFolder<'_, MyConfig> = ProverConstraintFolder {
+        preprocessed: VerticalPair::new(
+            RowMajorMatrixView::new_row(&[]),
+            RowMajorMatrixView::new_row(&[]),
+        ),
+        main: VerticalPair::new(
+            RowMajorMatrixView::new_row(&packed_main_local),
+            RowMajorMatrixView::new_row(&packed_main_next),
+        ),
+        main_ext: VerticalPair::new(
+            RowMajorMatrixView::new_row(&[]),
+            RowMajorMatrixView::new_row(&[]),
+        ),
+        perm: VerticalPair::new(
+            RowMajorMatrixView::new_row(&packed_perm_local),
+            RowMajorMatrixView::new_row(&packed_perm_next),
+        ),
+        perm_challenges: perm_challenges.map(PackedChallenge::<MyConfig>::from_f),
+        public_values: &[],
+        cumulative_sum: PackedChallenge::<MyConfig>::from_f(cumulative_sum),
+        is_first_row: is_first_row.into(),
+        is_last_row: is_last_row.into(),
+        is_transition: is_transition.into(),
+        trace_generator_inv,
+        alpha: PackedChallenge::<MyConfig>::from_f(alpha),
+        accumulator: Default::default(),
+        num_constraints: 0,
+    };
+    chip.eval_all(&mut prover_folder);
+
+    // Verifier
+    let lift = |row: &[Val]| row.iter().map(|&v| v.into()).collect::<Vec<Challenge>>();
+    let (lifted_main_local, lifted_main_next) = (lift(&main_local), lift(&main_next));
+    let mut verifier_folder: VerifierConstraintFolder<'_, MyConfig> = VerifierConstraintFolder {
+        preprocessed: VerticalPair::new(
+            RowMajorMatrixView::new_row(&[]),
+            RowMajorMatrixView::new_row(&[]),
+        ),
+        main: VerticalPair::new(
+            RowMajorMatrixView::new_row(&lifted_main_local),
+            RowMajorMatrixView::new_row(&lifted_main_next),
+        ),
+        main_ext: VerticalPair::new(
+            RowMajorMatrixView::new_row(&[]),
+            RowMajorMatrixView::new_row(&[]),
+        ),
+        perm: VerticalPair::new(
+            RowMajorMatrixView::new_row(&perm_local),
+            RowMajorMatrixView::new_row(&perm_next),
+        ),
+        perm_challenges,
+        public_values: &[],
+        cumulative_sum,
+        is_first_row: is_first_row.into(),
+        is_last_row: is_last_row.into(),
+        is_transition: is_transition.into(),
+        trace_generator_inv,
+        alpha,
+        accumulator: Default::default(),
+        num_constraints: 0,
+    };
+    chip.eval_all(&mut verifier_folder);
+
+    assert_eq!(
+        prover_folder.num_constraints,
+        verifier_folder.num_constraints
+    );
+    assert_eq!(
+        prover_folder.accumulator,
+        PackedChallenge::<MyConfig>::from_f(verifier_folder.accumulator)
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::error::VerificationError;
+use p3_machine::trace::{
+    ChipTraceOpening, MachineTrace, MachineTraceBuilder, MachineTraceConstraintVerifier,
+    MachineTraceCumulativeSums, MachineTraceLoader, MachineTraceOpening, PaddingStrategy,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::mock::MockChip;
+use common::{default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// A sender and a receiver of the same values on bus 0, along with a chip without interactions.
+fn chips() -> Vec<MockChip> {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    vec![
+        MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Idle", RowMajorMatrix::new_col(values)),
+    ]
+}
+
+fn generate(chips: &[MockChip]) -> MachineTrace<MyConfig, MockChip> {
+    let (config, _) = default_config();
+    let mut rng = thread_rng();
+    let main_traces = chips
+        .iter()
+        .map(|chip| Some(chip.trace.clone()))
+        .collect::<Vec<_>>();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(chips);
+    trace
+        .load_main_single_segment(config.pcs(), main_traces, PaddingStrategy::None)
+        .unwrap();
+    trace
+        .generate_permutation(config.pcs(), &[], [rng.gen(), rng.gen()], &[])
+        .unwrap();
+    trace
+}
+
+/// Opens the cumulative sums of `trace` the way the verifier receives them from the proof.
+fn open(trace: &MachineTrace<MyConfig, MockChip>) -> MachineTraceOpening<MyConfig, MockChip> {
+    trace
+        .iter()
+        .zip(trace.cumulative_sums())
+        .map(|(chip_trace, cumulative_sum)| {
+            let mut opening = ChipTraceOpening::new(chip_trace.chip.clone());
+            opening.cumulative_sum = cumulative_sum;
+            opening
+        })
+        .collect()
+}
+
+#[test]
+fn test_balanced_cumulative_sums() {
+    let trace = generate(&chips());
+
+    let sums = trace.cumulative_sums();
+    assert_eq!(sums.len(), 3);
+    assert!(sums[0].is_some_and(|sum| sum != Challenge::zero()));
+    assert_eq!(sums[1], sums[0].map(|sum| -sum));
+    assert_eq!(sums[2], None);
+    assert_eq!(trace.total_cumulative_sum(), Challenge::zero());
+}
+
+#[test]
+fn test_dropped_receive_cumulative_sums() {
+    let mut chips = chips();
+    chips[1].receives.clear();
+    let trace = generate(&chips);
+
+    assert_eq!(trace.cumulative_sums()[1], None);
+    assert_ne!(trace.total_cumulative_sum(), Challenge::zero());
+}
+
+#[test]
+fn test_verify_cumulative_sums() {
+    let trace = generate(&chips());
+    assert!(open(&trace).verify_cumulative_sums().is_ok());
+
+    let mut chips = chips();
+    chips[1].receives.clear();
+    let trace = generate(&chips);
+    assert!(matches!(
+        open(&trace).verify_cumulative_sums(),
+        Err(VerificationError::CumulativeSumMismatch { total })
+            if total == trace.total_cumulative_sum().to_string()
+    ));
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, Bus, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::{chip::Chip, machine::Machine};
+
+use common::{challenger, default_config};
+
+struct EmptyBus;
+
+impl From<usize> for EmptyBus {
+    fn from(_value: usize) -> Self {
+        panic!("Empty machine has no buses")
+    }
+}
+
+impl Display for EmptyBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EmptyBus")
+    }
+}
+
+impl Bus for EmptyBus {
+    fn index(&self) -> usize {
+        unreachable!()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct EmptyChip;
+
+impl Display for EmptyChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EmptyChip")
+    }
+}
+
+impl<F: Field> BaseAir<F> for EmptyChip {
+    fn width(&self) -> usize {
+        0
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for EmptyChip {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+impl<F: Field> BaseInteractionAir<F> for EmptyChip {}
+
+impl<F: Field> InteractionAir<F> for EmptyChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for EmptyChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for EmptyChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec![]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![]
+    }
+}
+
+impl Chip for EmptyChip {}
+
+struct EmptyMachine;
+
+impl Machine for EmptyMachine {
+    type Chip = EmptyChip;
+
+    type Bus = EmptyBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![]
+    }
+}
+
+#[test]
+fn test_empty_machine() {
+    let (config, perm) = default_config();
+    let machine = EmptyMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(&config, &mut challenger(&perm), &pk, vec![], &[]);
+    assert!(proof.opening_proof.is_none());
+    assert!(proof.chip_proofs.is_empty());
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Empty machine proof should verify");
+}
@@ -0,0 +1,66 @@
+use p3_baby_bear::BabyBear;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{AbstractField, ExtensionField, Field};
+use p3_goldilocks::Goldilocks;
+use p3_machine::verify::unflatten;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use rand::distributions::{Distribution, Standard};
+use rand::{thread_rng, Rng};
+
+const HEIGHT: usize = 16;
+
+/// Opening a committed trace is a linear functional over its rows. Checks that applying one to
+/// the flattened trace and unflattening gives the same result as applying it to the trace itself.
+fn check_round_trip<F, EF>(width: usize)
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    Standard: Distribution<F> + Distribution<EF>,
+{
+    let mut rng = thread_rng();
+    let matrix = RowMajorMatrix::<EF>::rand(&mut rng, HEIGHT, width);
+    let flat: RowMajorMatrix<F> = matrix.flatten_to_base();
+    assert_eq!(flat.width(), width * EF::D);
+
+    // Row by row
+    for (row, flat_row) in matrix.rows().zip(flat.rows()) {
+        let opened = flat_row.map(EF::from_base).collect::<Vec<_>>();
+        assert_eq!(unflatten::<F, EF>(&opened), row.collect::<Vec<_>>());
+    }
+
+    // At a random linear combination of the rows, like an opening at an out-of-domain point
+    let weights: Vec<EF> = (0..HEIGHT).map(|_| rng.gen()).collect();
+    let combine = |rows: Vec<Vec<EF>>| {
+        let mut acc = vec![EF::zero(); rows[0].len()];
+        for (row, &weight) in rows.iter().zip(weights.iter()) {
+            for (a, &v) in acc.iter_mut().zip(row.iter()) {
+                *a += weight * v;
+            }
+        }
+        acc
+    };
+    let opened = combine(
+        flat.rows()
+            .map(|row| row.map(EF::from_base).collect())
+            .collect(),
+    );
+    let expected = combine(matrix.rows().map(|row| row.collect()).collect());
+    assert_eq!(unflatten::<F, EF>(&opened), expected);
+}
+
+#[test]
+fn test_flatten_unflatten_round_trip_degree_4() {
+    type EF = BinomialExtensionField<BabyBear, 4>;
+    for width in [1, 2, 5] {
+        check_round_trip::<BabyBear, EF>(width);
+    }
+}
+
+#[test]
+fn test_flatten_unflatten_round_trip_degree_2() {
+    type EF = BinomialExtensionField<Goldilocks, 2>;
+    for width in [1, 2, 5] {
+        check_round_trip::<Goldilocks, EF>(width);
+    }
+}
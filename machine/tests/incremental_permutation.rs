@@ -0,0 +1,109 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_interaction::{
+    generate_permutation_trace_incremental, generate_permutation_trace_with_beta_source,
+    DefaultBetaSource, Interaction, InteractionType, PermutationTraceOptions, NUM_PERM_CHALLENGES,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use rand::{thread_rng, Rng};
+
+use common::{Challenge, Val};
+
+const HEIGHT: usize = 16;
+const WIDTH: usize = 4;
+
+/// A send of the first two columns and a receive of the third column along with the third column
+/// of the next row, counted by the last column.
+fn interactions() -> Vec<(Interaction<Val>, InteractionType)> {
+    let send = Interaction {
+        fields: vec![
+            VirtualPairCol::single_main(0),
+            VirtualPairCol::single_main(1),
+        ],
+        next_fields: vec![],
+        public_fields: vec![],
+        count: VirtualPairCol::single_main(3),
+        filter: None,
+        argument_index: 0,
+    };
+    let receive = Interaction {
+        fields: vec![VirtualPairCol::single_main(2)],
+        next_fields: vec![VirtualPairCol::single_main(2)],
+        public_fields: vec![],
+        count: VirtualPairCol::single_main(3),
+        filter: None,
+        argument_index: 1,
+    };
+    vec![
+        (send, InteractionType::Send),
+        (receive, InteractionType::Receive),
+    ]
+}
+
+fn generate(
+    main: &RowMajorMatrix<Val>,
+    interactions: &[(Interaction<Val>, InteractionType)],
+    perm_challenges: [Challenge; NUM_PERM_CHALLENGES],
+    options: PermutationTraceOptions,
+) -> RowMajorMatrix<Challenge> {
+    generate_permutation_trace_with_beta_source(
+        &None,
+        &Some(main.as_view()),
+        &[],
+        interactions,
+        perm_challenges,
+        options,
+        &DefaultBetaSource,
+    )
+    .unwrap()
+}
+
+/// Randomly rewrites a few ranges of rows of the main trace, and checks that updating the
+/// permutation trace of the old rows matches regenerating it.
+#[test]
+fn test_incremental_matches_full_generation() {
+    let mut rng = thread_rng();
+    let interactions = interactions();
+
+    for max_constraint_degree in [None, Some(3)] {
+        let options = PermutationTraceOptions {
+            max_constraint_degree,
+            ..Default::default()
+        };
+        for _ in 0..32 {
+            let perm_challenges: [Challenge; NUM_PERM_CHALLENGES] = [rng.gen(), rng.gen()];
+            let mut main = RowMajorMatrix::<Val>::rand(&mut rng, HEIGHT, WIDTH);
+            let previous = generate(&main, &interactions, perm_challenges, options);
+
+            let dirty_rows = (0..rng.gen_range(0..4))
+                .map(|_| {
+                    let start = rng.gen_range(0..HEIGHT);
+                    start..rng.gen_range(start..=HEIGHT)
+                })
+                .collect::<Vec<_>>();
+            for range in dirty_rows.iter() {
+                for value in main.values[range.start * WIDTH..range.end * WIDTH].iter_mut() {
+                    *value = rng.gen();
+                }
+            }
+
+            let incremental = generate_permutation_trace_incremental(
+                &None,
+                &Some(main.as_view()),
+                &[],
+                &interactions,
+                perm_challenges,
+                options,
+                &DefaultBetaSource,
+                previous,
+                &dirty_rows,
+            );
+            assert_eq!(
+                incremental,
+                generate(&main, &interactions, perm_challenges, options),
+                "dirty rows {dirty_rows:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::{generate_permutation_trace, InteractionAir};
+use p3_machine::machine::Machine;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use rand::{thread_rng, Rng};
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, Challenge, Val};
+
+const HEIGHT: usize = 8;
+
+/// `Sender` holds `0, 1, ..., HEIGHT - 1` along with a selector for the even rows, and sends its
+/// values on the rows the selector enables. `Receiver` receives the even values.
+fn machine() -> MockMachine {
+    let values = (0..HEIGHT)
+        .flat_map(|i| [Val::from_canonical_usize(i), Val::from_bool(i % 2 == 0)])
+        .collect();
+    let received = (0..HEIGHT)
+        .step_by(2)
+        .map(Val::from_canonical_usize)
+        .collect();
+
+    let mut sender = MockChip::new("Sender", RowMajorMatrix::new(values, 2)).with_send(
+        &[0],
+        VirtualPairCol::one(),
+        0,
+    );
+    sender.sends[0].filter = Some(VirtualPairCol::single_main(1));
+    MockMachine {
+        chips: vec![
+            sender,
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_filtered_send() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+/// The running sum of `Sender` only changes on the even rows, where its send is enabled.
+#[test]
+fn test_filtered_running_sum() {
+    let machine = machine();
+    let sender = &machine.chips[0];
+    let perm_challenges: [Challenge; 2] = [thread_rng().gen(), thread_rng().gen()];
+    let permutation = generate_permutation_trace(
+        &None,
+        &Some(sender.trace.as_view()),
+        &[],
+        &InteractionAir::<Val>::all_interactions(sender),
+        perm_challenges,
+    )
+    .unwrap();
+
+    let running_sums = permutation
+        .rows()
+        .map(|row| row.last().unwrap())
+        .collect::<Vec<_>>();
+    assert_ne!(running_sums[0], Challenge::zero());
+    for row in 1..HEIGHT {
+        assert_eq!(
+            running_sums[row] == running_sums[row - 1],
+            row % 2 == 1,
+            "Unexpected running sum on row {}",
+            row
+        );
+    }
+}
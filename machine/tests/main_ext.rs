@@ -0,0 +1,208 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, BaseAir, ExtensionBuilder};
+use p3_air_util::debug::rap::try_check_constraints;
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_field::{AbstractExtensionField, AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Bus, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::{
+    chip::Chip,
+    error::{MachineError, VerificationError},
+    joint::{prove_joint, ProvingInstance},
+    machine::Machine,
+    trace::{MachineTrace, MachineTraceBuilder, MachineTraceLoader, PaddingStrategy},
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::{challenger, default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+struct EmptyBus;
+
+impl From<usize> for EmptyBus {
+    fn from(_value: usize) -> Self {
+        panic!("Square machine has no buses")
+    }
+}
+
+impl Display for EmptyBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EmptyBus")
+    }
+}
+
+impl Bus for EmptyBus {
+    fn index(&self) -> usize {
+        unreachable!()
+    }
+}
+
+/// Has a base field column `a` and extension field columns `c` and `d`, constrained by
+/// `d = c^2 + a` in the extension field.
+#[derive(Clone, Debug)]
+struct SquareChip;
+
+impl Display for SquareChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "Square")
+    }
+}
+
+impl<F: Field> BaseAir<F> for SquareChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: InteractionAirBuilder> Air<AB> for SquareChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let a = main.row_slice(0)[0];
+        let main_ext = builder.main_ext();
+        let local = main_ext.row_slice(0);
+        let (c, d) = (local[0], local[1]);
+
+        let c: AB::ExprEF = c.into();
+        builder.assert_eq_ext(d, c.clone() * c + AB::ExprEF::from_base(a.into()));
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for SquareChip {}
+
+impl<F: Field> InteractionAir<F> for SquareChip {
+    fn main_ext_width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for SquareChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for SquareChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["a".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("a".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for SquareChip {}
+
+struct SquareMachine;
+
+impl Machine for SquareMachine {
+    type Chip = SquareChip;
+
+    type Bus = EmptyBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![SquareChip]
+    }
+}
+
+fn traces() -> (RowMajorMatrix<Val>, RowMajorMatrix<Challenge>) {
+    let mut rng = thread_rng();
+    let main = RowMajorMatrix::<Val>::rand(&mut rng, HEIGHT, 1);
+    let main_ext = main
+        .values
+        .iter()
+        .flat_map(|&a| {
+            let c: Challenge = rng.gen();
+            [c, c * c + Challenge::from_base(a)]
+        })
+        .collect();
+    (main, RowMajorMatrix::new(main_ext, 2))
+}
+
+#[test]
+fn test_main_ext_openings() {
+    let (config, perm) = default_config();
+    let machine = SquareMachine;
+    let (main, main_ext) = traces();
+
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = prove_joint(
+        &config,
+        &mut challenger(&perm),
+        vec![ProvingInstance::new(&machine, &pk, vec![Some(main)], &[])
+            .with_main_ext(vec![Some(main_ext)])],
+    )
+    .pop()
+    .unwrap();
+
+    // The extension columns are opened flattened to the base field
+    let opened_values = &proof.chip_proofs[0].as_ref().unwrap().opened_values;
+    let main_ext = opened_values.main_ext.as_ref().unwrap();
+    let width = 2 * <Challenge as AbstractExtensionField<Val>>::D;
+    assert_eq!(main_ext.local.len(), width);
+    assert_eq!(main_ext.next.len(), width);
+    assert_eq!(opened_values.main.as_ref().unwrap().local.len(), 1);
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+
+    proof.chip_proofs[0]
+        .as_mut()
+        .unwrap()
+        .opened_values
+        .main_ext = None;
+    let result = machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch { chip: Some(0), .. })
+    ));
+}
+
+#[test]
+fn test_main_ext_constraints() {
+    let (main, mut main_ext) = traces();
+    let check = |main_ext: &RowMajorMatrix<Challenge>| {
+        try_check_constraints::<Val, Challenge, _>(
+            &SquareChip,
+            &None,
+            &Some(main.as_view()),
+            &Some(main_ext.as_view()),
+            &None,
+            [Challenge::zero(); 2],
+            None,
+            &[],
+        )
+    };
+    assert!(check(&main_ext).is_ok());
+
+    main_ext.values[2 * 3 + 1] += Challenge::one();
+    assert_eq!(check(&main_ext).unwrap_err().row, 3);
+}
+
+#[test]
+fn test_main_ext_width_mismatch() {
+    let (config, _) = default_config();
+    let chips = SquareMachine.chips();
+    let (_, main_ext) = traces();
+
+    // Only the column `c`, and no trace at all
+    let c = RowMajorMatrix::new(main_ext.values.into_iter().step_by(2).collect(), 1);
+    for (main_ext, actual) in [(Some(c), 1), (None, 0)] {
+        let mut trace: MachineTrace<MyConfig, SquareChip> = MachineTraceBuilder::new(&chips);
+        let result = trace.load_main_ext(config.pcs(), vec![main_ext], PaddingStrategy::None);
+        assert_eq!(
+            result,
+            Err(MachineError::MainExtWidth {
+                chip: 0,
+                expected: 2,
+                actual
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{
+    error::MachineError,
+    joint::{prove_joint, ProvingInstance},
+    machine::Machine,
+    trace::{MachineTrace, MachineTraceBuilder, MachineTraceLoader, PaddingStrategy},
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+
+use common::mock::{MockChip, MockConstraint, MockMachine};
+use common::{challenger, default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// `Sum` is committed in a segment holding `x` and one holding `y` and `x + y`, and sends `x` to
+/// `Receiver`, whose main trace is a single segment.
+fn machine() -> MockMachine {
+    let values = (0..HEIGHT)
+        .flat_map(|i| {
+            let (x, y) = (
+                Val::from_canonical_usize(i),
+                Val::from_canonical_usize(2 * i),
+            );
+            [x, y, x + y]
+        })
+        .collect();
+    let received = (0..HEIGHT).rev().map(Val::from_canonical_usize).collect();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Sum", RowMajorMatrix::new(values, 3))
+                .with_main_segments(&[1, 2])
+                .with_constraint(MockConstraint::EveryRow(VirtualPairCol::new_main(
+                    vec![(0, Val::one()), (1, Val::one()), (2, -Val::one())],
+                    Val::zero(),
+                )))
+                .with_send(&[0], VirtualPairCol::one(), 0),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_load_main_segments() {
+    let (config, _) = default_config();
+    let machine = machine();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&machine.chips);
+    trace
+        .load_main(
+            config.pcs(),
+            machine.main_trace_segments(),
+            PaddingStrategy::None,
+        )
+        .unwrap();
+
+    let opening_indices = trace
+        .iter()
+        .map(|chip_trace| {
+            chip_trace
+                .main
+                .iter()
+                .map(|segment| segment.opening_index)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(opening_indices, vec![vec![0, 1], vec![2]]);
+
+    // The segments are put back side by side
+    for (chip, chip_trace) in machine.chips.iter().zip(&trace) {
+        assert_eq!(chip_trace.main_trace().unwrap().as_ref(), &chip.trace);
+    }
+}
+
+#[test]
+fn test_prove_main_segments() {
+    let (config, perm) = default_config();
+    let machine = machine();
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = prove_joint(
+        &config,
+        &mut challenger(&perm),
+        vec![ProvingInstance {
+            machine: &machine,
+            pk: &pk,
+            main_traces: machine.main_trace_segments(),
+            main_ext_traces: vec![],
+            public_values: &[],
+        }],
+    )
+    .pop()
+    .unwrap();
+
+    // The proof opens the main trace of `Sum` as a whole
+    let main = proof.chip_proofs[1]
+        .as_ref()
+        .unwrap()
+        .opened_values
+        .main
+        .as_ref()
+        .unwrap();
+    assert_eq!(main.local.len(), 3);
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_missing_main_segment() {
+    let (config, _) = default_config();
+    let machine = machine();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&machine.chips);
+    let result =
+        trace.load_main_single_segment(config.pcs(), machine.main_traces(), PaddingStrategy::None);
+    assert_eq!(
+        result,
+        Err(MachineError::MainSegmentCount {
+            chip: 0,
+            expected: 2,
+            actual: 1
+        })
+    );
+}
+
+#[test]
+fn test_main_segment_width_mismatch() {
+    let (config, _) = default_config();
+    let machine = machine();
+
+    // Swap the widths of the segments of `Sum`
+    let mut segments = machine.main_trace_segments();
+    let values = machine.chips[0].trace.values.clone();
+    segments[0] = vec![
+        Some(RowMajorMatrix::new(values[..2 * HEIGHT].to_vec(), 2)),
+        Some(RowMajorMatrix::new_col(values[2 * HEIGHT..].to_vec())),
+    ];
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&machine.chips);
+    let result = trace.load_main(config.pcs(), segments, PaddingStrategy::None);
+    assert_eq!(
+        result,
+        Err(MachineError::MainSegmentWidth {
+            chip: 0,
+            segment: 0,
+            expected: 1,
+            actual: 2
+        })
+    );
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_air_util::debug::rap::{
+    collect_constraint_values, try_check_constraints, ConstraintError, ConstraintKind,
+};
+use p3_field::AbstractField;
+use p3_machine::machine::Machine;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use common::mock::{MockChip, MockConstraint, MockMachine};
+use common::{challenger, default_config, Challenge, Val};
+
+const HEIGHT: usize = 8;
+
+/// A counter from zero, whose first column is `0, 1, ..., HEIGHT - 1`.
+fn counter(trace: RowMajorMatrix<Val>) -> MockChip {
+    MockChip::new("Counter", trace)
+        .with_constraint(MockConstraint::FirstRow(VirtualPairCol::single_main(0)))
+        .with_constraint(MockConstraint::Transition {
+            local: VirtualPairCol::new_main(vec![(0, Val::one())], Val::one()),
+            next: VirtualPairCol::single_main(0),
+        })
+}
+
+fn counter_trace() -> RowMajorMatrix<Val> {
+    RowMajorMatrix::new_col((0..HEIGHT).map(Val::from_canonical_usize).collect())
+}
+
+#[test]
+fn test_mock_machine() {
+    let (config, perm) = default_config();
+    let mut received = counter_trace().values;
+    received.reverse();
+    let machine = MockMachine {
+        chips: vec![
+            counter(counter_trace()).with_send(&[0], VirtualPairCol::one(), 0),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    };
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+/// A chip without interactions has no permutation trace, so nothing is committed for it.
+#[test]
+fn test_chip_without_interactions() {
+    let (config, perm) = default_config();
+    let machine = MockMachine {
+        chips: vec![counter(counter_trace())],
+    };
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    assert!(proof.commitments.permutation.is_none());
+    let chip_proof = proof.chip_proofs[0].as_ref().unwrap();
+    assert!(chip_proof.opened_values.permutation.is_none());
+    assert!(chip_proof.cumulative_sum.is_none());
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_mock_constraint_values() {
+    let mut trace = counter_trace();
+    trace.values[3] += Val::one();
+    let chip = counter(trace.clone());
+
+    let values = collect_constraint_values::<Val, Challenge, _>(
+        &chip,
+        &None,
+        &Some(trace.as_view()),
+        &None,
+        &None,
+        [Challenge::zero(); 2],
+        None,
+        &[],
+    );
+    assert_eq!(values.width(), 2);
+    // The transitions into and out of the modified row break
+    let failing_rows = (0..HEIGHT)
+        .filter(|&i| values.row_slice(i).iter().any(|v| !v.is_zero()))
+        .collect::<Vec<_>>();
+    assert_eq!(failing_rows, vec![2, 3]);
+}
+
+#[test]
+fn test_try_check_constraints() {
+    let check = |trace: RowMajorMatrix<Val>| {
+        try_check_constraints::<Val, Challenge, _>(
+            &counter(trace.clone()),
+            &None,
+            &Some(trace.as_view()),
+            &None,
+            &None,
+            [Challenge::zero(); 2],
+            None,
+            &[],
+        )
+    };
+    assert_eq!(check(counter_trace()), Ok(()));
+
+    let mut trace = counter_trace();
+    trace.values[3] += Val::one();
+    assert_eq!(
+        check(trace),
+        Err(ConstraintError {
+            row: 2,
+            constraint: 1,
+            kind: ConstraintKind::Transition,
+        })
+    );
+
+    let mut trace = counter_trace();
+    trace.values[0] += Val::one();
+    assert_eq!(
+        check(trace),
+        Err(ConstraintError {
+            row: 0,
+            constraint: 0,
+            kind: ConstraintKind::FirstRow,
+        })
+    );
+}
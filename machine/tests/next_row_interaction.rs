@@ -0,0 +1,125 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::Interaction;
+use p3_machine::machine::Machine;
+use p3_machine::trace::{
+    MachineTrace, MachineTraceBuilder, MachineTraceChecker, MachineTraceCumulativeSums,
+    MachineTraceLoader, PaddingStrategy,
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::mock::{MockBus, MockChip, MockMachine};
+use common::{challenger, default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// `Cycle` sends every value of its column along with the value on the next row, and `Pairs`
+/// receives the pairs `(i, i + 1)`, except on its last row, which holds `last`.
+fn machine(last: (usize, usize)) -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut cycle = MockChip::new("Cycle", RowMajorMatrix::new_col(values));
+    let mut send = Interaction::new(
+        vec![VirtualPairCol::single_main(0)],
+        VirtualPairCol::one(),
+        MockBus(0),
+    );
+    send.next_fields = vec![VirtualPairCol::single_main(0)];
+    cycle.sends.push(send);
+
+    let pairs = (0..HEIGHT - 1)
+        .map(|i| (i, i + 1))
+        .chain([last])
+        .flat_map(|(a, b)| [Val::from_canonical_usize(a), Val::from_canonical_usize(b)])
+        .collect();
+    let pairs = MockChip::new("Pairs", RowMajorMatrix::new(pairs, 2)).with_receive(
+        &[0, 1],
+        VirtualPairCol::one(),
+        0,
+    );
+
+    MockMachine {
+        chips: vec![cycle, pairs],
+    }
+}
+
+/// The last row of `Cycle` sends `(HEIGHT - 1, 0)`, since its next row wraps around to the first.
+fn wrapped() -> MockMachine {
+    machine((HEIGHT - 1, 0))
+}
+
+/// Receives the pair that a next row past the end of the trace would give instead.
+fn unwrapped() -> MockMachine {
+    machine((HEIGHT - 1, HEIGHT))
+}
+
+fn generate(
+    machine: &MockMachine,
+    perm_challenges: [Challenge; 2],
+) -> MachineTrace<MyConfig, MockChip> {
+    let (config, _) = default_config();
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&machine.chips);
+    trace
+        .load_main_single_segment(config.pcs(), machine.main_traces(), PaddingStrategy::None)
+        .unwrap();
+    trace
+        .generate_permutation(config.pcs(), &[], perm_challenges, &[])
+        .unwrap();
+    trace
+}
+
+#[test]
+fn test_next_field_wraps() {
+    let machine = wrapped();
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen(), rng.gen()];
+
+    // Both the generation of the permutation trace and the evaluation of its constraints wrap
+    let trace = generate(&machine, perm_challenges);
+    assert_eq!(trace.total_cumulative_sum(), Challenge::zero());
+    trace.check_constraints::<MockBus>(perm_challenges, &[]);
+
+    let (config, perm) = default_config();
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_next_field_without_wrap() {
+    let machine = unwrapped();
+    let mut rng = thread_rng();
+    let perm_challenges = [rng.gen(), rng.gen()];
+
+    // The constraints agree with the wrapped permutation trace, but the bus doesn't balance
+    let trace = generate(&machine, perm_challenges);
+    assert_eq!(trace.try_check_constraints(perm_challenges, &[]), Ok(()));
+    assert_ne!(trace.total_cumulative_sum(), Challenge::zero());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Bus0 bus cumulative sum is not zero")]
+fn test_prove_next_field_without_wrap() {
+    let (config, perm) = default_config();
+    let machine = unwrapped();
+    let (pk, _) = machine.setup(&config);
+    machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+}
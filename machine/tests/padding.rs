@@ -0,0 +1,117 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_air_util::debug::rap::{ConstraintError, ConstraintKind};
+use p3_field::AbstractField;
+use p3_machine::{
+    error::{ChipConstraintError, MachineError},
+    trace::{
+        MachineTrace, MachineTraceBuilder, MachineTraceChecker, MachineTraceLoader, PaddingStrategy,
+    },
+};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_uni_stark::StarkGenericConfig;
+
+use common::mock::{MockChip, MockConstraint};
+use common::{default_config, Challenge, MyConfig, Val};
+
+/// A two-column trace of the given height, whose rows are `(i, 2i)`.
+fn trace(height: usize) -> RowMajorMatrix<Val> {
+    let values = (0..height)
+        .flat_map(|i| {
+            [
+                Val::from_canonical_usize(i),
+                Val::from_canonical_usize(2 * i),
+            ]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+#[test]
+fn test_pad() {
+    let padded = PaddingStrategy::RepeatLastRow.pad(trace(6));
+    assert_eq!(padded.height(), 8);
+    assert_eq!(padded.row_slice(6).to_vec(), padded.row_slice(5).to_vec());
+    assert_eq!(padded.row_slice(7).to_vec(), padded.row_slice(5).to_vec());
+
+    let padded = PaddingStrategy::Zeros.pad(trace(6));
+    assert_eq!(padded.height(), 8);
+    assert_eq!(padded.row_slice(7).to_vec(), vec![Val::zero(); 2]);
+    assert_eq!(padded.row_slice(5).to_vec(), trace(6).row_slice(5).to_vec());
+
+    assert_eq!(PaddingStrategy::None.pad(trace(6)).height(), 6);
+    assert_eq!(PaddingStrategy::Zeros.pad(trace(0)).height(), 0);
+
+    // Power-of-two traces are returned without reallocating
+    let unpadded = trace(8);
+    let ptr = unpadded.values.as_ptr();
+    let padded = PaddingStrategy::RepeatLastRow.pad(unpadded);
+    assert_eq!(padded.values.as_ptr(), ptr);
+    assert_eq!(padded, trace(8));
+}
+
+#[test]
+fn test_load_main_with_padding() {
+    let (config, _) = default_config();
+    let chips = vec![
+        MockChip::new("Short", trace(6)),
+        MockChip::new("Empty", trace(0)),
+    ];
+    let main_traces = || vec![Some(trace(6)), Some(trace(0))];
+
+    let mut machine_trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    let result =
+        machine_trace.load_main_single_segment(config.pcs(), main_traces(), PaddingStrategy::None);
+    assert!(matches!(
+        result,
+        Err(MachineError::UnsupportedTraceHeight { chip: 0, height: 6 })
+    ));
+
+    machine_trace
+        .load_main_single_segment(config.pcs(), main_traces(), PaddingStrategy::RepeatLastRow)
+        .unwrap();
+    let main = &machine_trace[0].main[0];
+    assert_eq!(main.trace.value.height(), 8);
+    assert!(machine_trace[1].main.is_empty());
+}
+
+/// A constraint that isn't gated by the `is_real` column fails on the padding rows.
+#[test]
+fn test_ungated_constraint_on_padding_row() {
+    let (config, _) = default_config();
+    // The first column is one on the six real rows, and the second is `is_real`
+    let values = [[Val::one(); 2]; 6]
+        .into_iter()
+        .chain([[Val::zero(); 2]; 2])
+        .flatten()
+        .collect();
+    let chips = vec![MockChip::new("Padded", RowMajorMatrix::new(values, 2))
+        .with_is_real_column(1)
+        .with_constraint(MockConstraint::EveryRow(VirtualPairCol::new_main(
+            vec![(0, Val::one())],
+            -Val::one(),
+        )))];
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    trace
+        .load_main_single_segment(
+            config.pcs(),
+            vec![Some(chips[0].trace.clone())],
+            PaddingStrategy::None,
+        )
+        .unwrap();
+    let result = trace.try_check_constraints([Challenge::zero(); 2], &[]);
+    assert_eq!(
+        result,
+        Err(ChipConstraintError {
+            chip: "Padded".to_string(),
+            error: ConstraintError {
+                row: 6,
+                constraint: 0,
+                kind: ConstraintKind::EveryRow,
+            },
+            padding: true,
+        })
+    );
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::{generate_permutation_trace, InteractionAir};
+use p3_machine::trace::{MachineTrace, MachineTraceBuilder, MachineTraceLoader, PaddingStrategy};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::mock::MockChip;
+use common::{default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// Traces are loaded in parallel across chips, but opening indices are still assigned in chip
+/// order, skipping chips without a trace, and the permutation traces match those generated one
+/// chip at a time.
+#[test]
+fn test_parallel_trace_loading() {
+    let (config, _) = default_config();
+    let pcs = config.pcs();
+    let mut rng = thread_rng();
+
+    let values: Vec<Val> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    let chips = vec![
+        MockChip::new("Sender", RowMajorMatrix::new_col(values)).with_send(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Empty", RowMajorMatrix::new(vec![], 1)),
+        MockChip::new(
+            "Register",
+            RowMajorMatrix::new_col(vec![Val::one(); HEIGHT]),
+        ),
+        MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+    ];
+    let main_traces = chips
+        .iter()
+        .map(|chip| Some(chip.trace.clone()))
+        .collect::<Vec<_>>();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    trace
+        .load_main_single_segment(pcs, main_traces, PaddingStrategy::None)
+        .unwrap();
+    let opening_indices = trace
+        .iter()
+        .map(|chip_trace| chip_trace.main.first().map(|main| main.opening_index))
+        .collect::<Vec<_>>();
+    assert_eq!(opening_indices, vec![Some(0), None, Some(1), Some(2)]);
+
+    let perm_challenges: [Challenge; 2] = [rng.gen(), rng.gen()];
+    trace
+        .generate_permutation(pcs, &[], perm_challenges, &[])
+        .unwrap();
+    let opening_indices = trace
+        .iter()
+        .map(|chip_trace| {
+            chip_trace
+                .permutation
+                .as_ref()
+                .map(|permutation| permutation.opening_index)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(opening_indices, vec![Some(0), None, None, Some(1)]);
+
+    for (chip, chip_trace) in chips.iter().zip(&trace) {
+        let main = chip_trace
+            .main
+            .first()
+            .map(|main| main.trace.value.as_view());
+        let expected = generate_permutation_trace(
+            &None,
+            &main,
+            &[],
+            &InteractionAir::<Val>::all_interactions(chip),
+            perm_challenges,
+        );
+        let permutation = chip_trace
+            .permutation
+            .as_ref()
+            .map(|permutation| permutation.trace.value.clone());
+        assert_eq!(permutation, expected);
+    }
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, AirBuilder, BaseAir, VirtualPairCol};
+use p3_air_util::proof::Commitments;
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{
+    BaseInteractionAir, Bus, Interaction, InteractionAir, InteractionAirBuilder, Rap,
+};
+use p3_machine::{
+    chip::Chip,
+    error::{MachineError, VerificationError},
+    machine::Machine,
+    proof::KeyMismatch,
+    trace::{MachineTrace, MachineTraceBuilder, MachineTraceLoader},
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::thread_rng;
+
+use common::{challenger, default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+enum TestBus {
+    Values,
+}
+
+impl From<usize> for TestBus {
+    fn from(value: usize) -> Self {
+        match value {
+            0 => Self::Values,
+            _ => panic!("Invalid bus index {}", value),
+        }
+    }
+}
+
+impl Display for TestBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "Values")
+    }
+}
+
+impl Bus for TestBus {
+    fn index(&self) -> usize {
+        0
+    }
+}
+
+/// `Sender` sends its column to `Receiver`, while `Register` has no interactions and hence no
+/// permutation trace. In canonical order `Register` sits between the two interacting chips.
+#[derive(Clone, Debug)]
+enum TestChip {
+    Sender,
+    Register,
+    Receiver,
+}
+
+impl Display for TestChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::Sender => write!(f, "Sender"),
+            Self::Register => write!(f, "Register"),
+            Self::Receiver => write!(f, "Receiver"),
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for TestChip {
+    fn width(&self) -> usize {
+        match self {
+            Self::Register => 2,
+            Self::Sender | Self::Receiver => 1,
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TestChip {
+    fn eval(&self, builder: &mut AB) {
+        if let Self::Register = self {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            builder.assert_eq(local[1], local[0] + AB::F::one());
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for TestChip {
+    fn receives_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            Self::Receiver => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::one(),
+                TestBus::Values,
+            )],
+            _ => vec![],
+        }
+    }
+
+    fn sends_from_indices(
+        &self,
+        _preprocessed_indices: &[usize],
+        main_indices: &[usize],
+    ) -> Vec<Interaction<F>> {
+        match self {
+            Self::Sender => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(main_indices[0])],
+                VirtualPairCol::one(),
+                TestBus::Values,
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for TestChip {
+    fn receives(&self) -> Vec<Interaction<F>> {
+        let main_indices = (0..BaseAir::<F>::width(self)).collect::<Vec<_>>();
+        self.receives_from_main_indices(&main_indices)
+    }
+
+    fn sends(&self) -> Vec<Interaction<F>> {
+        let main_indices = (0..BaseAir::<F>::width(self)).collect::<Vec<_>>();
+        self.sends_from_main_indices(&main_indices)
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for TestChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for TestChip {
+    fn main_headers(&self) -> Vec<String> {
+        (0..BaseAir::<Val>::width(self))
+            .map(|i| format!("col_{}", i))
+            .collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        (0..BaseAir::<Val>::width(self))
+            .map(|i| (format!("col_{}", i), "Field".to_string(), i..i + 1))
+            .collect()
+    }
+}
+
+impl Chip for TestChip {}
+
+struct TestMachine;
+
+impl Machine for TestMachine {
+    type Chip = TestChip;
+
+    type Bus = TestBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![TestChip::Sender, TestChip::Register, TestChip::Receiver]
+    }
+}
+
+/// Main traces in the order of `TestMachine::chips`.
+fn main_traces() -> Vec<Option<RowMajorMatrix<Val>>> {
+    let mut rng = thread_rng();
+    let sender = RowMajorMatrix::<Val>::rand(&mut rng, HEIGHT, 1);
+    let mut received = sender.values.clone();
+    received.reverse();
+    let receiver = RowMajorMatrix::new(received, 1);
+    let register = RowMajorMatrix::new(
+        RowMajorMatrix::<Val>::rand(&mut rng, HEIGHT, 1)
+            .values
+            .into_iter()
+            .flat_map(|value| [value, value + Val::one()])
+            .collect(),
+        2,
+    );
+    vec![Some(sender), Some(register), Some(receiver)]
+}
+
+#[test]
+fn test_mixed_permutation_commitment() {
+    let (config, perm) = default_config();
+    let machine = TestMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+
+    // Chips are in canonical order: Receiver, Register, Sender
+    let has_permutation = proof
+        .chip_proofs
+        .iter()
+        .map(|chip_proof| {
+            chip_proof
+                .as_ref()
+                .unwrap()
+                .opened_values
+                .permutation
+                .is_some()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(has_permutation, vec![true, false, true]);
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_missing_permutation_opening() {
+    let (config, perm) = default_config();
+    let machine = TestMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+    proof.chip_proofs[2]
+        .as_mut()
+        .unwrap()
+        .opened_values
+        .permutation = None;
+
+    let result = machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch { chip: Some(2), .. })
+    ));
+}
+
+/// Dropping a commitment drops its round from the opening proof, which must not go unnoticed.
+#[test]
+fn test_missing_round_commitment() {
+    let (config, perm) = default_config();
+    let machine = TestMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let strip_main: fn(&mut Commitments<_>) = |commitments| commitments.main = None;
+    let strip_quotient: fn(&mut Commitments<_>) = |commitments| commitments.quotient_chunks = None;
+    for (strip, expected) in [
+        (
+            strip_main,
+            "main commitment doesn't match the main openings",
+        ),
+        (
+            strip_quotient,
+            "quotient commitment doesn't match the quotient openings",
+        ),
+    ] {
+        let mut proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+        strip(&mut proof.commitments);
+
+        let result = machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[]);
+        assert!(matches!(
+            result,
+            Err(VerificationError::ShapeMismatch { chip: None, detail }) if detail == expected
+        ));
+    }
+}
+
+#[test]
+fn test_unsupported_trace_height() {
+    let (config, perm) = default_config();
+    let machine = TestMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+    proof.chip_proofs[1].as_mut().unwrap().degree = HEIGHT + 1;
+
+    let result = machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::Machine(
+            MachineError::UnsupportedTraceHeight {
+                chip: 1,
+                height
+            }
+        )) if height == HEIGHT + 1
+    ));
+}
+
+#[test]
+fn test_permutation_width_mismatch() {
+    let (config, _) = default_config();
+    let chips = TestMachine.chips();
+    let width = InteractionAir::<Val>::permutation_width(&TestChip::Sender).unwrap();
+
+    // `Sender` gets a column too many
+    let mut trace: MachineTrace<MyConfig, TestChip> = MachineTraceBuilder::new(&chips);
+    let result = trace.load_permutation(
+        config.pcs(),
+        vec![
+            Some(RowMajorMatrix::new(
+                vec![Challenge::zero(); HEIGHT * (width + 1)],
+                width + 1,
+            )),
+            None,
+            Some(RowMajorMatrix::new(
+                vec![Challenge::zero(); HEIGHT * width],
+                width,
+            )),
+        ],
+    );
+    assert_eq!(
+        result,
+        Err(MachineError::PermutationWidth {
+            chip: 0,
+            expected: width,
+            actual: width + 1
+        })
+    );
+}
+
+#[test]
+fn test_audit() {
+    let report = TestMachine.audit::<Val>();
+    assert!(report.is_ok());
+
+    let chips = report
+        .chips
+        .iter()
+        .map(|chip| chip.chip.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(chips, vec!["Receiver", "Register", "Sender"]);
+    assert!(report.chips[1].num_constraints > 0);
+
+    assert_eq!(report.buses.len(), 1);
+    assert_eq!(report.buses[0].num_sends, 1);
+    assert_eq!(report.buses[0].num_receives, 1);
+}
+
+#[test]
+fn test_verifying_key_compatibility() {
+    let (config, _) = default_config();
+    let machine = TestMachine;
+
+    let (_, vk) = machine.setup(&config);
+    let (_, other_vk) = machine.setup(&config);
+    assert!(vk.is_compatible(&other_vk));
+
+    let mut other_vk = other_vk;
+    other_vk.chips.reverse();
+    assert!(!vk.is_compatible(&other_vk));
+    assert_eq!(vk.compatibility_report(&other_vk), vec![KeyMismatch::Chips]);
+}
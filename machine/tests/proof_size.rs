@@ -0,0 +1,56 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{machine::Machine, size::serialized_size};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, Val};
+
+const HEIGHT: usize = 8;
+
+#[test]
+fn test_size_estimate() {
+    let (config, perm) = default_config();
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let machine = MockMachine {
+        chips: vec![
+            MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(values)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    };
+
+    let (pk, _) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    let size = proof.size_estimate();
+
+    assert_eq!(size.chips.len(), 2);
+    for chip in size.chips.iter() {
+        let chip = chip.expect("Both chips have traces");
+        // An absent preprocessed trace only takes its tag
+        assert_eq!(chip.preprocessed, 1);
+        assert!(chip.main > 1 && chip.permutation > 1 && chip.quotient > 1);
+    }
+    // Both chips open a single main column at the same points
+    assert_eq!(size.chips[0].unwrap().main, size.chips[1].unwrap().main);
+    assert!(size.opening_proof > size.commitments);
+
+    // The format version, the number of chips and a tag per chip aren't attributed to any part
+    let overhead = 4 + 8 + size.chips.len();
+    assert_eq!(serialized_size(&proof), size.total() + overhead);
+}
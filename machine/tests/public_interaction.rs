@@ -0,0 +1,129 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_air_util::debug::rap::check_lookups;
+use p3_field::AbstractField;
+use p3_interaction::Interaction;
+use p3_machine::{error::VerificationError, machine::Machine};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockBus, MockChip, MockMachine};
+use common::{challenger, default_config, Val};
+
+const HEIGHT: usize = 8;
+const SELECTOR: u32 = 7;
+
+/// `Sender` sends its column keyed by the first public value, and `Receiver` receives its first
+/// column keyed by its second column, which holds the expected selector.
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut sender = MockChip::new("Sender", RowMajorMatrix::new_col(values.clone()));
+    sender.sends.push(
+        Interaction::new(
+            vec![VirtualPairCol::single_main(0)],
+            VirtualPairCol::one(),
+            MockBus(0),
+        )
+        .with_public_fields(vec![0]),
+    );
+
+    let receiver_values = values
+        .into_iter()
+        .flat_map(|value| [value, Val::from_canonical_u32(SELECTOR)])
+        .collect();
+    let receiver = MockChip::new("Receiver", RowMajorMatrix::new(receiver_values, 2)).with_receive(
+        &[0, 1],
+        VirtualPairCol::one(),
+        0,
+    );
+
+    MockMachine {
+        chips: vec![sender, receiver],
+    }
+}
+
+#[test]
+fn test_prove_public_field() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = [Val::from_canonical_u32(SELECTOR)];
+
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &public_values,
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &public_values)
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_inconsistent_public_values() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = [Val::from_canonical_u32(SELECTOR)];
+
+    let mut proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &public_values,
+    );
+    // `Receiver` comes first in canonical order, and both chips read the selector
+    proof.chip_public_values[1][0] += Val::one();
+    assert!(matches!(
+        machine.verify(&config, &mut challenger(&perm), &vk, &proof, &public_values),
+        Err(VerificationError::InconsistentPublicValues {
+            chips: (0, 1),
+            index: 0
+        })
+    ));
+}
+
+#[test]
+fn test_public_values_mismatch() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = [Val::from_canonical_u32(SELECTOR)];
+
+    let mut proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &public_values,
+    );
+    // The chips agree with each other, but not with the public values
+    for chip_public_values in proof.chip_public_values.iter_mut() {
+        chip_public_values[0] += Val::one();
+    }
+    assert!(matches!(
+        machine.verify(&config, &mut challenger(&perm), &vk, &proof, &public_values),
+        Err(VerificationError::PublicValuesMismatch { chip: 0, index: 0 })
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Bus0 bus has unmatched tuple")]
+fn test_public_field_mismatch() {
+    let machine = machine();
+    let mains = machine
+        .chips
+        .iter()
+        .map(|chip| Some(chip.trace.as_view()))
+        .collect::<Vec<_>>();
+    let public_values = [Val::from_canonical_u32(SELECTOR + 1)];
+    check_lookups::<_, _, MockBus>(
+        &machine.chips,
+        &[None, None],
+        &mains,
+        &[&public_values, &public_values],
+    );
+}
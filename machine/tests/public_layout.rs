@@ -0,0 +1,119 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_interaction::Interaction;
+use p3_machine::error::PublicValueLayoutError;
+use p3_machine::machine::Machine;
+use p3_machine::public::PublicValueLayout;
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockBus, MockChip, MockMachine};
+use common::{challenger, default_config, Val};
+
+const HEIGHT: usize = 8;
+const SELECTOR: u32 = 7;
+
+/// `Sender` sends its column keyed by the second public value, which is the first one in its
+/// range, and `Receiver` receives its first column keyed by the selector in its second column.
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut sender = MockChip::new("Sender", RowMajorMatrix::new_col(values.clone()))
+        .with_public_value_range(1..2);
+    sender.sends.push(
+        Interaction::new(
+            vec![VirtualPairCol::single_main(0)],
+            VirtualPairCol::one(),
+            MockBus(0),
+        )
+        .with_public_fields(vec![0]),
+    );
+
+    let receiver_values = values
+        .into_iter()
+        .flat_map(|value| [value, Val::from_canonical_u32(SELECTOR)])
+        .collect();
+    let receiver = MockChip::new("Receiver", RowMajorMatrix::new(receiver_values, 2))
+        .with_public_value_range(0..1)
+        .with_receive(&[0, 1], VirtualPairCol::one(), 0);
+
+    MockMachine {
+        chips: vec![sender, receiver],
+    }
+}
+
+#[test]
+fn test_prover_and_verifier_share_layout() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = [Val::zero(), Val::from_canonical_u32(SELECTOR)];
+
+    // Chips are laid out in canonical order, i.e. by name
+    let layout = machine.public_value_layout(&public_values).unwrap();
+    assert_eq!(layout.public_values_for(0), &public_values[0..1]);
+    assert_eq!(layout.public_values_for(1), &public_values[1..2]);
+
+    // The sender only balances the bus if it's evaluated against its own slice
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &public_values,
+    );
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &public_values)
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_layout_out_of_bounds() {
+    let machine = machine();
+    let public_values = [Val::zero()];
+    assert_eq!(
+        machine.public_value_layout(&public_values).unwrap_err(),
+        PublicValueLayoutError::OutOfBounds { chip: 1 }
+    );
+}
+
+#[test]
+fn test_layout_builder() {
+    let public_values = [1, 2, 3, 4];
+
+    let layout = PublicValueLayout::builder()
+        .with_chip(2..4)
+        .with_chip(0..0)
+        .with_chip(0..2)
+        .build(&public_values)
+        .unwrap();
+    assert_eq!(layout.public_values_for(0), &[3, 4]);
+    assert_eq!(layout.public_values_for(1), &[] as &[i32]);
+    assert_eq!(layout.public_values_for(2), &[1, 2]);
+
+    let overlap = PublicValueLayout::builder()
+        .with_chip(0..3)
+        .with_chip(2..4)
+        .build(&public_values);
+    assert_eq!(
+        overlap.unwrap_err(),
+        PublicValueLayoutError::Overlap {
+            chips: (0, 1),
+            index: 2
+        }
+    );
+
+    let gap = PublicValueLayout::builder()
+        .with_chip(0..1)
+        .with_chip(2..4)
+        .build(&public_values);
+    assert_eq!(gap.unwrap_err(), PublicValueLayoutError::Gap { index: 1 });
+
+    let uncovered = PublicValueLayout::builder()
+        .with_chip(0..3)
+        .build(&public_values);
+    assert_eq!(
+        uncovered.unwrap_err(),
+        PublicValueLayoutError::Gap { index: 3 }
+    );
+}
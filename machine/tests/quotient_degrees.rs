@@ -0,0 +1,37 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::machine::{chip_constraint_degree, chip_quotient_degrees};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockConstraint};
+use common::Val;
+
+/// Constraint and quotient degrees only depend on the constraints, so the chips' traces are left
+/// empty.
+#[test]
+fn test_chip_degrees() {
+    // A linear constraint is padded to degree 2, so the quotient is a single chunk
+    let register = MockChip::new("Register", RowMajorMatrix::new(vec![], 2)).with_constraint(
+        MockConstraint::EveryRow(VirtualPairCol::new_main(
+            vec![(0, Val::one()), (1, -Val::one())],
+            Val::zero(),
+        )),
+    );
+    // The running sum is multiplied by the count column and gated on transition rows, so the
+    // permutation constraints have degree 3
+    let sender = MockChip::new("Sender", RowMajorMatrix::new(vec![], 2)).with_send(
+        &[0],
+        VirtualPairCol::single_main(1),
+        0,
+    );
+
+    assert_eq!(chip_constraint_degree::<Val, _>(&register, 0), 1);
+    assert_eq!(chip_constraint_degree::<Val, _>(&sender, 0), 3);
+    assert_eq!(
+        chip_quotient_degrees::<Val, _>(&[&register, &sender], 0),
+        vec![1, 2]
+    );
+    assert!(chip_quotient_degrees::<Val, MockChip>(&[], 0).is_empty());
+}
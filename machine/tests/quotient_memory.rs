@@ -0,0 +1,175 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::get_quotient_degree;
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::{
+    chip::Chip,
+    trace::{
+        MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceLoader,
+        PaddingStrategy,
+    },
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::{config_with_log_blowup, Challenge, MyConfig, Val};
+
+const LOG_HEIGHT: usize = 10;
+const NUM_PAIRS: usize = 32;
+
+/// Tracks the current and peak number of bytes allocated on the heap.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of bytes allocated by `f` on top of what was allocated before it ran, at
+/// its peak.
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - before)
+}
+
+/// Constrains every odd column to be the fifth power of the column before it, so that the
+/// quotient is evaluated over a domain four times the size of the trace.
+#[derive(Clone, Debug)]
+struct PowerChip;
+
+impl Display for PowerChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "Power")
+    }
+}
+
+impl<F: Field> BaseAir<F> for PowerChip {
+    fn width(&self) -> usize {
+        2 * NUM_PAIRS
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for PowerChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        for pair in local.chunks_exact(2) {
+            let x: AB::Expr = pair[0].into();
+            builder.assert_eq(pair[1], x.square().square() * x);
+        }
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for PowerChip {}
+
+impl<F: Field> InteractionAir<F> for PowerChip {}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for PowerChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for PowerChip {
+    fn main_headers(&self) -> Vec<String> {
+        (0..BaseAir::<Val>::width(self))
+            .map(|i| format!("col_{}", i))
+            .collect()
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        (0..BaseAir::<Val>::width(self))
+            .map(|i| (format!("col_{}", i), "Field".to_string(), i..i + 1))
+            .collect()
+    }
+}
+
+impl Chip for PowerChip {}
+
+/// The quotient is evaluated from the committed low-degree extensions in place, so it allocates
+/// less than a single copy of the main trace over the quotient domain.
+#[test]
+fn test_quotient_peak_allocation() {
+    let (config, _) = config_with_log_blowup(2);
+    let pcs = config.pcs();
+    let mut rng = thread_rng();
+
+    let chip = PowerChip;
+    let quotient_degree = get_quotient_degree::<Val, _>(&chip, 0);
+    assert_eq!(quotient_degree, 4);
+
+    let main = RowMajorMatrix::new(
+        (0..(1 << LOG_HEIGHT) * NUM_PAIRS)
+            .flat_map(|_| {
+                let x = rng.gen::<Val>();
+                [x, x.exp_u64(5)]
+            })
+            .collect(),
+        2 * NUM_PAIRS,
+    );
+    let main_on_quotient_domain_bytes =
+        main.values.len() * quotient_degree * core::mem::size_of::<Val>();
+
+    let chips = vec![chip];
+    let mut trace: MachineTrace<MyConfig, PowerChip> = MachineTraceBuilder::new(&chips);
+    trace
+        .load_main_single_segment(pcs, vec![Some(main)], PaddingStrategy::None)
+        .unwrap();
+    let (_, main_data) = trace.commit_main(pcs);
+
+    let alpha = rng.gen::<Challenge>();
+    let perm_challenges = [rng.gen::<Challenge>(), rng.gen::<Challenge>()];
+    let (batch, peak) = peak_allocation(|| {
+        trace.evaluate_quotients(
+            pcs,
+            &None,
+            &main_data,
+            &None,
+            &[],
+            perm_challenges,
+            alpha,
+            &[],
+        )
+    });
+
+    assert_eq!(batch.len(), 1);
+    let evaluation = batch[0].as_ref().unwrap();
+    assert_eq!(
+        evaluation.values.height(),
+        (1 << LOG_HEIGHT) * quotient_degree
+    );
+    assert!(
+        peak < main_on_quotient_domain_bytes,
+        "Evaluating the quotient allocated {} bytes, but the main trace over the quotient domain \
+         is only {} bytes",
+        peak,
+        main_on_quotient_domain_bytes
+    );
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::Field;
+use p3_interaction::{BaseInteractionAir, Bus, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::{chip::Chip, error::VerificationError, machine::Machine, trace::rotate_point};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::{challenger, default_config, Challenge, Challenger, MyPcs, Val};
+
+const HEIGHT: usize = 8;
+const PERIOD: usize = 4;
+
+struct EmptyBus;
+
+impl From<usize> for EmptyBus {
+    fn from(_value: usize) -> Self {
+        panic!("Periodic machine has no buses")
+    }
+}
+
+impl Display for EmptyBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EmptyBus")
+    }
+}
+
+impl Bus for EmptyBus {
+    fn index(&self) -> usize {
+        unreachable!()
+    }
+}
+
+/// Constrains its column to repeat every `PERIOD` rows, which needs the main trace opened at a
+/// rotation beyond the next row.
+#[derive(Clone, Debug)]
+struct PeriodicChip;
+
+impl Display for PeriodicChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "Periodic")
+    }
+}
+
+impl<F: Field> BaseAir<F> for PeriodicChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for PeriodicChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        // The row at the first rotation
+        let rotated = main.row_slice(2);
+        builder.assert_eq(rotated[0], local[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for PeriodicChip {}
+
+impl<F: Field> InteractionAir<F> for PeriodicChip {
+    fn main_rotations(&self) -> Vec<usize> {
+        vec![PERIOD]
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for PeriodicChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for PeriodicChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["value".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("value".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for PeriodicChip {}
+
+struct PeriodicMachine;
+
+impl Machine for PeriodicMachine {
+    type Chip = PeriodicChip;
+
+    type Bus = EmptyBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![PeriodicChip]
+    }
+}
+
+fn main_traces() -> Vec<Option<RowMajorMatrix<Val>>> {
+    let period = RowMajorMatrix::<Val>::rand(&mut thread_rng(), PERIOD, 1);
+    let values = period.values.repeat(HEIGHT / PERIOD);
+    vec![Some(RowMajorMatrix::new(values, 1))]
+}
+
+#[test]
+fn test_rotation_openings() {
+    let (config, perm) = default_config();
+    let machine = PeriodicMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+
+    let main = proof.chip_proofs[0]
+        .as_ref()
+        .unwrap()
+        .opened_values
+        .main
+        .as_ref()
+        .unwrap();
+    assert_eq!(main.rotations.len(), 1);
+    assert_eq!(main.rotations[0].len(), 1);
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
+
+#[test]
+fn test_missing_rotation_opening() {
+    let (config, perm) = default_config();
+    let machine = PeriodicMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = machine.prove(&config, &mut challenger(&perm), &pk, main_traces(), &[]);
+    proof.chip_proofs[0]
+        .as_mut()
+        .unwrap()
+        .opened_values
+        .main
+        .as_mut()
+        .unwrap()
+        .rotations
+        .clear();
+
+    let result = machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch { chip: Some(0), .. })
+    ));
+}
+
+#[test]
+fn test_rotate_point() {
+    let (config, _) = default_config();
+    let domain =
+        <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(config.pcs(), HEIGHT);
+    let zeta: Challenge = thread_rng().gen();
+
+    assert_eq!(rotate_point(domain, zeta, 0), zeta);
+    assert_eq!(
+        rotate_point(domain, zeta, 1),
+        domain.next_point(zeta).unwrap()
+    );
+    assert_eq!(
+        rotate_point(domain, rotate_point(domain, zeta, 1), PERIOD - 1),
+        rotate_point(domain, zeta, PERIOD)
+    );
+    // Rotating by the size of the domain wraps around
+    assert_eq!(rotate_point(domain, zeta, HEIGHT), zeta);
+}
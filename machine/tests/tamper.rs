@@ -0,0 +1,205 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{
+    error::VerificationError,
+    machine::Machine,
+    proof::{MachineProof, FORMAT_VERSION},
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockConstraint, MockMachine};
+use common::{challenger, default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+/// A counter sending `0, 1, ..., HEIGHT - 1` to a chip receiving them in reverse.
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let mut received = values.clone();
+    received.reverse();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Counter", RowMajorMatrix::new_col(values))
+                .with_constraint(MockConstraint::FirstRow(VirtualPairCol::single_main(0)))
+                .with_constraint(MockConstraint::Transition {
+                    local: VirtualPairCol::new_main(vec![(0, Val::one())], Val::one()),
+                    next: VirtualPairCol::single_main(0),
+                })
+                .with_send(&[0], VirtualPairCol::one(), 0),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(received)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+/// Proves the machine, tampers with the proof and returns the result of verifying it.
+fn verify_tampered(
+    tamper: impl FnOnce(&mut MachineProof<MyConfig>),
+) -> Result<(), VerificationError> {
+    let (config, perm) = default_config();
+    let machine = machine();
+
+    let (pk, vk) = machine.setup(&config);
+    let mut proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    tamper(&mut proof);
+
+    machine.verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+}
+
+#[test]
+fn test_untampered_proof() {
+    verify_tampered(|_| {}).expect("Proof should verify");
+}
+
+/// A different commitment changes the challenges, so the openings no longer match.
+#[test]
+fn test_tampered_commitment() {
+    let result = verify_tampered(|proof| {
+        let mut digest: [Val; 8] = proof.commitments.main.unwrap().into();
+        digest[0] += Val::one();
+        proof.commitments.main = Some(digest.into());
+    });
+    assert!(matches!(result, Err(VerificationError::CommitmentMismatch)));
+}
+
+/// Rounds without a commitment aren't covered by the opening proof, so a proof without any
+/// commitments must not carry openings.
+#[test]
+fn test_stripped_commitments() {
+    let result = verify_tampered(|proof| {
+        proof.commitments.main = None;
+        proof.commitments.permutation = None;
+        proof.commitments.quotient_chunks = None;
+        proof.opening_proof = None;
+    });
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch { chip: None, .. })
+    ));
+}
+
+/// Opened values are checked against the commitments by the opening proof.
+#[test]
+fn test_tampered_opened_value() {
+    let result = verify_tampered(|proof| {
+        let main = proof.chip_proofs[0]
+            .as_mut()
+            .unwrap()
+            .opened_values
+            .main
+            .as_mut()
+            .unwrap();
+        main.local[0] += Challenge::one();
+    });
+    assert!(matches!(result, Err(VerificationError::CommitmentMismatch)));
+}
+
+/// Quotient chunks are opened like any other trace.
+#[test]
+fn test_tampered_quotient_chunk() {
+    let result = verify_tampered(|proof| {
+        let quotient_chunks = proof.chip_proofs[1]
+            .as_mut()
+            .unwrap()
+            .opened_values
+            .quotient_chunks
+            .as_mut()
+            .unwrap();
+        quotient_chunks[0][0] += Challenge::one();
+    });
+    assert!(matches!(result, Err(VerificationError::CommitmentMismatch)));
+}
+
+/// Omitting the quotient openings is an error rather than a panic.
+#[test]
+fn test_missing_quotient() {
+    let result = verify_tampered(|proof| {
+        proof.chip_proofs[1]
+            .as_mut()
+            .unwrap()
+            .opened_values
+            .quotient_chunks = None;
+    });
+    assert!(matches!(
+        result,
+        Err(VerificationError::MissingQuotient { chip: 1 })
+    ));
+}
+
+#[test]
+fn test_extra_quotient_chunk() {
+    let result = verify_tampered(|proof| {
+        let quotient_chunks = proof.chip_proofs[1]
+            .as_mut()
+            .unwrap()
+            .opened_values
+            .quotient_chunks
+            .as_mut()
+            .unwrap();
+        quotient_chunks.push(quotient_chunks[0].clone());
+    });
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch {
+            chip: Some(1),
+            detail: "quotient chunk count mismatch"
+        })
+    ));
+}
+
+/// The cumulative sum isn't committed to, but the last row of the permutation trace is constrained
+/// to equal it.
+#[test]
+fn test_tampered_cumulative_sum() {
+    let result = verify_tampered(|proof| {
+        let chip_proof = proof.chip_proofs[1].as_mut().unwrap();
+        chip_proof.cumulative_sum = chip_proof
+            .cumulative_sum
+            .map(|cumulative_sum| cumulative_sum + Challenge::one());
+    });
+    assert!(matches!(
+        result,
+        Err(VerificationError::ConstraintMismatch { chip: 1 })
+    ));
+}
+
+/// A missing opening is caught before anything is checked against the commitments.
+#[test]
+fn test_tampered_opening_shape() {
+    let result = verify_tampered(|proof| {
+        let main = proof.chip_proofs[0]
+            .as_mut()
+            .unwrap()
+            .opened_values
+            .main
+            .as_mut()
+            .unwrap();
+        main.next.pop();
+    });
+    assert!(matches!(
+        result,
+        Err(VerificationError::ShapeMismatch { chip: Some(0), .. })
+    ));
+}
+
+/// Proofs of another format version are rejected before anything else is checked.
+#[test]
+fn test_tampered_format_version() {
+    let result = verify_tampered(|proof| proof.format_version += 1);
+    assert!(matches!(
+        result,
+        Err(VerificationError::UnsupportedFormatVersion { found, expected })
+            if found == FORMAT_VERSION + 1 && expected == FORMAT_VERSION
+    ));
+}
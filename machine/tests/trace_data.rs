@@ -0,0 +1,53 @@
+mod common;
+
+use p3_commit::PolynomialSpace;
+use p3_field::AbstractExtensionField;
+use p3_machine::trace::TraceData;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::thread_rng;
+
+use common::{default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+#[test]
+fn test_trace_data_round_trip() {
+    let (config, _) = default_config();
+    let data = TraceData::new(RowMajorMatrix::<Val>::rand(&mut thread_rng(), HEIGHT, 3));
+
+    let json = serde_json::to_string(&data).unwrap();
+    let decoded: TraceData<Val> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, data);
+
+    let trace = decoded.to_trace::<MyConfig>(config.pcs());
+    assert_eq!(trace.domain.size(), HEIGHT);
+    assert_eq!(trace.value, data.value);
+    assert_eq!(TraceData::from(&trace), data);
+}
+
+/// Extension traces, like the permutation traces, are cached in their flattened form.
+#[test]
+fn test_flattened_trace_data_round_trip() {
+    let (config, _) = default_config();
+    let data = TraceData::new(RowMajorMatrix::<Challenge>::rand(
+        &mut thread_rng(),
+        HEIGHT,
+        2,
+    ));
+    let flat = data
+        .clone()
+        .to_trace::<MyConfig>(config.pcs())
+        .flatten_to_base();
+    let flat_data = TraceData::from(&flat);
+    assert_eq!(
+        flat_data.value.width(),
+        2 * <Challenge as AbstractExtensionField<Val>>::D
+    );
+    assert_eq!(flat_data.degree, HEIGHT);
+
+    let json = serde_json::to_string(&flat_data).unwrap();
+    let decoded: TraceData<Val> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, flat_data);
+}
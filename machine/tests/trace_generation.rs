@@ -0,0 +1,47 @@
+mod common;
+
+use p3_field::AbstractField;
+use p3_machine::{
+    error::{MachineError, TraceGenError},
+    machine::Machine,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{default_config, Val};
+
+const ERROR: TraceGenError = TraceGenError::InconsistentWitness {
+    row: Some(3),
+    detail: "value isn't in the table",
+};
+
+fn machine(error: Option<TraceGenError>) -> MockMachine {
+    let trace = || RowMajorMatrix::new_col(vec![Val::zero(); 4]);
+    let mut failing = MockChip::new("Failing", trace());
+    if let Some(error) = error {
+        failing = failing.with_trace_gen_error(error);
+    }
+    MockMachine {
+        chips: vec![MockChip::new("Working", trace()), failing],
+    }
+}
+
+#[test]
+fn test_try_setup() {
+    let (config, _) = default_config();
+    assert!(machine(None).try_setup(&config).is_ok());
+
+    // Errors are attributed to the chip's canonical index
+    let result = machine(Some(ERROR)).try_setup(&config);
+    assert!(matches!(
+        result,
+        Err(MachineError::TraceGeneration { chip: 0, error }) if error == ERROR
+    ));
+}
+
+#[test]
+#[should_panic(expected = "chip 0 failed to generate its trace: inconsistent witness on row 3")]
+fn test_setup_panics() {
+    let (config, _) = default_config();
+    machine(Some(ERROR)).setup(&config);
+}
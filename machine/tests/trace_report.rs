@@ -0,0 +1,97 @@
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractExtensionField, AbstractField};
+use p3_machine::{
+    report::TraceDimensions,
+    trace::{
+        MachineTrace, MachineTraceBuilder, MachineTraceCommiter, MachineTraceLoader,
+        MachineTraceReporter, PaddingStrategy,
+    },
+};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::StarkGenericConfig;
+use rand::{thread_rng, Rng};
+
+use common::mock::MockChip;
+use common::{default_config, Challenge, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+#[test]
+fn test_trace_report() {
+    let (config, _) = default_config();
+    let pcs = config.pcs();
+    let mut rng = thread_rng();
+
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    let chips = vec![
+        MockChip::new("Counter", RowMajorMatrix::new_col(values)).with_send(
+            &[0],
+            VirtualPairCol::one(),
+            0,
+        ),
+        MockChip::new("Idle", RowMajorMatrix::new(vec![], 2)),
+    ];
+    let main_traces = chips.iter().map(|chip| Some(chip.trace.clone())).collect();
+
+    let mut trace: MachineTrace<MyConfig, MockChip> = MachineTraceBuilder::new(&chips);
+    trace
+        .load_main_single_segment(pcs, main_traces, PaddingStrategy::None)
+        .unwrap();
+    let (_, main_data) = trace.commit_main(pcs);
+    let perm_challenges: [Challenge; 2] = [rng.gen(), rng.gen()];
+    trace
+        .generate_permutation(pcs, &[], perm_challenges, &[])
+        .unwrap();
+    let (_, permutation_data) = trace.commit_permutation(pcs);
+    trace.generate_quotient(
+        pcs,
+        &None,
+        &main_data,
+        &permutation_data,
+        &[],
+        perm_challenges,
+        rng.gen(),
+        &[],
+    );
+
+    let report = trace.report();
+    let counter = &report.chips[0];
+    assert_eq!(counter.chip, "Counter");
+    assert_eq!(counter.preprocessed, None);
+    assert_eq!(
+        counter.main,
+        Some(TraceDimensions {
+            height: HEIGHT,
+            width: 1
+        })
+    );
+    assert_eq!(counter.permutation.unwrap().height, HEIGHT);
+    // Each chunk of the quotient has the height of the trace, with an extension element flattened
+    // to its coordinates in each row
+    assert_eq!(
+        counter.quotient_chunk,
+        Some(TraceDimensions {
+            height: HEIGHT,
+            width: <Challenge as AbstractExtensionField<Val>>::D,
+        })
+    );
+    assert!(counter.quotient_degree.is_some());
+    assert!(counter.has_cumulative_sum);
+
+    let idle = &report.chips[1];
+    assert_eq!(idle.chip, "Idle");
+    assert_eq!(
+        (idle.main, idle.permutation, idle.quotient_chunk),
+        (None, None, None)
+    );
+    assert!(!idle.has_cumulative_sum);
+
+    let table = report.to_string();
+    let lines: Vec<_> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("chip    | preprocessed | main"));
+    assert!(lines[1].starts_with("Counter | -            | 8x1"));
+    assert!(lines[2].ends_with("| no"));
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "debug-transcript")]
+
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{
+    channel::{ProverRound, TranscriptChannel, TranscriptEvent},
+    machine::Machine,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(values)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_transcript_logs_match() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+    let public_values = [Val::one()];
+
+    let mut prover_challenger = challenger(&perm);
+    let mut prover_channel = TranscriptChannel::<MyConfig>::new(&mut prover_challenger);
+    let proof = machine.prove_with_channel(
+        &config,
+        &mut prover_channel,
+        &pk,
+        machine.main_traces(),
+        &public_values,
+    );
+
+    let mut verifier_challenger = challenger(&perm);
+    let mut verifier_channel = TranscriptChannel::<MyConfig>::new(&mut verifier_challenger);
+    machine
+        .verify_with_channel(&config, &mut verifier_channel, &vk, &proof, &public_values)
+        .expect("Proof should verify");
+
+    let log = prover_channel.transcript_log();
+    assert_eq!(log, verifier_channel.transcript_log());
+    assert!(
+        matches!(log[0], TranscriptEvent::PublicValues(ref values) if values == &public_values)
+    );
+    assert!(matches!(
+        log.last(),
+        Some(TranscriptEvent::Challenge {
+            round: ProverRound::Quotient,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_transcript_logs_diverge() {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, vk) = machine.setup(&config);
+
+    let mut prover_challenger = challenger(&perm);
+    let mut prover_channel = TranscriptChannel::<MyConfig>::new(&mut prover_challenger);
+    let mut proof = machine.prove_with_channel(
+        &config,
+        &mut prover_channel,
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    proof.commitments.quotient_chunks = proof.commitments.main.clone();
+
+    let mut verifier_challenger = challenger(&perm);
+    let mut verifier_channel = TranscriptChannel::<MyConfig>::new(&mut verifier_challenger);
+    let result = machine.verify_with_channel(&config, &mut verifier_channel, &vk, &proof, &[]);
+    assert!(result.is_err());
+
+    // The logs agree up to the tampered commitment
+    let divergence = prover_channel
+        .transcript_log()
+        .iter()
+        .zip(verifier_channel.transcript_log())
+        .position(|(prover_event, verifier_event)| prover_event != verifier_event);
+    let divergence = divergence.expect("Transcripts should diverge");
+    assert!(matches!(
+        verifier_channel.transcript_log()[divergence],
+        TranscriptEvent::Commitment {
+            round: ProverRound::Quotient,
+            ..
+        }
+    ));
+}
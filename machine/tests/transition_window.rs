@@ -0,0 +1,183 @@
+mod common;
+
+use core::fmt::{Display, Formatter, Result};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air_util::debug::rap::{try_check_constraints, ConstraintError, ConstraintKind};
+use p3_air_util::folders::transition_window_selector;
+#[cfg(feature = "air-logger")]
+use p3_air_util::AirLogger;
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{AbstractField, Field};
+use p3_interaction::{BaseInteractionAir, Bus, InteractionAir, InteractionAirBuilder, Rap};
+use p3_machine::{chip::Chip, machine::Machine, trace::trace_generator_inv};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::StarkGenericConfig;
+
+use common::{challenger, default_config, Challenge, Challenger, MyPcs, Val};
+
+const HEIGHT: usize = 8;
+
+struct EmptyBus;
+
+impl From<usize> for EmptyBus {
+    fn from(_value: usize) -> Self {
+        panic!("Fibonacci machine has no buses")
+    }
+}
+
+impl Display for EmptyBus {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "EmptyBus")
+    }
+}
+
+impl Bus for EmptyBus {
+    fn index(&self) -> usize {
+        unreachable!()
+    }
+}
+
+/// Constrains its column to the Fibonacci sequence, which relates windows of 3 rows.
+#[derive(Clone, Debug)]
+struct FibonacciChip;
+
+impl Display for FibonacciChip {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "Fibonacci")
+    }
+}
+
+impl<F: Field> BaseAir<F> for FibonacciChip {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for FibonacciChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        // The row at the first rotation
+        let next_next = main.row_slice(2);
+        builder
+            .when_transition_window(3)
+            .assert_eq(next_next[0], local[0] + next[0]);
+    }
+}
+
+impl<F: Field> BaseInteractionAir<F> for FibonacciChip {}
+
+impl<F: Field> InteractionAir<F> for FibonacciChip {
+    fn main_rotations(&self) -> Vec<usize> {
+        vec![2]
+    }
+}
+
+impl<AB: InteractionAirBuilder> Rap<AB> for FibonacciChip {}
+
+#[cfg(feature = "air-logger")]
+impl AirLogger for FibonacciChip {
+    fn main_headers(&self) -> Vec<String> {
+        vec!["value".to_string()]
+    }
+
+    #[cfg(feature = "schema")]
+    fn main_headers_and_types(&self) -> Vec<(String, String, core::ops::Range<usize>)> {
+        vec![("value".to_string(), "Field".to_string(), 0..1)]
+    }
+}
+
+impl Chip for FibonacciChip {}
+
+struct FibonacciMachine;
+
+impl Machine for FibonacciMachine {
+    type Chip = FibonacciChip;
+
+    type Bus = EmptyBus;
+
+    fn chips(&self) -> Vec<Self::Chip> {
+        vec![FibonacciChip]
+    }
+}
+
+fn fibonacci_trace() -> RowMajorMatrix<Val> {
+    let mut values = vec![Val::one(), Val::one()];
+    while values.len() < HEIGHT {
+        values.push(values[values.len() - 2] + values[values.len() - 1]);
+    }
+    RowMajorMatrix::new(values, 1)
+}
+
+#[test]
+fn test_transition_window_selector() {
+    let (config, _) = default_config();
+    let domain =
+        <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(config.pcs(), HEIGHT);
+    let g_inv = trace_generator_inv(domain);
+
+    let mut point = domain.first_point();
+    for row in 0..HEIGHT {
+        let is_transition = point - g_inv;
+        for size in 2..=4 {
+            let selector = transition_window_selector(is_transition, g_inv, size);
+            assert_eq!(
+                selector.is_zero(),
+                row + size > HEIGHT,
+                "row {row}, size {size}"
+            );
+        }
+        point = domain.next_point(point).unwrap();
+    }
+}
+
+#[test]
+fn test_check_transition_window() {
+    let check = |trace: RowMajorMatrix<Val>| {
+        try_check_constraints::<Val, Challenge, _>(
+            &FibonacciChip,
+            &None,
+            &Some(trace.as_view()),
+            &None,
+            &None,
+            [Challenge::zero(); 2],
+            None,
+            &[],
+        )
+    };
+    // The last two rows wrap around and are excluded by the window
+    assert_eq!(check(fibonacci_trace()), Ok(()));
+
+    let mut trace = fibonacci_trace();
+    trace.values[HEIGHT - 1] += Val::one();
+    assert_eq!(
+        check(trace),
+        Err(ConstraintError {
+            row: HEIGHT - 3,
+            constraint: 0,
+            kind: ConstraintKind::Transition,
+        })
+    );
+}
+
+#[test]
+fn test_prove_transition_window() {
+    let (config, perm) = default_config();
+    let machine = FibonacciMachine;
+
+    let (pk, vk) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        vec![Some(fibonacci_trace())],
+        &[],
+    );
+
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &proof, &[])
+        .expect("Proof should verify");
+}
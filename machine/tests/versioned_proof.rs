@@ -0,0 +1,98 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use p3_air::VirtualPairCol;
+use p3_field::AbstractField;
+use p3_machine::{
+    error::ProofDecodeError,
+    machine::Machine,
+    proof::{VersionedProof, FORMAT_VERSION, PROOF_MAGIC},
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use common::mock::{MockChip, MockMachine};
+use common::{challenger, default_config, MyConfig, Val};
+
+const HEIGHT: usize = 8;
+
+fn machine() -> MockMachine {
+    let values: Vec<_> = (0..HEIGHT).map(Val::from_canonical_usize).collect();
+    MockMachine {
+        chips: vec![
+            MockChip::new("Sender", RowMajorMatrix::new_col(values.clone())).with_send(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+            MockChip::new("Receiver", RowMajorMatrix::new_col(values)).with_receive(
+                &[0],
+                VirtualPairCol::one(),
+                0,
+            ),
+        ],
+    }
+}
+
+fn encoded_proof() -> Vec<u8> {
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (pk, _) = machine.setup(&config);
+    let proof = machine.prove(
+        &config,
+        &mut challenger(&perm),
+        &pk,
+        machine.main_traces(),
+        &[],
+    );
+    VersionedProof::new(proof).encode()
+}
+
+#[test]
+fn test_encode_decode() {
+    let bytes = encoded_proof();
+    assert!(bytes.starts_with(&PROOF_MAGIC));
+
+    let decoded = VersionedProof::<MyConfig>::decode(&bytes).unwrap();
+    assert_eq!(decoded.format_version, FORMAT_VERSION);
+
+    let (config, perm) = default_config();
+    let machine = machine();
+    let (_, vk) = machine.setup(&config);
+    machine
+        .verify(&config, &mut challenger(&perm), &vk, &decoded.proof, &[])
+        .expect("Decoded proof should verify");
+}
+
+#[test]
+fn test_decode_missing_magic() {
+    let mut bytes = encoded_proof();
+    bytes[0] ^= 1;
+    assert!(matches!(
+        VersionedProof::<MyConfig>::decode(&bytes),
+        Err(ProofDecodeError::MissingMagic)
+    ));
+}
+
+#[test]
+fn test_decode_unsupported_version() {
+    let mut bytes = encoded_proof();
+    bytes[PROOF_MAGIC.len()..PROOF_MAGIC.len() + 4]
+        .copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        VersionedProof::<MyConfig>::decode(&bytes),
+        Err(ProofDecodeError::UnsupportedFormatVersion { found, expected })
+            if found == FORMAT_VERSION + 1 && expected == FORMAT_VERSION
+    ));
+}
+
+#[test]
+fn test_decode_truncated() {
+    let bytes = encoded_proof();
+    for len in [PROOF_MAGIC.len() + 2, bytes.len() / 2] {
+        assert!(matches!(
+            VersionedProof::<MyConfig>::decode(&bytes[..len]),
+            Err(ProofDecodeError::Malformed(_))
+        ));
+    }
+}